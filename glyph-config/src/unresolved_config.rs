@@ -1,4 +1,5 @@
-use glyph_core::config::{Config, KeyMapPreset, StatuslineConfig, StatuslineMode};
+use glyph_core::config::{ClipboardConfig, Config, KeyMapPreset, StatuslineConfig, StatuslineMode};
+use glyph_core::options::DEFAULT_TABSTOP;
 use serde::Deserialize;
 
 use crate::error::{ConfigError, Result};
@@ -7,6 +8,18 @@ use crate::error::{ConfigError, Result};
 pub struct UnresolvedConfig {
     keymap_preset: Option<String>,
     statusline: Option<UnresolvedStatuslineConfig>,
+    autoread: Option<bool>,
+    ignorecase: Option<bool>,
+    smartcase: Option<bool>,
+    tabstop: Option<usize>,
+    expandtab: Option<bool>,
+    shiftwidth: Option<usize>,
+    softtabstop: Option<isize>,
+    wrap: Option<bool>,
+    linebreak: Option<bool>,
+    breakindent: Option<bool>,
+    showbreak: Option<String>,
+    clipboard: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -28,9 +41,33 @@ impl UnresolvedConfig {
             .transpose()?
             .unwrap_or(StatuslineConfig::default());
 
+        let clipboard = self
+            .clipboard
+            .map(parse_clipboard)
+            .transpose()?
+            .unwrap_or_default();
+
+        let tabstop = match self.tabstop {
+            Some(0) => return Err(ConfigError::InvalidOption),
+            Some(tabstop) => tabstop,
+            None => DEFAULT_TABSTOP,
+        };
+
         Ok(Config {
             keymap_preset,
             statusline,
+            autoread: self.autoread.unwrap_or_default(),
+            ignorecase: self.ignorecase.unwrap_or_default(),
+            smartcase: self.smartcase.unwrap_or_default(),
+            tabstop,
+            expandtab: self.expandtab.unwrap_or_default(),
+            shiftwidth: self.shiftwidth.unwrap_or(tabstop),
+            softtabstop: self.softtabstop.unwrap_or_default(),
+            wrap: self.wrap.unwrap_or_default(),
+            linebreak: self.linebreak.unwrap_or_default(),
+            breakindent: self.breakindent.unwrap_or_default(),
+            showbreak: self.showbreak.unwrap_or_default(),
+            clipboard,
         })
     }
 }
@@ -43,6 +80,17 @@ fn parse_keymap_preset<S: AsRef<str>>(s: S) -> Result<KeyMapPreset> {
     }
 }
 
+fn parse_clipboard<S: AsRef<str>>(s: S) -> Result<ClipboardConfig> {
+    match s.as_ref() {
+        "auto" => Ok(ClipboardConfig::Auto),
+        "osc52" => Ok(ClipboardConfig::Osc52),
+        "wl-clipboard" => Ok(ClipboardConfig::WlClipboard),
+        "xclip" => Ok(ClipboardConfig::Xclip),
+        "none" => Ok(ClipboardConfig::None),
+        _ => Err(ConfigError::InvalidOption),
+    }
+}
+
 fn parse_statusline(unresolved: UnresolvedStatuslineConfig) -> Result<StatuslineConfig> {
     let mode = match unresolved.mode.to_lowercase().as_str() {
         "local" => StatuslineMode::Local,
@@ -1,10 +1,32 @@
+mod encoding;
+mod events;
+mod file_io;
+mod line_ending;
+mod loader;
+mod swap;
+
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use encoding::Decoded;
+pub use encoding::Encoding;
+pub use events::{BufferEvent, BufferListener};
+use file_io::FileSnapshot;
+pub use line_ending::LineEnding;
+use loader::{FileLoader, LoadProgress};
+use swap::SwapFile;
+pub use swap::{SwapInfo, default_swap_dir};
 
-use crate::error::Result;
-use crate::geometry::Size;
+use crate::cursor::Cursor;
+use crate::error::{GlyphError, Result};
+use crate::geometry::{Point, Size};
+use crate::history::{History, HistoryStep};
+use crate::options::DEFAULT_TABSTOP;
 use crate::text_object::TextObject;
 
+/// Files from this size on are loaded in the background and opened in large file mode.
+const LARGE_FILE_THRESHOLD: u64 = 64 * 1024 * 1024;
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub enum BufferKind {
     Scratch,
@@ -17,15 +39,66 @@ pub enum FileStatus {
     Existing,
 }
 
+/// How the file on disk compares to what the buffer last read from or wrote to it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum DiskStatus {
+    Unchanged,
+    Changed,
+    Deleted,
+}
+
+/// What was written to disk when saving a buffer.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WriteSummary {
+    pub path: PathBuf,
+    pub lines: usize,
+    pub bytes: usize,
+    pub is_new: bool,
+}
+
+impl std::fmt::Display for WriteSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"{}\" ", self.path.display())?;
+
+        if self.is_new {
+            write!(f, "[New] ")?;
+        }
+
+        write!(f, "{}L, {}B written", self.lines, self.bytes)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Buffer {
     pub id: BufferId,
-    is_dirty: bool,
+    /// The history revision that matches what is on disk. The buffer is dirty whenever its
+    /// current revision is a different one, so undoing back to the saved state makes it clean.
+    /// `None` means no revision matches the disk, like after changing the line ending.
+    saved_revision: Option<usize>,
+    line_ending: LineEnding,
+    encoding: Encoding,
+    has_bom: bool,
+    disk_snapshot: Option<FileSnapshot>,
+    disk_change_reported: bool,
+    read_only: bool,
+    is_large: bool,
+    loader: Option<FileLoader>,
+    swap: Option<SwapFile>,
+    /// A swap file left behind by another session, the user has to decide what to do with it
+    /// before this buffer writes its own.
+    swap_conflict: Option<SwapInfo>,
     content: TextObject,
+    /// How many columns a tab character takes, a copy of the editor's `tabstop` so that anything
+    /// laying out the buffer can reach it.
+    tabstop: usize,
     file_status: FileStatus,
     buffer_kind: BufferKind,
     path: Option<PathBuf>,
     absolute_path: Option<PathBuf>,
+    history: History,
+    cursors_before_change: Option<Vec<Cursor>>,
+    /// Events waiting to be sent to the buffer listeners.
+    events: Vec<BufferEvent>,
 }
 
 impl Buffer {
@@ -43,8 +116,25 @@ impl Buffer {
             file_status,
             buffer_kind,
             absolute_path,
-            is_dirty: false,
+            saved_revision: Some(0),
+            line_ending: LineEnding::default(),
+            encoding: Encoding::default(),
+            has_bom: false,
+            disk_snapshot: None,
+            disk_change_reported: false,
+            read_only: false,
+            is_large: false,
+            loader: None,
+            swap: None,
+            swap_conflict: None,
             content: TextObject::new(content),
+            tabstop: DEFAULT_TABSTOP,
+            history: History::new(),
+            cursors_before_change: None,
+            events: vec![BufferEvent::Opened {
+                buffer_id: id,
+                revision: 0,
+            }],
         }
     }
 
@@ -56,6 +146,36 @@ impl Buffer {
         &mut self.content
     }
 
+    /// Increases every time the content changes, so subsystems can tell whether what they
+    /// computed from it is still up to date.
+    pub fn revision(&self) -> u64 {
+        self.content.revision()
+    }
+
+    /// Queues an event, after the text changes that happened before it.
+    fn push_event(&mut self, event: BufferEvent) {
+        self.collect_text_changes();
+        self.events.push(event);
+    }
+
+    fn collect_text_changes(&mut self) {
+        let changes = self.content.take_unnotified();
+        if changes.is_empty() {
+            return;
+        }
+
+        self.events.push(BufferEvent::TextChanged {
+            buffer_id: self.id,
+            revision: self.content.revision(),
+            changes,
+        });
+    }
+
+    fn take_events(&mut self) -> Vec<BufferEvent> {
+        self.collect_text_changes();
+        std::mem::take(&mut self.events)
+    }
+
     pub fn path(&self) -> Option<&PathBuf> {
         self.path.as_ref()
     }
@@ -63,6 +183,412 @@ impl Buffer {
     pub fn absolute_path(&self) -> Option<&PathBuf> {
         self.absolute_path.as_ref()
     }
+
+    pub fn file_status(&self) -> FileStatus {
+        self.file_status
+    }
+
+    pub fn buffer_kind(&self) -> BufferKind {
+        self.buffer_kind
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.content.has_pending_changes()
+            || Some(self.history.current_revision()) != self.saved_revision
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn tabstop(&self) -> usize {
+        self.tabstop
+    }
+
+    /// The display column `point` is drawn at, with tabs expanded to the next tab stop.
+    pub fn display_column(&self, point: Point<usize>) -> usize {
+        self.content.display_column(point, self.tabstop)
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.line_ending == line_ending {
+            return;
+        }
+
+        self.line_ending = line_ending;
+        self.saved_revision = None;
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        if self.encoding == encoding {
+            return;
+        }
+
+        self.encoding = encoding;
+        self.saved_revision = None;
+    }
+
+    pub fn has_bom(&self) -> bool {
+        self.has_bom
+    }
+
+    pub fn set_bom(&mut self, has_bom: bool) {
+        if self.has_bom == has_bom {
+            return;
+        }
+
+        self.has_bom = has_bom;
+        self.saved_revision = None;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Large files skip features whose cost grows with the file size, like swap files, undo
+    /// history and search highlighting.
+    pub fn is_large(&self) -> bool {
+        self.is_large
+    }
+
+    /// How much of the file was loaded, from 0 to 100, while it is still loading.
+    pub fn loading_progress(&self) -> Option<u8> {
+        self.loader.as_ref().map(FileLoader::percent)
+    }
+
+    /// Starts loading the file in the background, replacing the content with its beginning and
+    /// dropping the undo history.
+    fn start_loading(&mut self, path: &Path) -> Result<()> {
+        let (decoded, line_ending, loader) =
+            FileLoader::open(path).map_err(|source| match source.kind() {
+                std::io::ErrorKind::InvalidData => GlyphError::FailedToDecode(path.to_path_buf()),
+                _ => GlyphError::FailedToRead {
+                    path: path.to_path_buf(),
+                    source,
+                },
+            })?;
+
+        self.collect_text_changes();
+        self.content.reset(decoded.content);
+        self.history = History::new();
+        self.saved_revision = Some(self.history.current_revision());
+        self.cursors_before_change = None;
+        self.line_ending = line_ending;
+        self.encoding = decoded.encoding;
+        self.has_bom = decoded.has_bom;
+        self.is_large = true;
+        self.loader = Some(loader);
+        // the snapshot is taken once the whole file was read
+        self.disk_snapshot = None;
+        self.disk_change_reported = false;
+
+        Ok(())
+    }
+
+    /// Adds whatever the background loader read since the last call to the content.
+    ///
+    /// When the file cannot be read completely, the buffer is made read-only, so writing it
+    /// doesn't truncate the file.
+    pub fn poll_loading(&mut self) -> Result<()> {
+        let Some(progress) = self.loader.as_mut().and_then(FileLoader::poll) else {
+            return Ok(());
+        };
+
+        match progress {
+            LoadProgress::Loading(text) => self.content.append(&text),
+            LoadProgress::Done { text, snapshot } => {
+                self.content.append(&text);
+                self.disk_snapshot = Some(snapshot);
+                self.loader = None;
+            }
+            LoadProgress::Failed { text, reason } => {
+                self.content.append(&text);
+                self.loader = None;
+                self.read_only = true;
+                return Err(GlyphError::FailedToLoad {
+                    name: self.display_name(),
+                    reason,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn swap_conflict(&self) -> Option<&SwapInfo> {
+        self.swap_conflict.as_ref()
+    }
+
+    /// Replaces the content with what was being edited when the swap file was last written. The
+    /// recovered changes are not saved, so the buffer is left modified.
+    pub fn recover_from_swap(&mut self) -> Result<()> {
+        let Some(info) = self.swap_conflict.take() else {
+            return Ok(());
+        };
+
+        let content =
+            swap::recover(&info.swap_path).map_err(|source| GlyphError::FailedToAccessSwap {
+                path: info.swap_path.clone(),
+                source,
+            })?;
+
+        self.content.replace_content(&content);
+        self.commit_changes(&[]);
+
+        // this session takes over the swap file, it gets rewritten on the next flush
+        delete_swap_file(&info)
+    }
+
+    /// Opens the buffer without touching the swap file of the other session, and refuses to write
+    /// the file unless forced.
+    pub fn open_read_only(&mut self) {
+        self.swap_conflict = None;
+        self.swap = None;
+        self.read_only = true;
+    }
+
+    /// Deletes the swap file left by another session.
+    pub fn delete_swap(&mut self) -> Result<()> {
+        match self.swap_conflict.take() {
+            Some(info) => delete_swap_file(&info),
+            None => Ok(()),
+        }
+    }
+
+    /// Persists the journal of a modified buffer to its swap file, and deletes the swap file once
+    /// there is nothing left to recover.
+    fn flush_swap(&mut self) -> std::io::Result<()> {
+        let (Some(swap), Some(path)) = (self.swap.as_mut(), self.absolute_path.as_ref()) else {
+            return Ok(());
+        };
+
+        if self.swap_conflict.is_some() {
+            return Ok(());
+        }
+
+        if !self.content.has_pending_changes()
+            && Some(self.history.current_revision()) == self.saved_revision
+        {
+            return swap.remove();
+        }
+
+        swap.flush(path, &self.content)
+    }
+
+    /// Called whenever the content matches the file on disk again, the journal and the swap file
+    /// start over from there.
+    fn reset_swap(&mut self) {
+        self.content.clear_journal();
+
+        if self.swap_conflict.is_some() {
+            return;
+        }
+
+        if let Some(Err(e)) = self.swap.as_mut().map(SwapFile::remove) {
+            tracing::warn!("failed to remove swap file: {e}");
+        }
+    }
+
+    /// How the buffer is referred to in messages.
+    pub fn display_name(&self) -> String {
+        match self.path.as_ref() {
+            Some(path) => path.display().to_string(),
+            None => String::from("[No Name]"),
+        }
+    }
+
+    /// Compares the file on disk with the content that was last read from or written to it.
+    pub fn disk_status(&mut self) -> DiskStatus {
+        let (Some(path), Some(snapshot)) = (self.path.as_ref(), self.disk_snapshot) else {
+            return DiskStatus::Unchanged;
+        };
+
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return DiskStatus::Deleted,
+            Err(_) => return DiskStatus::Unchanged,
+        };
+
+        if snapshot.matches_metadata(&metadata) {
+            return DiskStatus::Unchanged;
+        }
+
+        match FileSnapshot::read(path) {
+            // the file was touched but its content is the same, like after a `git checkout` that
+            // restored it
+            Ok(current) if current.has_same_content(&snapshot) => {
+                self.disk_snapshot = Some(current);
+                DiskStatus::Unchanged
+            }
+            Ok(_) => DiskStatus::Changed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => DiskStatus::Deleted,
+            Err(_) => DiskStatus::Unchanged,
+        }
+    }
+
+    /// Like [`Buffer::disk_status`], but reports each external change only once, so the user isn't
+    /// warned about the same change every time the editor is focused.
+    pub fn poll_disk_status(&mut self) -> DiskStatus {
+        match self.disk_status() {
+            DiskStatus::Unchanged => DiskStatus::Unchanged,
+            _ if self.disk_change_reported => DiskStatus::Unchanged,
+            status => {
+                self.disk_change_reported = true;
+                status
+            }
+        }
+    }
+
+    /// Replaces the content with what is currently on disk, discarding unsaved changes. The reload
+    /// is recorded in the history, so it can be undone.
+    pub fn reload(&mut self) -> Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Err(GlyphError::NoFileName);
+        };
+
+        if self.is_large {
+            self.start_loading(&path)?;
+            self.push_event(BufferEvent::Reloaded {
+                buffer_id: self.id,
+                revision: self.revision(),
+            });
+            return Ok(());
+        }
+
+        let (decoded, snapshot) = read_file(&path)?;
+        let line_ending = LineEnding::detect(&decoded.content);
+        let content = line_ending.normalize(&decoded.content);
+
+        self.line_ending = line_ending;
+        self.encoding = decoded.encoding;
+        self.has_bom = decoded.has_bom;
+        self.content.replace_content(&content);
+        self.commit_changes(&[]);
+
+        self.file_status = FileStatus::Existing;
+        self.saved_revision = Some(self.history.current_revision());
+        self.disk_snapshot = Some(snapshot);
+        self.disk_change_reported = false;
+        self.reset_swap();
+        self.push_event(BufferEvent::Reloaded {
+            buffer_id: self.id,
+            revision: self.revision(),
+        });
+
+        Ok(())
+    }
+
+    /// Writes the content back to the buffer's path. Unless `force` is set, this fails when the
+    /// file was changed by something else since it was last read or written.
+    pub fn save(&mut self, force: bool) -> Result<WriteSummary> {
+        let Some(path) = self.path.clone() else {
+            return Err(GlyphError::NoFileName);
+        };
+
+        if self.loader.is_some() {
+            return Err(GlyphError::StillLoading(self.display_name()));
+        }
+
+        if !force && self.read_only {
+            return Err(GlyphError::ReadOnly);
+        }
+
+        if !force && self.disk_status() == DiskStatus::Changed {
+            return Err(GlyphError::FileChangedSinceReading(path));
+        }
+
+        let text = String::from_utf8(self.line_ending.restore(self.content.to_bytes()))
+            .expect("content is always valid utf-8");
+        let Some(bytes) = self.encoding.encode(&text, self.has_bom) else {
+            return Err(GlyphError::FailedToEncode {
+                path,
+                encoding: self.encoding,
+            });
+        };
+        file_io::write_atomically(&path, &bytes).map_err(|source| GlyphError::FailedToWrite {
+            path: path.clone(),
+            source,
+        })?;
+
+        let summary = WriteSummary {
+            path,
+            lines: self.content.len_file_lines(),
+            bytes: bytes.len(),
+            is_new: matches!(self.file_status, FileStatus::New),
+        };
+
+        self.file_status = FileStatus::Existing;
+        self.saved_revision = Some(self.history.current_revision());
+        self.disk_snapshot = Some(FileSnapshot::new(&summary.path, &bytes));
+        self.disk_change_reported = false;
+        self.reset_swap();
+        self.push_event(BufferEvent::Saved {
+            buffer_id: self.id,
+            revision: self.revision(),
+            path: summary.path.clone(),
+        });
+
+        Ok(summary)
+    }
+
+    /// Remembers where the cursors were before a group of changes starts, so undoing the group can
+    /// put them back. Calling this while a group is already in progress does nothing.
+    pub fn begin_change(&mut self, cursors: &[Cursor]) {
+        if self.cursors_before_change.is_none() {
+            self.cursors_before_change = Some(cursors.to_vec());
+        }
+    }
+
+    /// Groups every change made to the content since the last commit into a single undo step.
+    /// Large files keep no undo history, their changes only mark the buffer as modified.
+    pub fn commit_changes(&mut self, cursors: &[Cursor]) {
+        let cursors_before = self
+            .cursors_before_change
+            .take()
+            .unwrap_or_else(|| cursors.to_vec());
+
+        if !self.content.has_pending_changes() {
+            return;
+        }
+
+        let changes = self.content.take_changes();
+        if self.is_large {
+            self.saved_revision = None;
+            return;
+        }
+
+        self.history
+            .commit(changes, cursors_before, cursors.to_vec());
+    }
+
+    pub fn undo(&mut self) -> Option<Vec<Cursor>> {
+        let step = self.history.undo()?;
+        Some(self.apply_history_step(step))
+    }
+
+    pub fn redo(&mut self) -> Option<Vec<Cursor>> {
+        let step = self.history.redo()?;
+        Some(self.apply_history_step(step))
+    }
+
+    pub fn earlier(&mut self, steps: usize) -> Option<Vec<Cursor>> {
+        let step = self.history.earlier(steps)?;
+        Some(self.apply_history_step(step))
+    }
+
+    pub fn later(&mut self, steps: usize) -> Option<Vec<Cursor>> {
+        let step = self.history.later(steps)?;
+        Some(self.apply_history_step(step))
+    }
+
+    fn apply_history_step(&mut self, step: HistoryStep) -> Vec<Cursor> {
+        self.content.apply_changes(&step.changes);
+        step.cursors
+    }
 }
 
 #[derive(Debug, Default, Hash, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -102,41 +628,128 @@ impl From<u8> for BufferId {
     }
 }
 
-#[derive(Debug, Default)]
+/// Buffers are numbered from 1 for the user, like in vim.
+impl std::fmt::Display for BufferId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&(self.0 + 1), f)
+    }
+}
+
+#[derive(Debug)]
 pub struct BufferManager {
     next_buffer_id: BufferId,
+    /// Where swap files are written, `None` disables them.
+    swap_dir: Option<PathBuf>,
+    large_file_threshold: u64,
+    /// The `tabstop` given to every buffer.
+    tabstop: usize,
     pub(crate) buffers: BTreeMap<BufferId, Buffer>,
+    listeners: Vec<Box<dyn BufferListener>>,
+    /// Events of buffers that were already removed, waiting to be sent to the listeners.
+    closed_events: Vec<BufferEvent>,
+}
+
+impl Default for BufferManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BufferManager {
     pub fn new() -> Self {
+        Self::with_swap_dir(default_swap_dir())
+    }
+
+    pub fn with_swap_dir(swap_dir: Option<PathBuf>) -> Self {
         Self {
+            swap_dir,
+            large_file_threshold: LARGE_FILE_THRESHOLD,
+            tabstop: DEFAULT_TABSTOP,
             buffers: BTreeMap::new(),
             next_buffer_id: BufferId(0),
+            listeners: vec![],
+            closed_events: vec![],
+        }
+    }
+
+    /// Changes how many columns a tab character takes in every buffer, including the ones opened
+    /// later.
+    pub fn set_tabstop(&mut self, tabstop: usize) {
+        self.tabstop = tabstop;
+        for buffer in self.buffers.values_mut() {
+            buffer.tabstop = tabstop;
+        }
+    }
+
+    /// Opens the file at `path_str`, or returns the buffer that already has it open.
+    pub fn open(&mut self, path_str: &str) -> Result<BufferId> {
+        let absolute_path = std::env::current_dir()?.join(path_str);
+        let canonical = std::fs::canonicalize(&absolute_path).ok();
+
+        let existing = self.buffers.values().find(|buffer| {
+            let Some(path) = buffer.absolute_path.as_ref() else {
+                return false;
+            };
+
+            *path == absolute_path
+                || canonical.is_some() && std::fs::canonicalize(path).ok() == canonical
+        });
+
+        match existing {
+            Some(buffer) => Ok(buffer.id),
+            None => self.load_buffer(path_str),
         }
     }
 
-    pub fn load_buffer(&mut self, path_str: &str) -> Result<()> {
+    pub fn load_buffer(&mut self, path_str: &str) -> Result<BufferId> {
         let cwd = std::env::current_dir()?;
         let path = PathBuf::from(path_str);
         let file_status = if !path.exists() { FileStatus::New } else { FileStatus::Existing };
         let absolute_path = cwd.join(&path);
-        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let is_large = matches!(file_status, FileStatus::Existing)
+            && std::fs::metadata(&path).is_ok_and(|m| m.len() >= self.large_file_threshold);
+
+        let (decoded, disk_snapshot) = match file_status {
+            _ if is_large => (Decoded::default(), None),
+            FileStatus::New => (Decoded::default(), None),
+            FileStatus::Existing => read_file(&path).map(|(d, s)| (d, Some(s)))?,
+        };
+
+        let line_ending = LineEnding::detect(&decoded.content);
+        let content = line_ending.normalize(&decoded.content);
 
         let id = self.next_buffer_id;
         self.next_buffer_id = self.next_buffer_id.next();
 
-        let buffer = Buffer::new(
+        let mut buffer = Buffer::new(
             id,
             content,
-            Some(path),
+            Some(path.clone()),
             Some(absolute_path),
             file_status,
             BufferKind::Regular,
         );
+        buffer.line_ending = line_ending;
+        buffer.encoding = decoded.encoding;
+        buffer.has_bom = decoded.has_bom;
+        buffer.disk_snapshot = disk_snapshot;
+        buffer.tabstop = self.tabstop;
+
+        if is_large {
+            buffer.start_loading(&path)?;
+        }
+
+        let swap_dir = self.swap_dir.as_ref().filter(|_| !is_large);
+        if let (Some(swap_dir), Some(absolute_path)) = (swap_dir, &buffer.absolute_path) {
+            let file_path = std::fs::canonicalize(absolute_path).unwrap_or(absolute_path.clone());
+            let swap = SwapFile::new(swap_dir, &file_path);
+            buffer.swap_conflict = swap::read_info(swap.path()).ok();
+            buffer.swap = Some(swap);
+        }
+
         self.buffers.insert(id, buffer);
 
-        Ok(())
+        Ok(id)
     }
 
     pub fn load_startup_buffer(&mut self, size: Size) -> Result<()> {
@@ -149,8 +762,9 @@ impl BufferManager {
         ];
         let content = vertical_center(&message.join("\n"), size.height);
 
-        let id = BufferId::new(0);
-        let buffer = Buffer::new(
+        let id = self.next_buffer_id;
+        self.next_buffer_id = self.next_buffer_id.next();
+        let mut buffer = Buffer::new(
             id,
             content,
             None,
@@ -158,14 +772,239 @@ impl BufferManager {
             FileStatus::New,
             BufferKind::Scratch,
         );
+        buffer.tabstop = self.tabstop;
         self.buffers.insert(id, buffer);
 
         Ok(())
     }
 
+    /// Creates an empty buffer without a file, used when the last buffer is closed.
+    pub fn create_empty_buffer(&mut self) -> BufferId {
+        let id = self.next_buffer_id;
+        self.next_buffer_id = self.next_buffer_id.next();
+
+        let mut buffer = Buffer::new(
+            id,
+            String::new(),
+            None,
+            None,
+            FileStatus::New,
+            BufferKind::Scratch,
+        );
+        buffer.tabstop = self.tabstop;
+        self.buffers.insert(id, buffer);
+
+        id
+    }
+
+    /// Closes a buffer, deleting its swap file.
+    pub fn remove(&mut self, id: BufferId) -> Option<Buffer> {
+        let mut buffer = self.buffers.remove(&id)?;
+        buffer.reset_swap();
+
+        self.closed_events.extend(buffer.take_events());
+        self.closed_events.push(BufferEvent::Closed {
+            buffer_id: buffer.id,
+        });
+
+        Some(buffer)
+    }
+
+    /// Points a buffer to a different file. The file isn't read, the buffer is written there the
+    /// next time it is saved.
+    pub fn rename(&mut self, id: BufferId, path_str: &str) -> Result<()> {
+        let absolute_path = std::env::current_dir()?.join(path_str);
+        let buffer = self
+            .buffers
+            .get_mut(&id)
+            .ok_or(GlyphError::NoSuchBuffer(id.0 + 1))?;
+
+        buffer.reset_swap();
+        buffer.swap = self
+            .swap_dir
+            .as_ref()
+            .filter(|_| !buffer.is_large)
+            .map(|swap_dir| SwapFile::new(swap_dir, &absolute_path));
+
+        let new_path = PathBuf::from(path_str);
+        let old_path = buffer.path.replace(new_path.clone());
+        buffer.absolute_path = Some(absolute_path);
+        buffer.buffer_kind = BufferKind::Regular;
+        buffer.file_status = FileStatus::New;
+        buffer.disk_snapshot = None;
+        buffer.saved_revision = None;
+        buffer.push_event(BufferEvent::Renamed {
+            buffer_id: id,
+            old_path,
+            new_path,
+        });
+
+        Ok(())
+    }
+
+    /// Registers a listener for the events of every buffer, delivered by
+    /// [`BufferManager::notify_listeners`].
+    pub fn subscribe(&mut self, listener: Box<dyn BufferListener>) {
+        self.listeners.push(listener);
+    }
+
+    /// Sends every event that happened since the last call to the listeners.
+    pub fn notify_listeners(&mut self) {
+        let mut events = std::mem::take(&mut self.closed_events);
+        for buffer in self.buffers.values_mut() {
+            events.extend(buffer.take_events());
+        }
+
+        if events.is_empty() || self.listeners.is_empty() {
+            return;
+        }
+
+        // listeners get to look at the buffers, so they can't be borrowed from the manager while
+        // being notified
+        let mut listeners = std::mem::take(&mut self.listeners);
+        for event in events.iter() {
+            for listener in listeners.iter_mut() {
+                listener.on_buffer_event(event, self);
+            }
+        }
+        self.listeners = listeners;
+    }
+
+    /// Finds a buffer from its number, or from a unique part of its name.
+    pub fn find(&self, query: &str) -> Result<BufferId> {
+        if let Ok(number) = query.parse::<u64>() {
+            return number
+                .checked_sub(1)
+                .map(BufferId::new)
+                .filter(|id| self.buffers.contains_key(id))
+                .ok_or(GlyphError::NoSuchBuffer(number));
+        }
+
+        let names = self
+            .buffers
+            .values()
+            .map(|buffer| (buffer.id, buffer.display_name()))
+            .collect::<Vec<_>>();
+
+        if let Some((id, _)) = names.iter().find(|(_, name)| name == query) {
+            return Ok(*id);
+        }
+
+        let mut matches = names.iter().filter(|(_, name)| name.contains(query));
+        match (matches.next(), matches.next()) {
+            (Some((id, _)), None) => Ok(*id),
+            (Some(_), Some(_)) => Err(GlyphError::MultipleMatchingBuffers(query.to_string())),
+            (None, _) => Err(GlyphError::NoMatchingBuffer(query.to_string())),
+        }
+    }
+
+    /// The buffer after `id` in the buffer list, wrapping around at the end.
+    pub fn next_buffer(&self, id: BufferId) -> BufferId {
+        self.buffers
+            .range(id.next()..)
+            .chain(self.buffers.iter())
+            .map(|(id, _)| *id)
+            .next()
+            .unwrap_or(id)
+    }
+
+    /// The buffer before `id` in the buffer list, wrapping around at the start.
+    pub fn prev_buffer(&self, id: BufferId) -> BufferId {
+        self.buffers
+            .range(..id)
+            .rev()
+            .chain(self.buffers.iter().rev())
+            .map(|(id, _)| *id)
+            .next()
+            .unwrap_or(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Buffer> {
+        self.buffers.values()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Buffer> {
+        self.buffers.values_mut()
+    }
+
     pub fn get(&self, id: BufferId) -> Option<&Buffer> {
         self.buffers.get(&id)
     }
+
+    pub fn get_mut(&mut self, id: BufferId) -> Option<&mut Buffer> {
+        self.buffers.get_mut(&id)
+    }
+
+    /// The first buffer with a swap file left behind by another session.
+    pub fn next_swap_conflict(&self) -> Option<&Buffer> {
+        self.buffers.values().find(|b| b.swap_conflict.is_some())
+    }
+
+    /// Adds the content loaded in the background to the buffers of large files, returning the
+    /// first error found.
+    pub fn poll_loaders(&mut self) -> Result<()> {
+        let mut first_error = None;
+
+        for buffer in self.buffers.values_mut() {
+            if let Err(e) = buffer.poll_loading() {
+                _ = first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    pub fn flush_swap_files(&mut self) {
+        for buffer in self.buffers.values_mut() {
+            if let Err(e) = buffer.flush_swap() {
+                tracing::warn!("failed to write swap file: {e}");
+            }
+        }
+    }
+
+    /// Deletes the swap files of every buffer, called when the editor exits normally.
+    pub fn remove_swap_files(&mut self) {
+        for buffer in self.buffers.values_mut() {
+            buffer.reset_swap();
+        }
+    }
+}
+
+/// Every swap file left behind by sessions that didn't exit cleanly.
+pub fn recoverable_files() -> Result<Vec<SwapInfo>> {
+    let Some(swap_dir) = default_swap_dir() else {
+        return Ok(vec![]);
+    };
+
+    swap::list(&swap_dir).map_err(|source| GlyphError::FailedToAccessSwap {
+        path: swap_dir,
+        source,
+    })
+}
+
+fn delete_swap_file(info: &SwapInfo) -> Result<()> {
+    match std::fs::remove_file(&info.swap_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(GlyphError::FailedToAccessSwap {
+            path: info.swap_path.clone(),
+            source: e,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn read_file(path: &Path) -> Result<(Decoded, FileSnapshot)> {
+    let bytes = std::fs::read(path).map_err(|source| GlyphError::FailedToRead {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let decoded =
+        Encoding::decode(&bytes).ok_or_else(|| GlyphError::FailedToDecode(path.to_path_buf()))?;
+
+    Ok((decoded, FileSnapshot::new(path, &bytes)))
 }
 
 fn vertical_center(s: &str, height: u16) -> String {
@@ -197,3 +1036,279 @@ fn pad_top(s: &str, pad: usize) -> String {
     result.push_str(s);
     result
 }
+
+/// A scratch buffer holding `content`, for tests.
+#[cfg(test)]
+pub(crate) fn scratch_buffer(content: &str) -> Buffer {
+    Buffer::new(
+        BufferId::new(0),
+        content.into(),
+        None,
+        None,
+        FileStatus::New,
+        BufferKind::Scratch,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_object::{Change, Transaction};
+
+    #[test]
+    fn test_dirty_follows_history() {
+        let mut buffer = scratch_buffer("abc");
+        assert!(!buffer.is_dirty());
+
+        buffer
+            .content_mut()
+            .apply(Transaction::new().insert(0, "x"));
+        assert!(buffer.is_dirty());
+
+        buffer.commit_changes(&[]);
+        assert!(buffer.is_dirty());
+
+        // undoing back to the unmodified revision makes the buffer clean again
+        buffer.undo();
+        assert!(!buffer.is_dirty());
+
+        buffer.redo();
+        assert!(buffer.is_dirty());
+    }
+
+    #[test]
+    fn test_large_buffers_keep_no_history() {
+        let mut buffer = scratch_buffer("abc");
+        buffer.is_large = true;
+
+        buffer
+            .content_mut()
+            .apply(Transaction::new().insert(0, "x"));
+        buffer.commit_changes(&[]);
+        assert!(buffer.is_dirty());
+
+        assert!(buffer.undo().is_none());
+        assert_eq!(buffer.content().to_bytes(), b"xabc");
+    }
+
+    #[test]
+    fn test_external_changes_are_detected_and_reloaded() {
+        let dir = std::env::temp_dir().join(format!("glyph-disk-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "one\n").unwrap();
+
+        let mut buffers = BufferManager::with_swap_dir(None);
+        buffers.load_buffer(path.to_str().unwrap()).unwrap();
+        let buffer = buffers.get_mut(BufferId::new(0)).unwrap();
+        assert_eq!(buffer.disk_status(), DiskStatus::Unchanged);
+
+        std::fs::write(&path, "two lines\nhere\n").unwrap();
+        assert_eq!(buffer.disk_status(), DiskStatus::Changed);
+        assert!(matches!(
+            buffer.save(false),
+            Err(GlyphError::FileChangedSinceReading(_))
+        ));
+
+        // each change is only reported once
+        assert_eq!(buffer.poll_disk_status(), DiskStatus::Changed);
+        assert_eq!(buffer.poll_disk_status(), DiskStatus::Unchanged);
+
+        buffer.reload().unwrap();
+        assert_eq!(buffer.content().to_bytes(), b"two lines\nhere\n");
+        assert!(!buffer.is_dirty());
+        assert_eq!(buffer.disk_status(), DiskStatus::Unchanged);
+
+        // the reload can be undone like any other change
+        buffer.undo();
+        assert_eq!(buffer.content().to_bytes(), b"one\n");
+        assert!(buffer.is_dirty());
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(buffer.disk_status(), DiskStatus::Deleted);
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recover_from_swap_after_crash() {
+        let dir = std::env::temp_dir().join(format!("glyph-recover-{}", std::process::id()));
+        let swap_dir = dir.join("swap");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "abc\n").unwrap();
+
+        let mut buffers = BufferManager::with_swap_dir(Some(swap_dir.clone()));
+        buffers.load_buffer(path.to_str().unwrap()).unwrap();
+        let buffer = buffers.get_mut(BufferId::new(0)).unwrap();
+        buffer
+            .content_mut()
+            .apply(Transaction::new().insert(0, "x"));
+        buffers.flush_swap_files();
+
+        // the editor crashed, so the swap file is still there when the file is opened again
+        let mut buffers = BufferManager::with_swap_dir(Some(swap_dir));
+        buffers.load_buffer(path.to_str().unwrap()).unwrap();
+        let buffer = buffers.get_mut(BufferId::new(0)).unwrap();
+        assert!(buffer.swap_conflict().is_some());
+
+        buffer.recover_from_swap().unwrap();
+        assert_eq!(buffer.content().to_bytes(), b"xabc\n");
+        assert!(buffer.is_dirty());
+        assert!(buffer.swap_conflict().is_none());
+
+        // once saved there is nothing left to recover
+        buffer.save(false).unwrap();
+        buffers.flush_swap_files();
+        assert!(swap::list(&dir.join("swap")).unwrap().is_empty());
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_buffer_list() {
+        let dir = std::env::temp_dir().join(format!("glyph-buffer-list-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.txt");
+        let second = dir.join("second.txt");
+        std::fs::write(&first, "abc\n").unwrap();
+        std::fs::write(&second, "def\n").unwrap();
+
+        let mut buffers = BufferManager::with_swap_dir(None);
+        let first_id = buffers.open(first.to_str().unwrap()).unwrap();
+        let second_id = buffers.open(second.to_str().unwrap()).unwrap();
+
+        // opening a file twice reuses its buffer
+        assert_eq!(buffers.open(first.to_str().unwrap()).unwrap(), first_id);
+
+        assert_eq!(buffers.find("2").unwrap(), second_id);
+        assert_eq!(buffers.find("first").unwrap(), first_id);
+        assert!(matches!(
+            buffers.find("3"),
+            Err(GlyphError::NoSuchBuffer(3))
+        ));
+        assert!(matches!(
+            buffers.find(".txt"),
+            Err(GlyphError::MultipleMatchingBuffers(_))
+        ));
+
+        assert_eq!(buffers.next_buffer(second_id), first_id);
+        assert_eq!(buffers.prev_buffer(first_id), second_id);
+
+        buffers.remove(first_id);
+        assert_eq!(buffers.next_buffer(second_id), second_id);
+        assert!(matches!(
+            buffers.find("first"),
+            Err(GlyphError::NoMatchingBuffer(_))
+        ));
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[derive(Debug, Default)]
+    struct RecordingListener {
+        events: std::rc::Rc<std::cell::RefCell<Vec<BufferEvent>>>,
+    }
+
+    impl BufferListener for RecordingListener {
+        fn on_buffer_event(&mut self, event: &BufferEvent, _: &BufferManager) {
+            self.events.borrow_mut().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_listeners_follow_buffer_events() {
+        let dir = std::env::temp_dir().join(format!("glyph-events-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "abc\n").unwrap();
+
+        let listener = RecordingListener::default();
+        let events = listener.events.clone();
+        let mut buffers = BufferManager::with_swap_dir(None);
+        buffers.subscribe(Box::new(listener));
+
+        let id = buffers.open(path.to_str().unwrap()).unwrap();
+        let buffer = buffers.get_mut(id).unwrap();
+        buffer
+            .content_mut()
+            .apply(Transaction::new().insert(0, "x"));
+        buffer.commit_changes(&[]);
+        buffer.save(false).unwrap();
+        let revision = buffer.revision();
+        buffers.notify_listeners();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                BufferEvent::Opened {
+                    buffer_id: id,
+                    revision: 0,
+                },
+                BufferEvent::TextChanged {
+                    buffer_id: id,
+                    revision,
+                    changes: vec![Change::Insert {
+                        char_idx: 0,
+                        text: "x".into(),
+                    }],
+                },
+                BufferEvent::Saved {
+                    buffer_id: id,
+                    revision,
+                    path: path.clone(),
+                },
+            ]
+        );
+
+        // undoing is a text change like any other, delivered before the buffer is closed
+        events.borrow_mut().clear();
+        buffers.get_mut(id).unwrap().undo();
+        buffers.remove(id);
+        buffers.notify_listeners();
+
+        let events = events.borrow();
+        assert!(matches!(events[0], BufferEvent::TextChanged { revision: r, .. } if r > revision));
+        assert_eq!(events[1], BufferEvent::Closed { buffer_id: id });
+        assert_eq!(events.len(), 2);
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rename_points_to_another_file() {
+        let dir = std::env::temp_dir().join(format!("glyph-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("file.txt");
+        let new_path = dir.join("renamed.txt");
+        std::fs::write(&path, "abc\n").unwrap();
+
+        let listener = RecordingListener::default();
+        let events = listener.events.clone();
+        let mut buffers = BufferManager::with_swap_dir(None);
+        buffers.subscribe(Box::new(listener));
+
+        let id = buffers.open(path.to_str().unwrap()).unwrap();
+        buffers.rename(id, new_path.to_str().unwrap()).unwrap();
+        buffers.notify_listeners();
+
+        assert_eq!(
+            events.borrow().last(),
+            Some(&BufferEvent::Renamed {
+                buffer_id: id,
+                old_path: Some(path.clone()),
+                new_path: new_path.clone(),
+            })
+        );
+
+        // the new file doesn't have the content yet, until the buffer is written there
+        let buffer = buffers.get_mut(id).unwrap();
+        assert!(buffer.is_dirty());
+        buffer.save(false).unwrap();
+        assert_eq!(std::fs::read_to_string(&new_path).unwrap(), "abc\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "abc\n");
+        assert!(!buffer.is_dirty());
+
+        assert!(matches!(
+            buffers.rename(BufferId::new(7), "other.txt"),
+            Err(GlyphError::NoSuchBuffer(8))
+        ));
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,229 @@
+use std::str::FromStr;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// The character encoding of a file on disk.
+///
+/// Buffers always hold UTF-8 internally, the encoding is only used when reading and writing the
+/// file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Windows-1252, a superset of ISO-8859-1 which decodes any sequence of bytes. Used as the
+    /// fallback for files that aren't valid in any other encoding.
+    Latin1,
+}
+
+/// The result of decoding a file.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decoded {
+    pub content: String,
+    pub encoding: Encoding,
+    pub has_bom: bool,
+}
+
+impl Encoding {
+    /// Detects the encoding of `bytes` and decodes them.
+    ///
+    /// A byte order mark always wins, then UTF-16 without a byte order mark is guessed from the
+    /// amount of zero bytes, which is very high on mostly ASCII text, then valid UTF-8 is assumed.
+    /// Anything else is decoded as [`Encoding::Latin1`]. Returns `None` when the content is
+    /// malformed for the detected encoding, like UTF-16 with an odd number of bytes.
+    pub fn decode(bytes: &[u8]) -> Option<Decoded> {
+        let (encoding, bom_len) = Self::detect(bytes, true);
+        let content = encoding.decode_without_bom(&bytes[bom_len..])?;
+        let has_bom = bom_len > 0;
+
+        Some(Decoded {
+            content,
+            encoding,
+            has_bom,
+        })
+    }
+
+    /// Detects the encoding of `bytes` and the length of its byte order mark. When the bytes are
+    /// only the start of a file, `complete` is false, and a UTF-8 character cut at the end doesn't
+    /// rule UTF-8 out.
+    pub(crate) fn detect(bytes: &[u8], complete: bool) -> (Self, usize) {
+        if bytes.starts_with(UTF8_BOM) {
+            return (Self::Utf8, UTF8_BOM.len());
+        }
+
+        if bytes.starts_with(UTF16_LE_BOM) {
+            return (Self::Utf16Le, UTF16_LE_BOM.len());
+        }
+
+        if bytes.starts_with(UTF16_BE_BOM) {
+            return (Self::Utf16Be, UTF16_BE_BOM.len());
+        }
+
+        (Self::guess(bytes, complete), 0)
+    }
+
+    fn guess(bytes: &[u8], complete: bool) -> Self {
+        // zero bytes are valid UTF-8, so UTF-16 has to be ruled out first
+        if bytes.len().is_multiple_of(2) {
+            let pairs = bytes.len() / 2;
+            let even_zeros = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+            let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+            // ASCII text in UTF-16 has a zero on every high byte
+            if odd_zeros * 2 > pairs && even_zeros == 0 {
+                return Self::Utf16Le;
+            }
+
+            if even_zeros * 2 > pairs && odd_zeros == 0 {
+                return Self::Utf16Be;
+            }
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(_) => Self::Utf8,
+            Err(e) if !complete && e.error_len().is_none() => Self::Utf8,
+            Err(_) => Self::Latin1,
+        }
+    }
+
+    /// A decoder for reading a file in chunks, the byte order mark has to be skipped beforehand.
+    pub(crate) fn decoder(self) -> encoding_rs::Decoder {
+        let encoding = match self {
+            Self::Utf8 => encoding_rs::UTF_8,
+            Self::Utf16Le => encoding_rs::UTF_16LE,
+            Self::Utf16Be => encoding_rs::UTF_16BE,
+            Self::Latin1 => encoding_rs::WINDOWS_1252,
+        };
+
+        encoding.new_decoder_without_bom_handling()
+    }
+
+    fn decode_without_bom(self, bytes: &[u8]) -> Option<String> {
+        let encoding = match self {
+            Self::Utf8 => return String::from_utf8(bytes.to_vec()).ok(),
+            Self::Utf16Le => encoding_rs::UTF_16LE,
+            Self::Utf16Be => encoding_rs::UTF_16BE,
+            Self::Latin1 => encoding_rs::WINDOWS_1252,
+        };
+
+        encoding
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(|content| content.into_owned())
+    }
+
+    /// Encodes UTF-8 `text` into this encoding. Returns `None` when the text has characters that
+    /// cannot be represented in it.
+    pub fn encode(self, text: &str, with_bom: bool) -> Option<Vec<u8>> {
+        let mut bytes = vec![];
+
+        match self {
+            Self::Utf8 => {
+                if with_bom {
+                    bytes.extend_from_slice(UTF8_BOM);
+                }
+                bytes.extend_from_slice(text.as_bytes());
+            }
+            // encoding_rs can only decode UTF-16, so encoding is done by hand
+            Self::Utf16Le => {
+                if with_bom {
+                    bytes.extend_from_slice(UTF16_LE_BOM);
+                }
+                bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            }
+            Self::Utf16Be => {
+                if with_bom {
+                    bytes.extend_from_slice(UTF16_BE_BOM);
+                }
+                bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+            }
+            Self::Latin1 => {
+                let (encoded, _, had_unmappable) = encoding_rs::WINDOWS_1252.encode(text);
+                if had_unmappable {
+                    return None;
+                }
+                bytes.extend_from_slice(&encoded);
+            }
+        }
+
+        Some(bytes)
+    }
+
+    /// The name of the encoding as used by vim's `fileencoding` option.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16",
+            Self::Latin1 => "latin1",
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "utf-16le" | "utf16le" => Ok(Self::Utf16Le),
+            "utf-16" | "utf16" | "utf-16be" | "utf16be" => Ok(Self::Utf16Be),
+            "latin1" | "iso-8859-1" | "cp1252" | "windows-1252" => Ok(Self::Latin1),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        let text = "olá, café";
+        let cases = [
+            (Encoding::Utf8, false),
+            (Encoding::Utf8, true),
+            (Encoding::Utf16Le, true),
+            (Encoding::Utf16Be, true),
+            (Encoding::Utf16Le, false),
+            (Encoding::Utf16Be, false),
+            (Encoding::Latin1, false),
+        ];
+
+        for (encoding, has_bom) in cases {
+            let bytes = encoding.encode(text, has_bom).unwrap();
+            let decoded = Encoding::decode(&bytes).unwrap();
+
+            assert_eq!(decoded.content, text, "{encoding} {has_bom}");
+            assert_eq!(decoded.encoding, encoding, "{encoding} {has_bom}");
+            assert_eq!(decoded.has_bom, has_bom, "{encoding} {has_bom}");
+        }
+    }
+
+    #[test]
+    fn test_latin1_fallback_keeps_every_byte() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+        let decoded = Encoding::decode(&bytes).unwrap();
+        assert_eq!(decoded.encoding, Encoding::Latin1);
+        assert_eq!(
+            Encoding::Latin1.encode(&decoded.content, false).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn test_malformed_and_unmappable() {
+        // a byte order mark followed by an odd number of bytes
+        assert!(Encoding::decode(&[0xFF, 0xFE, b'a']).is_none());
+        assert!(Encoding::Latin1.encode("日本", false).is_none());
+    }
+}
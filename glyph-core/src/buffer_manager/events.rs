@@ -0,0 +1,68 @@
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+use super::{BufferId, BufferManager};
+use crate::text_object::Change;
+
+/// Something that happened to a buffer, sent to every [`BufferListener`]. The events of a buffer
+/// are always delivered in the order they happened.
+///
+/// Revisions are the [`Buffer::revision`] the buffer was at right after the event.
+///
+/// [`Buffer::revision`]: super::Buffer::revision
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BufferEvent {
+    Opened {
+        buffer_id: BufferId,
+        revision: u64,
+    },
+    /// The content was edited. Changes are in the order they were applied, each one with offsets
+    /// into the content as it was right before it.
+    TextChanged {
+        buffer_id: BufferId,
+        revision: u64,
+        changes: Vec<Change>,
+    },
+    Saved {
+        buffer_id: BufferId,
+        revision: u64,
+        path: PathBuf,
+    },
+    /// The content was read again from disk, after the text changes that made the content match
+    /// the file. Large files are loaded from scratch instead, so listeners have to start over
+    /// from their content.
+    Reloaded {
+        buffer_id: BufferId,
+        revision: u64,
+    },
+    Renamed {
+        buffer_id: BufferId,
+        old_path: Option<PathBuf>,
+        new_path: PathBuf,
+    },
+    /// The buffer was removed from the buffer list, it can't be looked up anymore.
+    Closed {
+        buffer_id: BufferId,
+    },
+}
+
+impl BufferEvent {
+    pub fn buffer_id(&self) -> BufferId {
+        match self {
+            Self::Opened { buffer_id, .. }
+            | Self::TextChanged { buffer_id, .. }
+            | Self::Saved { buffer_id, .. }
+            | Self::Reloaded { buffer_id, .. }
+            | Self::Renamed { buffer_id, .. }
+            | Self::Closed { buffer_id } => *buffer_id,
+        }
+    }
+}
+
+/// A subsystem that follows what happens to buffers, like highlighting or a git gutter, so it can
+/// update what changed instead of scanning every buffer again.
+pub trait BufferListener: Debug {
+    /// Called once per event, with the buffers as they are after every event that is being
+    /// delivered.
+    fn on_buffer_event(&mut self, event: &BufferEvent, buffers: &BufferManager);
+}
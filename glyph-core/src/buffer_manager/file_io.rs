@@ -0,0 +1,150 @@
+use std::fs::{Metadata, OpenOptions};
+use std::hash::{DefaultHasher, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// What a file looked like the last time it was read or written, used to notice when something
+/// else changes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileSnapshot {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl FileSnapshot {
+    /// Snapshots `path`, whose content is `bytes`.
+    pub fn new(path: &Path, bytes: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(bytes);
+        Self::from_hasher(path, bytes.len() as u64, &hasher)
+    }
+
+    /// Snapshots `path` from a hasher that was fed its `len` bytes, for files read in chunks.
+    pub fn from_hasher(path: &Path, len: u64, hasher: &DefaultHasher) -> Self {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        Self {
+            modified,
+            len,
+            hash: hasher.finish(),
+        }
+    }
+
+    pub fn read(path: &Path) -> std::io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Ok(Self::new(path, &bytes))
+    }
+
+    /// Whether the file metadata still matches the snapshot, in which case the file is assumed to
+    /// be unchanged without reading it.
+    pub fn matches_metadata(&self, metadata: &Metadata) -> bool {
+        self.len == metadata.len() && self.modified == metadata.modified().ok()
+    }
+
+    pub fn has_same_content(&self, other: &Self) -> bool {
+        self.len == other.len && self.hash == other.hash
+    }
+}
+
+/// Writes `bytes` to `path` without ever leaving a partially written file behind.
+///
+/// The content is written to a temporary file next to the destination, which then replaces it
+/// with a rename. Symlinks are followed so the file they point to is the one replaced, and the
+/// permissions of the original file are carried over to the new one.
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let target = resolve_symlinks(path)?;
+    let permissions = std::fs::metadata(&target).ok().map(|m| m.permissions());
+    let temp_path = temp_path_for(&target);
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)?;
+
+        file.write_all(bytes)?;
+        file.sync_all()?;
+
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+
+        std::fs::rename(&temp_path, &target)
+    })();
+
+    if result.is_err() {
+        _ = std::fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+fn resolve_symlinks(path: &Path) -> std::io::Result<PathBuf> {
+    match std::fs::canonicalize(path) {
+        Ok(path) => Ok(path),
+        // the file doesn't exist yet, so there are no links to follow
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(path.to_path_buf()),
+        Err(e) => Err(e),
+    }
+}
+
+fn temp_path_for(target: &Path) -> PathBuf {
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    target.with_file_name(format!(".{file_name}.{}.glyph-tmp", std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("glyph-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_write_new_and_existing_file() {
+        let dir = temp_dir("write");
+        let path = dir.join("file.txt");
+
+        write_atomically(&path, b"hello").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+
+        write_atomically(&path, b"world").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"world");
+
+        // no temporary file is left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_permissions_and_follows_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("symlink");
+        let path = dir.join("file.sh");
+        let link = dir.join("link.sh");
+
+        std::fs::write(&path, "old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o750)).unwrap();
+        std::os::unix::fs::symlink(&path, &link).unwrap();
+
+        write_atomically(&link, b"new").unwrap();
+
+        assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
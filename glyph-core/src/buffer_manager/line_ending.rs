@@ -0,0 +1,190 @@
+use std::str::FromStr;
+
+/// The line terminator used by a file on disk.
+///
+/// Buffers always use `\n` internally, the line ending is only used when reading and writing the
+/// file, so every piece of code that deals with content only has to care about a single kind of
+/// line break.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl LineEnding {
+    /// Finds the line ending of `text`, like vim's `fileformats`: [`LineEnding::Crlf`] when every
+    /// `\n` follows a `\r`, [`LineEnding::Cr`] when there is no `\n` at all, and
+    /// [`LineEnding::Lf`] otherwise. Text that mixes line endings is taken as [`LineEnding::Lf`],
+    /// so it's written back unchanged.
+    pub fn detect(text: &str) -> Self {
+        let mut lf = 0;
+        let mut crlf = 0;
+        let mut cr = 0;
+
+        let bytes = text.as_bytes();
+        let mut idx = 0;
+        while idx < bytes.len() {
+            match bytes[idx] {
+                b'\r' if bytes.get(idx + 1) == Some(&b'\n') => {
+                    crlf += 1;
+                    idx += 1;
+                }
+                b'\r' => cr += 1,
+                b'\n' => lf += 1,
+                _ => {}
+            }
+            idx += 1;
+        }
+
+        match (lf, crlf, cr) {
+            (0, 0, 0) => Self::Lf,
+            (0, 0, _) => Self::Cr,
+            (0, _, _) => Self::Crlf,
+            _ => Self::Lf,
+        }
+    }
+
+    /// Whether every line break of `text` is of this kind, a lone `\r` aside in a
+    /// [`LineEnding::Crlf`] file. Only then does [`LineEnding::restore`] give back the text
+    /// [`LineEnding::normalize`] was given.
+    pub fn is_used_throughout(self, text: &str) -> bool {
+        match self {
+            Self::Lf => true,
+            Self::Crlf => text
+                .match_indices('\n')
+                .all(|(idx, _)| text[..idx].ends_with('\r')),
+            Self::Cr => !text.contains('\n'),
+        }
+    }
+
+    /// Replaces the line breaks of this kind in `text` by `\n`. Any other `\r`, and a `\r\n` in
+    /// a [`LineEnding::Cr`] file, is kept as it is.
+    pub fn normalize(self, text: &str) -> String {
+        match self {
+            Self::Lf => text.to_string(),
+            Self::Crlf => text.replace("\r\n", "\n"),
+            Self::Cr => {
+                let mut normalized = String::with_capacity(text.len());
+                let mut chars = text.chars().peekable();
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '\r' if chars.peek() != Some(&'\n') => normalized.push('\n'),
+                        ch => normalized.push(ch),
+                    }
+                }
+                normalized
+            }
+        }
+    }
+
+    /// Turns `\n` line breaks of normalized content into this line ending.
+    pub fn restore(self, bytes: Vec<u8>) -> Vec<u8> {
+        if matches!(self, Self::Lf) {
+            return bytes;
+        }
+
+        let mut restored = Vec::with_capacity(bytes.len());
+        for byte in bytes {
+            match byte {
+                b'\n' => restored.extend_from_slice(self.as_str().as_bytes()),
+                _ => restored.push(byte),
+            }
+        }
+
+        restored
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
+
+    /// The name of the line ending as used by vim's `fileformat` option.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lf => "unix",
+            Self::Crlf => "dos",
+            Self::Cr => "mac",
+        }
+    }
+}
+
+impl FromStr for LineEnding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unix" => Ok(Self::Lf),
+            "dos" => Ok(Self::Crlf),
+            "mac" => Ok(Self::Cr),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(LineEnding::detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\rb\r"), LineEnding::Cr);
+        assert_eq!(LineEnding::detect("a\r\nb\rc\r\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::Lf);
+        // the only `\n` follows a `\r`, so the lone `\r`s are kept inside the lines
+        assert_eq!(LineEnding::detect("a\rb\rc\r\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("no line breaks"), LineEnding::Lf);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for original in ["a\r\nb\r\n", "a\rb\r", "a\nb\n"] {
+            let line_ending = LineEnding::detect(original);
+            let normalized = line_ending.normalize(original);
+            assert_eq!(normalized, "a\nb\n");
+
+            let restored = line_ending.restore(normalized.into_bytes());
+            assert_eq!(restored, original.as_bytes());
+        }
+
+        // files that mix line endings are written back byte for byte
+        for original in ["a\rb\rc\r\n", "a\r\nb\r\nc\n", "a\rb\nc\r", "a\r\nb\rc\n"] {
+            let line_ending = LineEnding::detect(original);
+            assert!(line_ending.is_used_throughout(original));
+
+            let restored = line_ending.restore(line_ending.normalize(original).into_bytes());
+            assert_eq!(restored, original.as_bytes());
+        }
+
+        // a mac file with a `\r\n` doesn't round trip, but the `\r\n` isn't split in two lines
+        assert_eq!(LineEnding::Cr.normalize("a\rb\r\n"), "a\nb\r\n");
+        assert!(!LineEnding::Cr.is_used_throughout("a\rb\r\n"));
+        assert!(!LineEnding::Crlf.is_used_throughout("a\r\nb\n"));
+    }
+
+    #[test]
+    fn test_round_trip_keeps_bare_carriage_returns() {
+        for (original, expected) in [("a\rb\nc\n", "a\rb\nc\n"), ("a\r\nb\rc\r\n", "a\nb\rc\n")] {
+            let line_ending = LineEnding::detect(original);
+            let normalized = line_ending.normalize(original);
+            assert_eq!(normalized, expected);
+
+            let restored = line_ending.restore(normalized.into_bytes());
+            assert_eq!(restored, original.as_bytes());
+        }
+    }
+}
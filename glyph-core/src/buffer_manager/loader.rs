@@ -0,0 +1,350 @@
+use std::fs::File;
+use std::hash::{DefaultHasher, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+
+use encoding_rs::{Decoder, DecoderResult};
+
+use super::encoding::{Decoded, Encoding};
+use super::file_io::FileSnapshot;
+use super::line_ending::LineEnding;
+
+/// How much of the file is read before the first frame, enough for a few screenfuls.
+const FIRST_CHUNK_SIZE: usize = 256 * 1024;
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// How many chunks are added to the buffer per frame, so the editor stays responsive while the
+/// rest of the file streams in.
+const CHUNKS_PER_POLL: usize = 16;
+
+enum LoadEvent {
+    Chunk { text: String, bytes_read: u64 },
+    Done(FileSnapshot),
+    Failed(String),
+}
+
+/// What happened since the last time the loader was polled.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LoadProgress {
+    Loading(String),
+    Done {
+        text: String,
+        snapshot: FileSnapshot,
+    },
+    Failed {
+        text: String,
+        reason: String,
+    },
+}
+
+/// Reads a large file in the background, so the editor can show its beginning right away instead
+/// of waiting for the whole file.
+#[derive(Debug)]
+pub struct FileLoader {
+    receiver: Receiver<LoadEvent>,
+    total_bytes: u64,
+    bytes_read: u64,
+}
+
+// buffers are comparable, a loader is compared by how far it got
+impl PartialEq for FileLoader {
+    fn eq(&self, other: &Self) -> bool {
+        self.total_bytes == other.total_bytes && self.bytes_read == other.bytes_read
+    }
+}
+
+impl Eq for FileLoader {}
+
+impl PartialOrd for FileLoader {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FileLoader {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.total_bytes, self.bytes_read).cmp(&(other.total_bytes, other.bytes_read))
+    }
+}
+
+impl FileLoader {
+    /// Reads and decodes the start of the file at `path`, and keeps reading the rest on a
+    /// background thread. The returned content already has its line endings normalized.
+    pub fn open(path: &Path) -> std::io::Result<(Decoded, LineEnding, Self)> {
+        let mut file = File::open(path)?;
+        let total_bytes = file.metadata()?.len();
+
+        let mut bytes = vec![0; FIRST_CHUNK_SIZE];
+        let len = read_chunk(&mut file, &mut bytes)?;
+        bytes.truncate(len);
+
+        let complete = (len as u64) == total_bytes;
+        let (encoding, bom_len) = Encoding::detect(&bytes, complete);
+        let mut decoder = encoding.decoder();
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&bytes);
+
+        let text = decode(&mut decoder, &bytes[bom_len..], complete).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed content")
+        })?;
+        let line_ending = LineEnding::detect(&text);
+        let mut normalizer = Normalizer::new(line_ending);
+        let content = normalizer
+            .push(&text, complete)
+            .expect("the line ending was detected from the same text");
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let state = ReaderState {
+            path: path.to_path_buf(),
+            file,
+            decoder,
+            normalizer,
+            hasher,
+            bytes_read: len as u64,
+        };
+
+        match complete {
+            true => _ = sender.send(LoadEvent::Done(state.snapshot())),
+            false => _ = std::thread::spawn(move || state.read_rest(sender)),
+        }
+
+        let decoded = Decoded {
+            content,
+            encoding,
+            has_bom: bom_len > 0,
+        };
+
+        let loader = Self {
+            receiver,
+            total_bytes,
+            bytes_read: len as u64,
+        };
+
+        Ok((decoded, line_ending, loader))
+    }
+
+    /// Takes what was read since the last poll.
+    pub fn poll(&mut self) -> Option<LoadProgress> {
+        let mut text = String::new();
+
+        for _ in 0..CHUNKS_PER_POLL {
+            match self.receiver.try_recv() {
+                Ok(LoadEvent::Chunk {
+                    text: chunk,
+                    bytes_read,
+                }) => {
+                    text.push_str(&chunk);
+                    self.bytes_read = bytes_read;
+                }
+                Ok(LoadEvent::Done(snapshot)) => {
+                    self.bytes_read = self.total_bytes;
+                    return Some(LoadProgress::Done { text, snapshot });
+                }
+                Ok(LoadEvent::Failed(reason)) => {
+                    return Some(LoadProgress::Failed { text, reason });
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let reason = String::from("the file stopped being read");
+                    return Some(LoadProgress::Failed { text, reason });
+                }
+            }
+        }
+
+        match text.is_empty() {
+            true => None,
+            false => Some(LoadProgress::Loading(text)),
+        }
+    }
+
+    /// How much of the file was read, from 0 to 100.
+    pub fn percent(&self) -> u8 {
+        match self.total_bytes {
+            0 => 100,
+            total => (self.bytes_read * 100 / total) as u8,
+        }
+    }
+}
+
+struct ReaderState {
+    path: PathBuf,
+    file: File,
+    decoder: Decoder,
+    normalizer: Normalizer,
+    hasher: DefaultHasher,
+    bytes_read: u64,
+}
+
+impl ReaderState {
+    fn read_rest(mut self, sender: Sender<LoadEvent>) {
+        // when sending fails the buffer was closed, and nobody is waiting for the rest
+        let last_event = self.read_chunks(&sender);
+        _ = sender.send(last_event);
+    }
+
+    /// Sends every chunk of the file, returns the event that ends the loading.
+    fn read_chunks(&mut self, sender: &Sender<LoadEvent>) -> LoadEvent {
+        let mut bytes = vec![0; CHUNK_SIZE];
+
+        loop {
+            let len = match read_chunk(&mut self.file, &mut bytes) {
+                Ok(len) => len,
+                Err(e) => return LoadEvent::Failed(e.to_string()),
+            };
+
+            let last = len == 0;
+            self.hasher.write(&bytes[..len]);
+            self.bytes_read += len as u64;
+
+            let Some(text) = decode(&mut self.decoder, &bytes[..len], last) else {
+                return LoadEvent::Failed(String::from("the file has malformed content"));
+            };
+
+            // the line ending was detected from the start of the file, writing back a file that
+            // changes it further down would turn its other line breaks into the detected one
+            let Some(text) = self.normalizer.push(&text, last) else {
+                return LoadEvent::Failed(String::from("the file mixes line endings"));
+            };
+            let chunk = LoadEvent::Chunk {
+                text,
+                bytes_read: self.bytes_read,
+            };
+
+            if sender.send(chunk).is_err() {
+                return LoadEvent::Failed(String::from("the buffer was closed"));
+            }
+
+            if last {
+                return LoadEvent::Done(self.snapshot());
+            }
+        }
+    }
+
+    fn snapshot(&self) -> FileSnapshot {
+        FileSnapshot::from_hasher(&self.path, self.bytes_read, &self.hasher)
+    }
+}
+
+/// Normalizes line endings of a file that arrives in chunks, where a `\r\n` may be split between
+/// two of them. Chunks with line breaks of another kind are refused.
+#[derive(Debug)]
+struct Normalizer {
+    line_ending: LineEnding,
+    pending_cr: bool,
+}
+
+impl Normalizer {
+    fn new(line_ending: LineEnding) -> Self {
+        Self {
+            line_ending,
+            pending_cr: false,
+        }
+    }
+
+    fn push(&mut self, text: &str, last: bool) -> Option<String> {
+        let mut text = match std::mem::take(&mut self.pending_cr) {
+            true => format!("\r{text}"),
+            false => text.to_string(),
+        };
+
+        if !last && self.line_ending != LineEnding::Lf && text.ends_with('\r') {
+            text.pop();
+            self.pending_cr = true;
+        }
+
+        self.line_ending
+            .is_used_throughout(&text)
+            .then(|| self.line_ending.normalize(&text))
+    }
+}
+
+/// Fills `bytes` as much as possible, only reading less at the end of the file.
+fn read_chunk(file: &mut File, bytes: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+
+    while len < bytes.len() {
+        match file.read(&mut bytes[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(len)
+}
+
+fn decode(decoder: &mut Decoder, bytes: &[u8], last: bool) -> Option<String> {
+    let capacity = decoder.max_utf8_buffer_length_without_replacement(bytes.len())?;
+    let mut text = String::with_capacity(capacity);
+
+    match decoder.decode_to_string_without_replacement(bytes, &mut text, last) {
+        (DecoderResult::InputEmpty, _) => Some(text),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_in_chunks() {
+        let dir = std::env::temp_dir().join(format!("glyph-loader-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("large.txt");
+
+        // multibyte characters and line breaks end up split between chunks
+        let line = "olá mundo\r\n";
+        let content = line.repeat(FIRST_CHUNK_SIZE / 4);
+        std::fs::write(&path, &content).unwrap();
+
+        let (decoded, line_ending, mut loader) = FileLoader::open(&path).unwrap();
+        assert_eq!(line_ending, LineEnding::Crlf);
+        assert_eq!(decoded.encoding, Encoding::Utf8);
+
+        let mut text = decoded.content;
+        let snapshot = loop {
+            match loader.poll() {
+                Some(LoadProgress::Loading(chunk)) => text.push_str(&chunk),
+                Some(LoadProgress::Done {
+                    text: chunk,
+                    snapshot,
+                }) => {
+                    text.push_str(&chunk);
+                    break snapshot;
+                }
+                Some(LoadProgress::Failed { reason, .. }) => panic!("{reason}"),
+                None => std::thread::yield_now(),
+            }
+        };
+
+        assert_eq!(text, LineEnding::Crlf.normalize(&content));
+        assert_eq!(loader.percent(), 100);
+        assert_eq!(snapshot, FileSnapshot::new(&path, content.as_bytes()));
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_refuse_mixed_line_endings_past_the_first_chunk() {
+        let dir = std::env::temp_dir().join(format!("glyph-loader-mixed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mixed.txt");
+
+        let content = format!("{}unix line\n", "dos line\r\n".repeat(FIRST_CHUNK_SIZE / 8));
+        std::fs::write(&path, &content).unwrap();
+
+        let (_, line_ending, mut loader) = FileLoader::open(&path).unwrap();
+        assert_eq!(line_ending, LineEnding::Crlf);
+
+        let reason = loop {
+            match loader.poll() {
+                Some(LoadProgress::Failed { reason, .. }) => break reason,
+                Some(LoadProgress::Done { .. }) => panic!("mixed line endings were loaded"),
+                _ => std::thread::yield_now(),
+            }
+        };
+        assert_eq!(reason, "the file mixes line endings");
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,339 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ropey::Rope;
+
+use crate::text_object::{Change, TextObject};
+
+const HEADER: &str = "glyph swap 1";
+const EXTENSION: &str = "swp";
+
+/// The directory swap files are kept in, `$XDG_STATE_HOME/glyph/swap` on linux.
+pub fn default_swap_dir() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("glyph").join("swap"))
+}
+
+/// The swap file of a buffer, where its journal is persisted so unsaved changes survive a crash.
+///
+/// A swap file starts with a header identifying the process and the file being edited, followed
+/// by the content the journal is based on and then every change of the journal, appended as they
+/// are flushed. Records are length prefixed, so a record cut short by a crash is simply dropped
+/// when reading.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SwapFile {
+    path: PathBuf,
+    /// How many journal entries were written, `None` while the swap file doesn't exist.
+    written: Option<usize>,
+}
+
+/// What was found in an existing swap file.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SwapInfo {
+    pub swap_path: PathBuf,
+    pub file_path: PathBuf,
+    pub pid: u32,
+}
+
+impl SwapInfo {
+    /// Whether the process that wrote the swap file is still running, which means the file is
+    /// probably being edited somewhere else.
+    pub fn is_running(&self) -> bool {
+        is_process_running(self.pid)
+    }
+}
+
+impl SwapFile {
+    pub fn new(dir: &Path, file_path: &Path) -> Self {
+        Self {
+            path: swap_path_for(dir, file_path),
+            written: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes every journal entry of `content` that isn't in the swap file yet, creating it when
+    /// needed.
+    pub fn flush(&mut self, file_path: &Path, content: &TextObject) -> std::io::Result<()> {
+        let journal = content.journal();
+
+        let Some(written) = self.written else {
+            return self.create(file_path, content);
+        };
+
+        if written == journal.len() {
+            return Ok(());
+        }
+
+        let mut bytes = vec![];
+        for change in &journal[written..] {
+            write_change(&mut bytes, change);
+        }
+
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(&bytes)?;
+        file.sync_data()?;
+        self.written = Some(journal.len());
+
+        Ok(())
+    }
+
+    fn create(&mut self, file_path: &Path, content: &TextObject) -> std::io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut bytes = format!(
+            "{HEADER}\npid {}\npath {}\n",
+            std::process::id(),
+            file_path.display()
+        )
+        .into_bytes();
+
+        write_record(&mut bytes, "=", &content.journal_base().to_string());
+        for change in content.journal() {
+            write_change(&mut bytes, change);
+        }
+
+        super::file_io::write_atomically(&self.path, &bytes)?;
+        self.written = Some(content.journal().len());
+
+        Ok(())
+    }
+
+    /// Deletes the swap file, the next flush creates it again.
+    pub fn remove(&mut self) -> std::io::Result<()> {
+        if self.written.take().is_none() {
+            return Ok(());
+        }
+
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads the header of the swap file at `swap_path`.
+pub fn read_info(swap_path: &Path) -> std::io::Result<SwapInfo> {
+    let bytes = std::fs::read(swap_path)?;
+    parse_header(swap_path, &bytes).map(|(info, _)| info)
+}
+
+/// Rebuilds the content that was being edited when the swap file at `swap_path` was last flushed.
+pub fn recover(swap_path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(swap_path)?;
+    let (_, mut rest) = parse_header(swap_path, &bytes)?;
+
+    let Some(("=", _, base, remaining)) = read_record(rest) else {
+        return Err(invalid_data(swap_path));
+    };
+
+    let mut content = Rope::from(base);
+    rest = remaining;
+
+    while let Some((kind, char_idx, text, remaining)) = read_record(rest) {
+        let change = match kind {
+            "+" => Change::Insert {
+                char_idx,
+                text: text.to_string(),
+            },
+            "-" => Change::Delete {
+                char_idx,
+                text: text.to_string(),
+            },
+            _ => break,
+        };
+
+        let end = char_idx + text.chars().count();
+        let in_bounds = match change {
+            Change::Insert { .. } => char_idx <= content.len_chars(),
+            Change::Delete { .. } => end <= content.len_chars(),
+        };
+
+        if !in_bounds {
+            break;
+        }
+
+        change.apply(&mut content);
+        rest = remaining;
+    }
+
+    Ok(content.to_string())
+}
+
+/// Lists every swap file in `dir`.
+pub fn list(dir: &Path) -> std::io::Result<Vec<SwapInfo>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut swap_files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .filter_map(|path| read_info(&path).ok())
+        .collect::<Vec<_>>();
+
+    swap_files.sort();
+    Ok(swap_files)
+}
+
+/// Swap files are named after the absolute path of the file they belong to, with path separators
+/// replaced by `%`, so every file has exactly one swap file.
+fn swap_path_for(dir: &Path, file_path: &Path) -> PathBuf {
+    let name = file_path.to_string_lossy().replace(['/', '\\', ':'], "%");
+
+    dir.join(format!("{name}.{EXTENSION}"))
+}
+
+fn write_change(bytes: &mut Vec<u8>, change: &Change) {
+    match change {
+        Change::Insert { char_idx, text } => write_record(bytes, &format!("+ {char_idx}"), text),
+        Change::Delete { char_idx, text } => write_record(bytes, &format!("- {char_idx}"), text),
+    }
+}
+
+fn write_record(bytes: &mut Vec<u8>, prefix: &str, text: &str) {
+    bytes.extend_from_slice(format!("{prefix} {}\n", text.len()).as_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.push(b'\n');
+}
+
+/// Reads a `<kind> [char_idx] <byte_len>` line followed by the record text, returns `None` when
+/// the record is incomplete.
+fn read_record(bytes: &[u8]) -> Option<(&str, usize, &str, &[u8])> {
+    let line_end = bytes.iter().position(|&b| b == b'\n')?;
+    let line = std::str::from_utf8(&bytes[..line_end]).ok()?;
+    let mut parts = line.split(' ');
+
+    let kind = parts.next()?;
+    let char_idx = match kind {
+        "=" => 0,
+        _ => parts.next()?.parse().ok()?,
+    };
+    let len = parts.next()?.parse::<usize>().ok()?;
+
+    let text_start = line_end + 1;
+    let text_end = text_start + len;
+    let text = std::str::from_utf8(bytes.get(text_start..text_end)?).ok()?;
+
+    // the newline after the text is what tells a complete record apart from a truncated one
+    if bytes.get(text_end) != Some(&b'\n') {
+        return None;
+    }
+
+    Some((kind, char_idx, text, &bytes[text_end + 1..]))
+}
+
+fn parse_header<'a>(swap_path: &Path, bytes: &'a [u8]) -> std::io::Result<(SwapInfo, &'a [u8])> {
+    let mut parts = bytes.splitn(4, |&b| b == b'\n');
+    let mut lines = parts
+        .by_ref()
+        .take(3)
+        .map(|line| std::str::from_utf8(line).ok());
+
+    let (Some(Some(HEADER)), Some(Some(pid)), Some(Some(path))) =
+        (lines.next(), lines.next(), lines.next())
+    else {
+        return Err(invalid_data(swap_path));
+    };
+
+    let pid = pid
+        .strip_prefix("pid ")
+        .and_then(|pid| pid.parse().ok())
+        .ok_or_else(|| invalid_data(swap_path))?;
+
+    let file_path = path
+        .strip_prefix("path ")
+        .map(PathBuf::from)
+        .ok_or_else(|| invalid_data(swap_path))?;
+
+    let info = SwapInfo {
+        swap_path: swap_path.to_path_buf(),
+        file_path,
+        pid,
+    };
+
+    Ok((info, parts.next().unwrap_or_default()))
+}
+
+fn invalid_data(swap_path: &Path) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("{} is not a valid swap file", swap_path.display()),
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn is_process_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_process_running(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text_object::Transaction;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("glyph-{name}-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_flush_and_recover() {
+        let dir = temp_dir("swap");
+        let file_path = Path::new("/some/file.txt");
+        let mut content = TextObject::new("abc\ndef\n".into());
+        let mut swap = SwapFile::new(&dir, file_path);
+
+        content.apply(Transaction::new().insert(0, "x"));
+        swap.flush(file_path, &content).unwrap();
+        content.apply(Transaction::new().delete(5..9).insert(9, "é"));
+        swap.flush(file_path, &content).unwrap();
+
+        let recovered = recover(swap.path()).unwrap();
+        assert_eq!(recovered.as_bytes(), content.to_bytes());
+
+        let swap_files = list(&dir).unwrap();
+        assert_eq!(swap_files.len(), 1);
+        assert_eq!(swap_files[0].file_path, file_path);
+        assert_eq!(swap_files[0].pid, std::process::id());
+
+        swap.remove().unwrap();
+        assert!(list(&dir).unwrap().is_empty());
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_truncated_record_is_dropped() {
+        let dir = temp_dir("swap-truncated");
+        let file_path = Path::new("/some/file.txt");
+        let mut content = TextObject::new("abc".into());
+        let mut swap = SwapFile::new(&dir, file_path);
+
+        content.apply(Transaction::new().insert(0, "x"));
+        swap.flush(file_path, &content).unwrap();
+
+        // a crash in the middle of appending a record
+        let mut file = OpenOptions::new().append(true).open(swap.path()).unwrap();
+        file.write_all(b"+ 0 10\nyy").unwrap();
+
+        assert_eq!(recover(swap.path()).unwrap(), "xabc");
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt::Debug;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::error::{GlyphError, Result};
+
+/// The selections of the system clipboard, `"+` is the clipboard and `"*` the primary selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ClipboardKind {
+    Clipboard,
+    Primary,
+}
+
+impl ClipboardKind {
+    pub fn from_register(name: char) -> Option<Self> {
+        match name {
+            '+' => Some(Self::Clipboard),
+            '*' => Some(Self::Primary),
+            _ => None,
+        }
+    }
+}
+
+/// Reaches the system clipboard for the `"+` and `"*` registers.
+pub trait ClipboardProvider: Debug {
+    fn name(&self) -> &str;
+
+    fn set_contents(&self, kind: ClipboardKind, text: &str) -> Result<()>;
+
+    /// The text in the clipboard, or `None` when it can't be read, as a terminal that only
+    /// accepts OSC 52 writes.
+    fn get_contents(&self, kind: ClipboardKind) -> Option<String>;
+}
+
+/// A clipboard reached through helper programs like `wl-copy` and `xclip`, which take the text on
+/// their standard input and print it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandClipboardProvider {
+    name: &'static str,
+    copy: [&'static [&'static str]; 2],
+    paste: [&'static [&'static str]; 2],
+}
+
+impl CommandClipboardProvider {
+    pub fn wl_clipboard() -> Self {
+        Self {
+            name: "wl-clipboard",
+            copy: [
+                &["wl-copy", "--type", "text/plain"],
+                &["wl-copy", "--primary", "--type", "text/plain"],
+            ],
+            paste: [
+                &["wl-paste", "--no-newline"],
+                &["wl-paste", "--no-newline", "--primary"],
+            ],
+        }
+    }
+
+    pub fn xclip() -> Self {
+        Self {
+            name: "xclip",
+            copy: [
+                &["xclip", "-i", "-selection", "clipboard"],
+                &["xclip", "-i", "-selection", "primary"],
+            ],
+            paste: [
+                &["xclip", "-o", "-selection", "clipboard"],
+                &["xclip", "-o", "-selection", "primary"],
+            ],
+        }
+    }
+
+    /// The helper for the display server the editor runs under, when it's installed.
+    pub fn detect() -> Option<Self> {
+        if has_env("WAYLAND_DISPLAY") && is_executable("wl-copy") && is_executable("wl-paste") {
+            return Some(Self::wl_clipboard());
+        }
+
+        if has_env("DISPLAY") && is_executable("xclip") {
+            return Some(Self::xclip());
+        }
+
+        None
+    }
+
+    fn command(&self, argv: &[&str]) -> Command {
+        let mut command = Command::new(argv[0]);
+        command.args(&argv[1..]).stderr(Stdio::null());
+        command
+    }
+}
+
+impl ClipboardProvider for CommandClipboardProvider {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn set_contents(&self, kind: ClipboardKind, text: &str) -> Result<()> {
+        let argv = self.copy[kind as usize];
+        let failed = |reason: String| GlyphError::ClipboardFailed {
+            provider: self.name.to_string(),
+            reason,
+        };
+
+        let mut child = self
+            .command(argv)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| failed(e.to_string()))?;
+
+        // the helpers fork to keep serving the selection once their input is closed, so waiting
+        // only takes until then
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| failed(e.to_string()))?;
+        drop(stdin);
+
+        match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(failed(status.to_string())),
+            Err(e) => Err(failed(e.to_string())),
+        }
+    }
+
+    fn get_contents(&self, kind: ClipboardKind) -> Option<String> {
+        let argv = self.paste[kind as usize];
+        let output = self.command(argv).stdin(Stdio::null()).output().ok()?;
+
+        match output.status.success() {
+            true => String::from_utf8(output.stdout).ok(),
+            false => None,
+        }
+    }
+}
+
+/// Whether the editor runs in an ssh session, where the clipboard of the display server isn't the
+/// one of the user.
+pub fn is_over_ssh() -> bool {
+    has_env("SSH_TTY") || has_env("SSH_CONNECTION")
+}
+
+/// Whether the environment variable `name` is set to something, empty ones count as unset.
+fn has_env(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|value| !value.is_empty())
+}
+
+/// The escape sequence that asks the terminal to put `text` in the clipboard, which reaches the
+/// clipboard of the machine the terminal runs on even over ssh.
+pub fn osc52_sequence(kind: ClipboardKind, text: &str) -> String {
+    let selection = match kind {
+        ClipboardKind::Clipboard => 'c',
+        ClipboardKind::Primary => 'p',
+    };
+
+    format!("\x1b]52;{selection};{}\x07", base64_encode(text.as_bytes()))
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| {
+            group | (byte as u32) << (16 - i * 8)
+        });
+
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => encoded.push(ALPHABET[(group >> (18 - i * 6)) as usize & 0x3f] as char),
+                false => encoded.push('='),
+            }
+        }
+    }
+
+    encoded
+}
+
+fn is_executable(program: &str) -> bool {
+    let Some(paths) = std::env::var_os("PATH") else {
+        return false;
+    };
+
+    std::env::split_paths(&paths).any(|dir| Path::new(&dir).join(program).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foob"), "Zm9vYg==");
+        assert_eq!(base64_encode("é\n".as_bytes()), "w6kK");
+
+        assert_eq!(
+            osc52_sequence(ClipboardKind::Clipboard, "foo"),
+            "\x1b]52;c;Zm9v\x07"
+        );
+        assert_eq!(
+            osc52_sequence(ClipboardKind::Primary, "foo"),
+            "\x1b]52;p;Zm9v\x07"
+        );
+    }
+}
@@ -1,12 +1,25 @@
+mod buffer_command_handler;
 mod file_command_handler;
+mod option_command_handler;
+mod substitute_command_handler;
 
-use std::collections::{BTreeMap, VecDeque};
+use std::collections::VecDeque;
 use std::fmt::Debug;
 
+pub use buffer_command_handler::BufferCommandHandler;
 pub use file_command_handler::FileCommandHandler;
+pub use option_command_handler::OptionCommandHandler;
+pub use substitute_command_handler::SubstituteCommandHandler;
 
-use crate::buffer_manager::{Buffer, BufferId};
+use crate::buffer_manager::BufferManager;
+use crate::geometry::Point;
 use crate::key_mapper::ResolvedKeymap;
+use crate::message::Message;
+use crate::options::EditorOptions;
+use crate::prompt::Prompt;
+use crate::register::Registers;
+use crate::search::SearchState;
+use crate::text_object::{Assoc, Transaction};
 use crate::view_manager::ViewManager;
 
 pub enum CommandHandlerResult {
@@ -16,9 +29,17 @@ pub enum CommandHandlerResult {
 
 pub struct CommandContext<'ctx> {
     pub resolved_keymap: &'ctx ResolvedKeymap,
-    pub buffers: &'ctx mut BTreeMap<BufferId, Buffer>,
+    pub buffers: &'ctx mut BufferManager,
     pub views: &'ctx mut ViewManager,
     pub should_quit: &'ctx mut bool,
+    pub message: &'ctx mut Option<Message>,
+    pub options: &'ctx mut EditorOptions,
+    pub search: &'ctx mut SearchState,
+    pub registers: &'ctx mut Registers,
+    /// A question for the user, whose answer comes back as [`Command::AnswerPrompt`].
+    ///
+    /// [`Command::AnswerPrompt`]: crate::key_mapper::Command::AnswerPrompt
+    pub prompt: &'ctx mut Option<Prompt>,
 }
 
 pub trait CommandHandler: Debug {
@@ -52,4 +73,46 @@ impl CommandHandler for CommandHandlerChain {
 
         CommandHandlerResult::Consumed
     }
+}
+
+/// Applies `transaction` to the buffer of the active view, carrying the cursors of every view that
+/// shows the buffer over to the new content.
+pub fn apply_transaction(ctx: &mut CommandContext<'_>, transaction: &Transaction) {
+    if transaction.is_empty() {
+        return;
+    }
+
+    let buffer_id = ctx.views.get_active_view().buffer_id;
+    let buffer = ctx
+        .buffers
+        .get_mut(buffer_id)
+        .expect("view references non-existing buffer");
+
+    // positions have to be taken against the content the transaction was built for
+    let content = buffer.content();
+    let positions = ctx
+        .views
+        .iter_mut()
+        .filter(|view| view.buffer_id == buffer_id)
+        .flat_map(|view| view.cursors.iter())
+        .map(|cursor| content.point_to_char(Point::new(cursor.x, cursor.y)))
+        .collect::<Vec<_>>();
+
+    buffer.content_mut().apply(transaction);
+
+    let content = buffer.content();
+    let mut positions = positions.into_iter();
+    for view in ctx
+        .views
+        .iter_mut()
+        .filter(|view| view.buffer_id == buffer_id)
+    {
+        for cursor in view.cursors.iter_mut() {
+            let char_idx = positions.next().expect("every cursor has a position");
+            let position = content.char_to_point(transaction.map_position(char_idx, Assoc::After));
+            cursor.x = position.x;
+            cursor.y = position.y;
+            cursor.update_virtual_x(buffer);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,169 @@
+use crate::buffer_manager::BufferId;
+use crate::command_handler::{CommandContext, CommandHandler, CommandHandlerResult};
+use crate::error::{GlyphError, Result};
+use crate::key_mapper::Command;
+use crate::message::Message;
+
+/// Opens, switches between and closes buffers. Other commands are left for the next handlers.
+#[derive(Debug)]
+pub struct BufferCommandHandler;
+
+impl CommandHandler for BufferCommandHandler {
+    fn handle_commands(&mut self, ctx: &mut CommandContext<'_>) -> CommandHandlerResult {
+        for command in ctx.resolved_keymap.commands.iter() {
+            let result = match command {
+                Command::Edit(path) => edit(ctx, path),
+                Command::NextBuffer => {
+                    let buffer_id = ctx.views.get_active_view().buffer_id;
+                    show_buffer(ctx, ctx.buffers.next_buffer(buffer_id))
+                }
+                Command::PrevBuffer => {
+                    let buffer_id = ctx.views.get_active_view().buffer_id;
+                    show_buffer(ctx, ctx.buffers.prev_buffer(buffer_id))
+                }
+                Command::SwitchBuffer(query) => switch_buffer(ctx, query),
+                Command::AlternateBuffer => alternate_buffer(ctx),
+                Command::DeleteBuffer { buffer, force } => {
+                    delete_buffer(ctx, buffer.as_deref(), *force)
+                }
+                Command::ListBuffers => {
+                    list_buffers(ctx);
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+
+            if let Err(e) = result {
+                *ctx.message = Some(Message::error(e.to_string()));
+                return CommandHandlerResult::Consumed;
+            }
+        }
+
+        CommandHandlerResult::NotConsumed
+    }
+}
+
+fn edit(ctx: &mut CommandContext<'_>, path: &str) -> Result<()> {
+    let buffer_id = ctx.buffers.open(path)?;
+    show_buffer(ctx, buffer_id)
+}
+
+fn switch_buffer(ctx: &mut CommandContext<'_>, query: &str) -> Result<()> {
+    if query == "#" {
+        return alternate_buffer(ctx);
+    }
+
+    let buffer_id = ctx.buffers.find(query)?;
+    show_buffer(ctx, buffer_id)
+}
+
+fn alternate_buffer(ctx: &mut CommandContext<'_>) -> Result<()> {
+    let buffer_id = ctx
+        .views
+        .get_active_view()
+        .alternate_buffer
+        .ok_or(GlyphError::NoAlternateBuffer)?;
+
+    show_buffer(ctx, buffer_id)
+}
+
+fn show_buffer(ctx: &mut CommandContext<'_>, buffer_id: BufferId) -> Result<()> {
+    let buffer = ctx
+        .buffers
+        .get(buffer_id)
+        .expect("buffer ids come from the buffer manager");
+
+    let view = ctx.views.get_mut_active_view();
+    view.show_buffer(buffer_id);
+
+    // the buffer may have changed since the view last showed it
+    for cursor in view.cursors.iter_mut() {
+        cursor.clamp_to_buffer(buffer);
+    }
+
+    Ok(())
+}
+
+fn delete_buffer(ctx: &mut CommandContext<'_>, query: Option<&str>, force: bool) -> Result<()> {
+    let buffer_id = match query {
+        Some(query) => ctx.buffers.find(query)?,
+        None => ctx.views.get_active_view().buffer_id,
+    };
+
+    let buffer = ctx
+        .buffers
+        .get(buffer_id)
+        .expect("buffer ids come from the buffer manager");
+
+    if !force && buffer.is_dirty() {
+        return Err(GlyphError::UnsavedBuffer(buffer_id));
+    }
+
+    // views showing the buffer move to their alternate buffer, or to the next one in the list
+    let next_buffer_id = match ctx.buffers.next_buffer(buffer_id) {
+        next_buffer_id if next_buffer_id == buffer_id => ctx.buffers.create_empty_buffer(),
+        next_buffer_id => next_buffer_id,
+    };
+
+    for view in ctx.views.iter_mut() {
+        if view.buffer_id == buffer_id {
+            let replacement = view
+                .alternate_buffer
+                .filter(|alternate| *alternate != buffer_id)
+                .unwrap_or(next_buffer_id);
+
+            view.show_buffer(replacement);
+
+            let buffer = ctx
+                .buffers
+                .get(replacement)
+                .expect("buffer ids come from the buffer manager");
+
+            for cursor in view.cursors.iter_mut() {
+                cursor.clamp_to_buffer(buffer);
+            }
+        }
+
+        view.forget_buffer(buffer_id);
+    }
+
+    ctx.buffers.remove(buffer_id);
+
+    Ok(())
+}
+
+/// Shows every buffer like vim's `:ls`, `%` marks the current buffer, `#` the alternate one, `a`
+/// buffers shown in a view, `h` hidden ones and `+` the ones with unsaved changes.
+fn list_buffers(ctx: &mut CommandContext<'_>) {
+    let active_view = ctx.views.get_active_view();
+    let visible = ctx.views.get_visible();
+
+    let lines = ctx
+        .buffers
+        .iter()
+        .map(|buffer| {
+            let current = match buffer.id {
+                id if id == active_view.buffer_id => '%',
+                id if Some(id) == active_view.alternate_buffer => '#',
+                _ => ' ',
+            };
+            let shown = match visible.iter().any(|view| view.buffer_id == buffer.id) {
+                true => 'a',
+                false => 'h',
+            };
+            let modified = if buffer.is_dirty() { '+' } else { ' ' };
+            let line = active_view
+                .position_in(buffer.id)
+                .and_then(|position| position.cursors.first().map(|cursor| cursor.y + 1))
+                .unwrap_or_default();
+            let name = format!("\"{}\"", buffer.display_name());
+
+            format!(
+                "{:>3} {current}{shown} {modified} {name:<30} line {line}",
+                buffer.id
+            )
+        })
+        .collect::<Vec<_>>();
+
+    *ctx.message = Some(Message::info(lines.join("\n")));
+}
@@ -1,20 +1,220 @@
-use crate::command_handler::{CommandHandler, CommandHandlerResult};
+use std::path::Path;
+
+use crate::buffer_manager::{Buffer, BufferId, DiskStatus};
+use crate::command_handler::{CommandContext, CommandHandler, CommandHandlerResult};
+use crate::error::{GlyphError, Result};
 use crate::key_mapper::Command;
+use crate::message::Message;
+use crate::prompt::PromptKind;
+use crate::view_manager::ViewManager;
 
 #[derive(Debug)]
 pub struct FileCommandHandler;
 
 impl CommandHandler for FileCommandHandler {
-    fn handle_commands(&mut self, ctx: &mut super::CommandContext<'_>) -> CommandHandlerResult {
+    fn handle_commands(&mut self, ctx: &mut CommandContext<'_>) -> CommandHandlerResult {
+        let mut status = CommandHandlerResult::Consumed;
+
         for command in ctx.resolved_keymap.commands.iter() {
-            match command {
-                Command::Quit => *ctx.should_quit = true,
-                Command::Save => todo!(),
-                Command::SaveAll => todo!(),
-                _ => {}
+            let result = match command {
+                Command::Quit => quit(ctx),
+                Command::ForceQuit => {
+                    *ctx.should_quit = true;
+                    Ok(())
+                }
+                Command::Save => save(ctx, false),
+                Command::ForceSave => save(ctx, true),
+                Command::SaveAll => save_all(ctx),
+                Command::Update => update(ctx),
+                Command::Rename(path) => rename(ctx, path),
+                Command::SaveAs { path, force } => save_as(ctx, path, *force),
+                Command::Reload => reload(ctx, false),
+                Command::ForceReload => reload(ctx, true),
+                Command::CheckTime => check_time(ctx),
+                Command::AnswerPrompt {
+                    kind: PromptKind::SwapExists(buffer_id),
+                    answer,
+                } => resolve_swap_conflict(ctx, *buffer_id, *answer),
+                Command::SetOption { .. } => {
+                    status = CommandHandlerResult::NotConsumed;
+                    Ok(())
+                }
+                _ => Ok(()),
+            };
+
+            // commands after a failed one are not executed, so `:wq` doesn't quit when the file
+            // couldn't be written
+            if let Err(e) = result {
+                *ctx.message = Some(Message::error(e.to_string()));
+                return CommandHandlerResult::Consumed;
+            }
+        }
+
+        status
+    }
+}
+
+fn quit(ctx: &mut CommandContext<'_>) -> Result<()> {
+    let unsaved = ctx
+        .buffers
+        .iter()
+        .filter(|buffer| buffer.is_dirty())
+        .map(|buffer| buffer.display_name())
+        .collect::<Vec<_>>();
+
+    if !unsaved.is_empty() {
+        return Err(GlyphError::UnsavedChanges(unsaved.join(", ")));
+    }
+
+    *ctx.should_quit = true;
+    Ok(())
+}
+
+fn update(ctx: &mut CommandContext<'_>) -> Result<()> {
+    let buffer_id = ctx.views.get_active_view().buffer_id;
+    let buffer = ctx
+        .buffers
+        .get(buffer_id)
+        .expect("view references non-existing buffer");
+
+    match buffer.is_dirty() {
+        true => save(ctx, false),
+        false => Ok(()),
+    }
+}
+
+fn save(ctx: &mut CommandContext<'_>, force: bool) -> Result<()> {
+    let buffer_id = ctx.views.get_active_view().buffer_id;
+    let buffer = ctx
+        .buffers
+        .get_mut(buffer_id)
+        .expect("view references non-existing buffer");
+
+    let summary = buffer.save(force)?;
+    *ctx.message = Some(Message::info(summary.to_string()));
+
+    Ok(())
+}
+
+fn rename(ctx: &mut CommandContext<'_>, path: &str) -> Result<()> {
+    let buffer_id = ctx.views.get_active_view().buffer_id;
+    ctx.buffers.rename(buffer_id, path)
+}
+
+fn save_as(ctx: &mut CommandContext<'_>, path: &str, force: bool) -> Result<()> {
+    if !force && Path::new(path).exists() {
+        return Err(GlyphError::FileExists);
+    }
+
+    rename(ctx, path)?;
+    save(ctx, force)
+}
+
+fn save_all(ctx: &mut CommandContext<'_>) -> Result<()> {
+    let mut first_error = None;
+
+    for buffer in ctx.buffers.iter_mut() {
+        if !buffer.is_dirty() {
+            continue;
+        }
+
+        match buffer.save(false) {
+            Ok(summary) => *ctx.message = Some(Message::info(summary.to_string())),
+            Err(e) => _ = first_error.get_or_insert(e),
+        }
+    }
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn reload(ctx: &mut CommandContext<'_>, force: bool) -> Result<()> {
+    let buffer_id = ctx.views.get_active_view().buffer_id;
+    let buffer = ctx
+        .buffers
+        .get_mut(buffer_id)
+        .expect("view references non-existing buffer");
+
+    if !force && buffer.is_dirty() {
+        return Err(GlyphError::UnsavedChanges(buffer.display_name()));
+    }
+
+    buffer.reload()?;
+    ctx.views.clamp_cursors(buffer);
+
+    Ok(())
+}
+
+/// Looks for buffers whose file was changed by something else. With `autoread`, the ones without
+/// unsaved changes are reloaded, every other change is reported to the user.
+fn check_time(ctx: &mut CommandContext<'_>) -> Result<()> {
+    let mut first_error = None;
+
+    for buffer in ctx.buffers.iter_mut() {
+        let result = match buffer.poll_disk_status() {
+            DiskStatus::Unchanged => Ok(()),
+            DiskStatus::Changed if ctx.options.autoread && !buffer.is_dirty() => {
+                reload_changed(buffer, ctx.views, ctx.message)
+            }
+            DiskStatus::Changed if buffer.is_dirty() => {
+                Err(GlyphError::ChangedAndModified(buffer.display_name()))
             }
+            DiskStatus::Changed => Err(GlyphError::ChangedOnDisk(buffer.display_name())),
+            DiskStatus::Deleted => Err(GlyphError::DeletedOnDisk(buffer.display_name())),
+        };
+
+        if let Err(e) = result {
+            _ = first_error.get_or_insert(e);
         }
+    }
 
-        CommandHandlerResult::Consumed
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
     }
+}
+
+fn reload_changed(
+    buffer: &mut Buffer,
+    views: &mut ViewManager,
+    message: &mut Option<Message>,
+) -> Result<()> {
+    buffer.reload()?;
+    views.clamp_cursors(buffer);
+    *message = Some(Message::info(format!(
+        "\"{}\" reloaded, it was changed outside of the editor",
+        buffer.display_name()
+    )));
+
+    Ok(())
+}
+
+fn resolve_swap_conflict(
+    ctx: &mut CommandContext<'_>,
+    buffer_id: BufferId,
+    answer: char,
+) -> Result<()> {
+    let buffer = ctx
+        .buffers
+        .get_mut(buffer_id)
+        .expect("prompt references non-existing buffer");
+
+    match answer {
+        'r' => {
+            buffer.recover_from_swap()?;
+            ctx.views.clamp_cursors(buffer);
+            *ctx.message = Some(Message::info(format!(
+                "Recovered \"{}\", write it to keep the changes",
+                buffer.display_name()
+            )));
+        }
+        'o' => buffer.open_read_only(),
+        'd' => buffer.delete_swap()?,
+        'q' => *ctx.should_quit = true,
+        _ => {}
+    }
+
+    Ok(())
 }
\ No newline at end of file
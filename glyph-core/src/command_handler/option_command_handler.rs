@@ -0,0 +1,112 @@
+use crate::command_handler::{CommandContext, CommandHandler, CommandHandlerResult};
+use crate::error::Result;
+use crate::key_mapper::Command;
+use crate::message::Message;
+use crate::options::EditorOption;
+
+#[derive(Debug)]
+pub struct OptionCommandHandler;
+
+impl CommandHandler for OptionCommandHandler {
+    fn handle_commands(&mut self, ctx: &mut CommandContext<'_>) -> CommandHandlerResult {
+        for command in ctx.resolved_keymap.commands.iter() {
+            let Command::SetOption { name, value } = command else {
+                continue;
+            };
+
+            if let Err(e) = set_option(ctx, name, value.as_deref()) {
+                *ctx.message = Some(Message::error(e.to_string()));
+                break;
+            }
+        }
+
+        CommandHandlerResult::Consumed
+    }
+}
+
+fn set_option(ctx: &mut CommandContext<'_>, name: &str, value: Option<&str>) -> Result<()> {
+    let option = EditorOption::parse(name, value)?;
+
+    match option {
+        EditorOption::Autoread(autoread) => {
+            ctx.options.autoread = autoread;
+            return Ok(());
+        }
+        EditorOption::IgnoreCase(ignorecase) => {
+            ctx.options.ignorecase = ignorecase;
+            return Ok(());
+        }
+        EditorOption::SmartCase(smartcase) => {
+            ctx.options.smartcase = smartcase;
+            return Ok(());
+        }
+        EditorOption::TabStop(tabstop) => {
+            ctx.options.tabstop = tabstop;
+            ctx.buffers.set_tabstop(tabstop);
+            return Ok(());
+        }
+        EditorOption::ExpandTab(expandtab) => {
+            ctx.options.expandtab = expandtab;
+            return Ok(());
+        }
+        EditorOption::ShiftWidth(shiftwidth) => {
+            ctx.options.shiftwidth = shiftwidth;
+            return Ok(());
+        }
+        EditorOption::SoftTabStop(softtabstop) => {
+            ctx.options.softtabstop = softtabstop;
+            return Ok(());
+        }
+        EditorOption::Wrap(_)
+        | EditorOption::LineBreak(_)
+        | EditorOption::BreakIndent(_)
+        | EditorOption::ShowBreak(_) => {
+            set_view_option(ctx, option);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let buffer_id = ctx.views.get_active_view().buffer_id;
+    let buffer = ctx
+        .buffers
+        .get_mut(buffer_id)
+        .expect("view references non-existing buffer");
+
+    match option {
+        EditorOption::FileFormat(line_ending) => buffer.set_line_ending(line_ending),
+        EditorOption::FileEncoding(encoding) => buffer.set_encoding(encoding),
+        EditorOption::Bomb(has_bom) => buffer.set_bom(has_bom),
+        EditorOption::Autoread(_)
+        | EditorOption::IgnoreCase(_)
+        | EditorOption::SmartCase(_)
+        | EditorOption::TabStop(_)
+        | EditorOption::ExpandTab(_)
+        | EditorOption::ShiftWidth(_)
+        | EditorOption::SoftTabStop(_)
+        | EditorOption::Wrap(_)
+        | EditorOption::LineBreak(_)
+        | EditorOption::BreakIndent(_)
+        | EditorOption::ShowBreak(_) => unreachable!("handled above"),
+    }
+
+    Ok(())
+}
+
+/// Sets an option of the active view, scrolling it again as its rows may have changed.
+fn set_view_option(ctx: &mut CommandContext<'_>, option: EditorOption) {
+    let view = ctx.views.get_mut_active_view();
+
+    match option {
+        EditorOption::Wrap(wrap) => view.options.wrap = wrap,
+        EditorOption::LineBreak(linebreak) => view.options.linebreak = linebreak,
+        EditorOption::BreakIndent(breakindent) => view.options.breakindent = breakindent,
+        EditorOption::ShowBreak(showbreak) => view.options.showbreak = showbreak,
+        _ => unreachable!("only view options are set here"),
+    }
+
+    // views don't scroll sideways when lines wrap, and only skip rows of a line when they do
+    view.scroll_offset.x = 0;
+    view.skipped_rows = 0;
+    ctx.views.scroll_to_cursor(ctx.buffers);
+}
@@ -0,0 +1,273 @@
+use crate::buffer_manager::BufferId;
+use crate::command_handler::{
+    CommandContext, CommandHandler, CommandHandlerResult, apply_transaction,
+};
+use crate::cursor::Cursor;
+use crate::error::{GlyphError, Result};
+use crate::key_mapper::Command;
+use crate::message::Message;
+use crate::prompt::{Prompt, PromptKind};
+use crate::substitute::{LineRange, SubstituteFlags};
+use crate::text_object::{Assoc, Replacement, SearchPattern, SubstituteMatch, Transaction};
+
+/// Runs `:s`, holding on to the matches left to confirm while the `c` flag asks about each one.
+#[derive(Debug, Default)]
+pub struct SubstituteCommandHandler {
+    pending: Option<PendingSubstitution>,
+}
+
+#[derive(Debug)]
+struct PendingSubstitution {
+    buffer_id: BufferId,
+    /// The revision the matches were found at, the substitution is dropped if the buffer changed
+    /// while waiting for an answer.
+    revision: u64,
+    replacement: String,
+    cursors_before: Vec<Cursor>,
+    matches: Vec<SubstituteMatch>,
+    /// The match the user is being asked about.
+    current: usize,
+    accepted: Vec<SubstituteMatch>,
+}
+
+impl CommandHandler for SubstituteCommandHandler {
+    fn handle_commands(&mut self, ctx: &mut CommandContext<'_>) -> CommandHandlerResult {
+        for command in ctx.resolved_keymap.commands.iter() {
+            let result = match command {
+                Command::Substitute {
+                    range,
+                    pattern,
+                    replacement,
+                    flags,
+                } => self.substitute(ctx, range, pattern, replacement, flags),
+                Command::AnswerPrompt {
+                    kind: PromptKind::ConfirmSubstitute,
+                    answer,
+                } => self.answer(ctx, *answer),
+                _ => Ok(()),
+            };
+
+            if let Err(e) = result {
+                *ctx.message = Some(Message::error(e.to_string()));
+                return CommandHandlerResult::Consumed;
+            }
+        }
+
+        CommandHandlerResult::NotConsumed
+    }
+}
+
+impl SubstituteCommandHandler {
+    fn substitute(
+        &mut self,
+        ctx: &mut CommandContext<'_>,
+        range: &str,
+        pattern: &str,
+        replacement: &str,
+        flags: &str,
+    ) -> Result<()> {
+        let range = LineRange::parse(range)?;
+        let flags = SubstituteFlags::parse(flags)?;
+
+        // the pattern becomes the last search pattern, and an empty one reuses it
+        if !pattern.is_empty() {
+            ctx.search.pattern = Some(pattern.to_string());
+            ctx.search.smartcase = true;
+            ctx.search.highlight = true;
+        }
+
+        let search_pattern = match flags.ignore_case {
+            Some(ignore_case) => {
+                let pattern = ctx
+                    .search
+                    .pattern
+                    .as_deref()
+                    .ok_or(GlyphError::NoPreviousPattern)?;
+                SearchPattern::new(pattern, ignore_case)?
+            }
+            None => ctx.search.compile(ctx.options)?,
+        };
+
+        let view = ctx.views.get_active_view();
+        let buffer = ctx
+            .buffers
+            .get(view.buffer_id)
+            .expect("view references non-existing buffer");
+        let content = buffer.content();
+
+        let cursor = view.cursors.first().unwrap();
+        let lines = range.resolve(cursor.y, content.len_file_lines())?;
+        let matches = content.substitute_matches(
+            &search_pattern,
+            lines,
+            &Replacement::parse(replacement),
+            flags.global,
+        );
+
+        if matches.is_empty() {
+            return Err(GlyphError::PatternNotFound(
+                search_pattern.as_str().to_string(),
+            ));
+        }
+
+        if flags.count_only {
+            *ctx.message = Some(Message::info(report(&matches, "match", "matches")));
+            return Ok(());
+        }
+
+        let cursors_before = view.cursors.clone();
+        if !flags.confirm {
+            apply_substitution(ctx, &cursors_before, &matches);
+            return Ok(());
+        }
+
+        self.pending = Some(PendingSubstitution {
+            buffer_id: view.buffer_id,
+            revision: buffer.revision(),
+            replacement: replacement.to_string(),
+            cursors_before,
+            matches,
+            current: 0,
+            accepted: vec![],
+        });
+        self.confirm_next(ctx);
+
+        Ok(())
+    }
+
+    fn answer(&mut self, ctx: &mut CommandContext<'_>, answer: char) -> Result<()> {
+        let Some(pending) = self.pending.as_mut() else {
+            return Ok(());
+        };
+
+        let view = ctx.views.get_active_view();
+        let is_stale = view.buffer_id != pending.buffer_id
+            || ctx
+                .buffers
+                .get(pending.buffer_id)
+                .is_none_or(|buffer| buffer.revision() != pending.revision);
+        if is_stale {
+            self.pending = None;
+            return Ok(());
+        }
+
+        let current = pending.current;
+        let remaining = pending.matches.len();
+        match answer {
+            'y' => {
+                pending.accepted.push(pending.matches[current].clone());
+                pending.current += 1;
+            }
+            'n' => pending.current += 1,
+            'a' => {
+                pending
+                    .accepted
+                    .extend_from_slice(&pending.matches[current..]);
+                pending.current = remaining;
+            }
+            'l' => {
+                pending.accepted.push(pending.matches[current].clone());
+                pending.current = remaining;
+            }
+            _ => pending.current = remaining,
+        }
+
+        self.confirm_next(ctx);
+
+        Ok(())
+    }
+
+    /// Asks about the next match, or replaces the accepted ones once every match was answered.
+    fn confirm_next(&mut self, ctx: &mut CommandContext<'_>) {
+        let Some(pending) = self.pending.as_ref() else {
+            return;
+        };
+
+        let Some(next) = pending.matches.get(pending.current) else {
+            let pending = self.pending.take().expect("checked above");
+            if !pending.accepted.is_empty() {
+                apply_substitution(ctx, &pending.cursors_before, &pending.accepted);
+            }
+            return;
+        };
+
+        let view = ctx.views.get_mut_active_view();
+        let buffer = ctx
+            .buffers
+            .get(view.buffer_id)
+            .expect("view references non-existing buffer");
+        let position = buffer.content().char_to_point(next.range.start);
+
+        let cursor = view.cursors.first_mut().unwrap();
+        cursor.x = position.x;
+        cursor.y = position.y;
+        cursor.clamp_to_buffer(buffer);
+        ctx.views.scroll_to_cursor(ctx.buffers);
+
+        *ctx.prompt = Some(Prompt::confirm_substitute(&pending.replacement));
+    }
+}
+
+/// Replaces every match as a single undo step, leaving the cursor on the first non-blank character
+/// of the last line that changed.
+fn apply_substitution(
+    ctx: &mut CommandContext<'_>,
+    cursors_before: &[Cursor],
+    matches: &[SubstituteMatch],
+) {
+    let mut transaction = Transaction::new();
+    for m in matches.iter() {
+        transaction.replace(m.range.clone(), m.text.as_str());
+    }
+
+    let last = matches
+        .last()
+        .expect("substitutions have at least one match");
+    let last_change = transaction.map_position(last.range.start, Assoc::Before);
+
+    let buffer_id = ctx.views.get_active_view().buffer_id;
+    ctx.buffers
+        .get_mut(buffer_id)
+        .expect("view references non-existing buffer")
+        .begin_change(cursors_before);
+
+    apply_transaction(ctx, &transaction);
+
+    let view = ctx.views.get_mut_active_view();
+    let buffer = ctx
+        .buffers
+        .get_mut(buffer_id)
+        .expect("view references non-existing buffer");
+    let line_idx = buffer.content().char_to_point(last_change).y;
+    let position = buffer.content().find_first_non_space_character(line_idx);
+
+    let cursor = view.cursors.first_mut().unwrap();
+    cursor.x = position.x;
+    cursor.y = position.y;
+    cursor.clamp_to_buffer(buffer);
+    buffer.commit_changes(&view.cursors);
+    ctx.views.scroll_to_cursor(ctx.buffers);
+
+    *ctx.message = Some(Message::info(report(
+        matches,
+        "substitution",
+        "substitutions",
+    )));
+}
+
+/// Reports how many matches there were and on how many lines, like "3 substitutions on 2 lines".
+fn report(matches: &[SubstituteMatch], singular: &str, plural: &str) -> String {
+    let mut lines = matches.iter().map(|m| m.line_idx).collect::<Vec<_>>();
+    lines.dedup();
+
+    let count = |amount: usize, singular: &str, plural: &str| match amount {
+        1 => format!("1 {singular}"),
+        amount => format!("{amount} {plural}"),
+    };
+
+    format!(
+        "{} on {}",
+        count(matches.len(), singular, plural),
+        count(lines.len(), "line", "lines")
+    )
+}
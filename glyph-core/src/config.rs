@@ -16,8 +16,48 @@ pub enum StatuslineMode {
     Local,
 }
 
+/// Where the `"+` and `"*` registers go.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum ClipboardConfig {
+    /// A clipboard helper for the display server when one is installed, or OSC 52 otherwise and
+    /// always over ssh.
+    #[default]
+    Auto,
+    /// Escape sequences the terminal turns into clipboard writes, it can't read the clipboard.
+    Osc52,
+    WlClipboard,
+    Xclip,
+    /// The registers are only kept inside the editor.
+    None,
+}
+
 #[derive(Debug)]
 pub struct Config {
     pub keymap_preset: KeyMapPreset,
     pub statusline: StatuslineConfig,
+    /// Whether buffers without unsaved changes are reloaded when their file changes on disk.
+    pub autoread: bool,
+    /// Whether searches ignore case.
+    pub ignorecase: bool,
+    /// Whether searches with an uppercase letter match case even when `ignorecase` is set.
+    pub smartcase: bool,
+    /// How many columns a tab character takes.
+    pub tabstop: usize,
+    /// Whether the `Tab` key inserts spaces instead of tab characters.
+    pub expandtab: bool,
+    /// How many columns an indent level takes, `0` uses `tabstop`.
+    pub shiftwidth: usize,
+    /// How many columns the `Tab` key moves to, `0` turns it off and a negative value uses
+    /// `shiftwidth`.
+    pub softtabstop: isize,
+    /// Whether lines wider than a view are broken into several rows instead of scrolling sideways.
+    pub wrap: bool,
+    /// Whether wrapped lines break after a blank rather than in the middle of a word.
+    pub linebreak: bool,
+    /// Whether wrapped rows are indented like the start of their line.
+    pub breakindent: bool,
+    /// Drawn at the start of every wrapped row.
+    pub showbreak: String,
+    /// Which system clipboard the `"+` and `"*` registers use.
+    pub clipboard: ClipboardConfig,
 }
\ No newline at end of file
@@ -1,16 +1,43 @@
 use crate::buffer_manager::Buffer;
+use crate::geometry::Point;
 
-#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+/// How the terminal draws the cursor, so the user can tell what the editor is waiting for.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    /// A vertical line between characters, for when typing inserts text.
+    Bar,
+    /// A line under the character, for when the editor waits for the rest of a command.
+    Underline,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Cursor {
+    /// The column in chars, always at the start of a grapheme cluster.
     pub x: usize,
     pub y: usize,
+    /// The display column vertical moves try to keep, which can be past the end of the line the
+    /// cursor is on. `usize::MAX` keeps the cursor at the end of every line.
     pub virtual_x: usize,
 }
 
 impl Cursor {
-    pub fn move_left_by(&mut self, amount: usize) {
-        self.x = self.x.saturating_sub(amount);
-        self.virtual_x = self.x;
+    /// Moves left by `amount` grapheme clusters, stopping at the start of the line.
+    pub fn move_left_by(&mut self, buffer: &Buffer, amount: usize) {
+        let content = buffer.content();
+        self.x = usize::min(self.x, content.line_len(self.y));
+
+        for _ in 0..amount {
+            if self.x == 0 {
+                break;
+            }
+
+            let char_idx = content.point_to_char(Point::new(self.x, self.y));
+            self.x -= char_idx - content.prev_grapheme_boundary(char_idx);
+        }
+
+        self.update_virtual_x(buffer);
     }
 
     pub fn move_to(&mut self, buffer: &Buffer, x: usize, y: usize) {
@@ -23,7 +50,8 @@ impl Cursor {
 
         self.x = x;
         self.y = y;
-        self.virtual_x = self.x;
+        self.snap_to_grapheme(buffer);
+        self.update_virtual_x(buffer);
     }
 
     pub fn move_to_with_offset(&mut self, buffer: &Buffer, x: usize, y: usize, offset: usize) {
@@ -34,7 +62,35 @@ impl Cursor {
         let line_len = content.line_len(y).saturating_sub(offset);
         self.x = usize::min(x, line_len);
         self.y = y;
-        self.virtual_x = self.x;
+        self.snap_to_grapheme(buffer);
+        self.update_virtual_x(buffer);
+    }
+
+    /// Moves the cursor back into the bounds of `buffer`, onto the last character of its line
+    /// when it's past it. Used when the content changed underneath the cursor.
+    pub fn clamp_to_buffer(&mut self, buffer: &Buffer) {
+        let content = buffer.content();
+        self.y = usize::min(self.y, content.len_lines().saturating_sub(1));
+
+        let has_newline = content.line(self.y).chars().last() == Some('\n');
+        let line_len = content.line_len(self.y) - usize::from(has_newline);
+        self.x = usize::min(self.x, line_len.saturating_sub(1));
+        self.snap_to_grapheme(buffer);
+        self.update_virtual_x(buffer);
+    }
+
+    /// Makes the column the cursor is drawn at the one vertical moves aim for.
+    pub fn update_virtual_x(&mut self, buffer: &Buffer) {
+        self.virtual_x = buffer.display_column(Point::new(self.x, self.y));
+    }
+
+    /// Moves the cursor back to the start of the grapheme cluster it's in, keeping `virtual_x`
+    /// so vertical moves still aim for the same column.
+    pub fn snap_to_grapheme(&mut self, buffer: &Buffer) {
+        self.x = buffer
+            .content()
+            .snap_to_grapheme(Point::new(self.x, self.y))
+            .x;
     }
 
     pub fn move_down_by(&mut self, buffer: &Buffer, amount: usize) {
@@ -48,12 +104,26 @@ impl Cursor {
         self.y = self.y.saturating_sub(amount);
     }
 
+    /// Moves right by `amount` grapheme clusters, stopping `offset` chars before the end of the
+    /// line.
     pub fn move_right_by_with_offset(&mut self, buffer: &Buffer, amount: usize, offset: usize) {
         let content = buffer.content();
         let line_len = content.line_len(self.y);
-        let max_x = line_len.saturating_sub(offset);
-        self.x = usize::min(self.x.saturating_add(amount), max_x);
-        self.virtual_x = self.x;
+        let max_x = content
+            .snap_to_grapheme(Point::new(line_len.saturating_sub(offset), self.y))
+            .x;
+
+        for _ in 0..amount {
+            if self.x >= max_x {
+                break;
+            }
+
+            let char_idx = content.point_to_char(Point::new(self.x, self.y));
+            self.x += content.next_grapheme_boundary(char_idx) - char_idx;
+        }
+
+        self.x = usize::min(self.x, max_x);
+        self.update_virtual_x(buffer);
     }
 
     pub fn move_to_line_start(&mut self) {
@@ -65,4 +135,36 @@ impl Cursor {
         self.move_right_by_with_offset(buffer, usize::MAX, offset);
         self.virtual_x = usize::MAX;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_manager::scratch_buffer;
+
+    #[test]
+    fn test_move_over_graphemes() {
+        // a decomposed é, a Hangul syllable made of jamo and a flag
+        let buffer = scratch_buffer("e\u{301}\u{1112}\u{1161}\u{11ab}\u{1f1e7}\u{1f1f7}\n");
+        let mut cursor = Cursor::default();
+
+        let mut columns = vec![];
+        for _ in 0..4 {
+            cursor.move_right_by_with_offset(&buffer, 1, 2);
+            columns.push(cursor.x);
+        }
+        assert_eq!(columns, vec![2, 5, 5, 5]);
+
+        cursor.move_left_by(&buffer, 1);
+        assert_eq!(cursor.x, 2);
+        cursor.move_left_by(&buffer, 5);
+        assert_eq!(cursor.x, 0);
+
+        // landing inside a cluster moves back to its start
+        cursor.move_to_with_offset(&buffer, 3, 0, 2);
+        assert_eq!(cursor.x, 2);
+        cursor.x = 6;
+        cursor.clamp_to_buffer(&buffer);
+        assert_eq!(cursor.x, 5);
+    }
+}
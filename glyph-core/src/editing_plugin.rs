@@ -1,10 +1,18 @@
 use std::fmt::Debug;
 
 use crate::command_handler::CommandHandler;
+use crate::cursor::CursorShape;
 use crate::key_mapper::Keymapper;
 use crate::status_provider::StatuslineProvider;
 
 pub trait EditingPlugin: StatuslineProvider + Keymapper + Debug {
     fn create_command_handler(&self) -> Box<dyn CommandHandler>;
     fn dock_height(&self) -> u16;
+
+    /// What the plugin wants to show in the dock, such as a command being typed. Messages are
+    /// displayed in the dock when the plugin has nothing to show.
+    fn render_dock(&self) -> Option<String>;
+
+    /// How the cursor is drawn in the active view.
+    fn cursor_shape(&self) -> CursorShape;
 }
\ No newline at end of file
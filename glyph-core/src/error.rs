@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+
+use crate::buffer_manager::{BufferId, Encoding};
 use crate::event_loop::error::EventLoopError;
 use crate::renderer::error::RendererError;
 
@@ -8,6 +11,124 @@ pub enum GlyphError {
     #[error("Failed to canonicalize path: {0}")]
     FailedToCanonicalizePath(#[from] std::io::Error),
 
+    #[error("E32: No file name")]
+    NoFileName,
+
+    #[error("\"{}\" E212: Can't open file for writing: {source}", path.display())]
+    FailedToWrite {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("E13: File exists (add ! to override)")]
+    FileExists,
+
+    #[error("E37: No write since last change for {0} (add ! to override)")]
+    UnsavedChanges(String),
+
+    #[error("\"{}\" E484: Can't open file: {source}", path.display())]
+    FailedToRead {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("\"{}\" Can't decode file, it is not valid in its detected encoding", .0.display())]
+    FailedToDecode(PathBuf),
+
+    #[error("\"{}\" E513: Conversion to {encoding} failed", path.display())]
+    FailedToEncode { path: PathBuf, encoding: Encoding },
+
+    #[error(
+        "\"{}\" WARNING: The file has been changed since reading it (add ! to override)",
+        .0.display()
+    )]
+    FileChangedSinceReading(PathBuf),
+
+    #[error("W11: Warning: File \"{0}\" has changed since editing started (:e! to reload)")]
+    ChangedOnDisk(String),
+
+    #[error(
+        "W12: Warning: File \"{0}\" has changed and the buffer was changed in the editor as well"
+    )]
+    ChangedAndModified(String),
+
+    #[error("E211: File \"{0}\" no longer available")]
+    DeletedOnDisk(String),
+
+    #[error("\"{name}\" could not be loaded completely, opened read-only: {reason}")]
+    FailedToLoad { name: String, reason: String },
+
+    #[error("\"{0}\" is still loading")]
+    StillLoading(String),
+
+    #[error("E86: Buffer {0} does not exist")]
+    NoSuchBuffer(u64),
+
+    #[error("E94: No matching buffer for {0}")]
+    NoMatchingBuffer(String),
+
+    #[error("E93: More than one match for {0}")]
+    MultipleMatchingBuffers(String),
+
+    #[error("E89: No write since last change for buffer {0} (add ! to override)")]
+    UnsavedBuffer(BufferId),
+
+    #[error("E23: No alternate file")]
+    NoAlternateBuffer,
+
+    #[error("E45: 'readonly' option is set (add ! to override)")]
+    ReadOnly,
+
+    #[error("E306: Cannot use swap file \"{}\": {source}", path.display())]
+    FailedToAccessSwap {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("E518: Unknown option: {0}")]
+    UnknownOption(String),
+
+    #[error("E474: Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("E521: Number required after =: {0}")]
+    NumberRequired(String),
+
+    #[error("E487: Argument must be positive: {0}")]
+    ArgumentMustBePositive(String),
+
+    #[error("E383: Invalid search string: {0}")]
+    InvalidPattern(String),
+
+    #[error("E486: Pattern not found: {0}")]
+    PatternNotFound(String),
+
+    #[error("E35: No previous regular expression")]
+    NoPreviousPattern,
+
+    #[error("E348: No string under cursor")]
+    NoStringUnderCursor,
+
+    #[error("E16: Invalid range: {0}")]
+    InvalidRange(String),
+
+    #[error("E20: Mark not set")]
+    MarkNotSet,
+
+    #[error("E488: Trailing characters: {0}")]
+    TrailingCharacters(String),
+
+    #[error("E354: Invalid register name: '{0}'")]
+    InvalidRegister(char),
+
+    #[error("Failed to set the clipboard with {provider}: {reason}")]
+    ClipboardFailed { provider: String, reason: String },
+
+    #[error("E353: Nothing in register {0}")]
+    EmptyRegister(char),
+
     #[error(transparent)]
     RendererError(#[from] RendererError),
 
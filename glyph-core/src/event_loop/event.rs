@@ -8,6 +8,8 @@
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
 pub enum Event {
     Key(KeyEvent),
+    FocusGained,
+    FocusLost,
 }
 
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
@@ -39,6 +41,8 @@ impl std::fmt::Display for KeyEvent {
         }
 
         let (base_str, always_wrap) = match self.code {
+            // a bare `<` would read as the start of a special key, vim writes it as `<lt>`
+            KeyCode::Char('<') => ("lt".into(), true),
             KeyCode::Char(c) => (c.to_string(), false),
 
             KeyCode::Backspace => ("bs".into(), true),
@@ -0,0 +1,254 @@
+use crate::cursor::Cursor;
+use crate::text_object::Change;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Revision {
+    parent: usize,
+    /// The child that was most recently created or visited, which is where redo goes to.
+    last_child: Option<usize>,
+    changes: Vec<Change>,
+    cursors_before: Vec<Cursor>,
+    cursors_after: Vec<Cursor>,
+}
+
+/// The outcome of moving through the history: the changes that must be applied to the content, in
+/// order, and where the cursors should be placed afterwards.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HistoryStep {
+    pub changes: Vec<Change>,
+    pub cursors: Vec<Cursor>,
+}
+
+/// An undo tree.
+///
+/// Revisions are stored in the order they were created, so a parent always has a smaller index
+/// than its children. Undo and redo walk the tree through parents and the most recently visited
+/// child, while [`History::earlier`] and [`History::later`] move chronologically between
+/// revisions, reaching branches that would otherwise be lost after undoing and making a new change.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct History {
+    revisions: Vec<Revision>,
+    current: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        let root = Revision {
+            parent: 0,
+            last_child: None,
+            changes: vec![],
+            cursors_before: vec![],
+            cursors_after: vec![],
+        };
+
+        Self {
+            revisions: vec![root],
+            current: 0,
+        }
+    }
+
+    pub fn current_revision(&self) -> usize {
+        self.current
+    }
+
+    pub fn commit(
+        &mut self,
+        changes: Vec<Change>,
+        cursors_before: Vec<Cursor>,
+        cursors_after: Vec<Cursor>,
+    ) {
+        if changes.is_empty() {
+            return;
+        }
+
+        let revision_idx = self.revisions.len();
+        self.revisions.push(Revision {
+            parent: self.current,
+            last_child: None,
+            changes,
+            cursors_before,
+            cursors_after,
+        });
+
+        self.revisions[self.current].last_child = Some(revision_idx);
+        self.current = revision_idx;
+    }
+
+    pub fn undo(&mut self) -> Option<HistoryStep> {
+        if self.current == 0 {
+            return None;
+        }
+
+        let revision = &self.revisions[self.current];
+        let step = HistoryStep {
+            changes: revision.changes.iter().rev().map(Change::invert).collect(),
+            cursors: revision.cursors_before.clone(),
+        };
+
+        self.current = revision.parent;
+        Some(step)
+    }
+
+    pub fn redo(&mut self) -> Option<HistoryStep> {
+        let child = self.revisions[self.current].last_child?;
+        let revision = &self.revisions[child];
+        let step = HistoryStep {
+            changes: revision.changes.clone(),
+            cursors: revision.cursors_after.clone(),
+        };
+
+        self.current = child;
+        Some(step)
+    }
+
+    /// Moves `steps` revisions back in time, regardless of which branch they are on.
+    pub fn earlier(&mut self, steps: usize) -> Option<HistoryStep> {
+        let target = self.current.saturating_sub(steps);
+        if target == self.current {
+            return None;
+        }
+
+        Some(self.jump_to(target))
+    }
+
+    /// Moves `steps` revisions forward in time, regardless of which branch they are on.
+    pub fn later(&mut self, steps: usize) -> Option<HistoryStep> {
+        let last_revision = self.revisions.len() - 1;
+        let target = usize::min(self.current.saturating_add(steps), last_revision);
+        if target == self.current {
+            return None;
+        }
+
+        Some(self.jump_to(target))
+    }
+
+    fn jump_to(&mut self, target: usize) -> HistoryStep {
+        let mut undo_path = vec![];
+        let mut redo_path = vec![];
+        let mut from = self.current;
+        let mut to = target;
+
+        // parents always come before their children, so walking up from whichever side is further
+        // along meets at the lowest common ancestor
+        while from != to {
+            if from > to {
+                undo_path.push(from);
+                from = self.revisions[from].parent;
+            } else {
+                redo_path.push(to);
+                to = self.revisions[to].parent;
+            }
+        }
+
+        let mut changes = vec![];
+        for &idx in undo_path.iter() {
+            changes.extend(self.revisions[idx].changes.iter().rev().map(Change::invert));
+        }
+
+        for &idx in redo_path.iter().rev() {
+            let parent = self.revisions[idx].parent;
+            self.revisions[parent].last_child = Some(idx);
+            changes.extend(self.revisions[idx].changes.iter().cloned());
+        }
+
+        let cursors = match (redo_path.is_empty(), undo_path.last()) {
+            (false, _) => self.revisions[target].cursors_after.clone(),
+            (true, Some(&last_undone)) => self.revisions[last_undone].cursors_before.clone(),
+            (true, None) => vec![],
+        };
+
+        self.current = target;
+        HistoryStep { changes, cursors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(char_idx: usize, text: &str) -> Vec<Change> {
+        vec![Change::Insert {
+            char_idx,
+            text: text.into(),
+        }]
+    }
+
+    fn cursor(x: usize) -> Vec<Cursor> {
+        vec![Cursor {
+            x,
+            y: 0,
+            virtual_x: x,
+        }]
+    }
+
+    #[test]
+    fn test_undo_redo_linear() {
+        let mut history = History::new();
+        history.commit(insert(0, "a"), cursor(0), cursor(1));
+        history.commit(insert(1, "b"), cursor(1), cursor(2));
+
+        let step = history.undo().unwrap();
+        assert_eq!(step.changes, vec![insert(1, "b")[0].invert()]);
+        assert_eq!(step.cursors, cursor(1));
+        assert_eq!(history.current_revision(), 1);
+
+        let step = history.redo().unwrap();
+        assert_eq!(step.changes, insert(1, "b"));
+        assert_eq!(step.cursors, cursor(2));
+        assert!(history.redo().is_none());
+
+        history.undo();
+        history.undo();
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn test_redo_follows_newest_branch() {
+        let mut history = History::new();
+        history.commit(insert(0, "a"), cursor(0), cursor(1));
+        history.undo();
+        history.commit(insert(0, "b"), cursor(0), cursor(1));
+        history.undo();
+
+        let step = history.redo().unwrap();
+        assert_eq!(step.changes, insert(0, "b"));
+        assert_eq!(history.current_revision(), 2);
+    }
+
+    #[test]
+    fn test_earlier_and_later_cross_branches() {
+        let mut history = History::new();
+        history.commit(insert(0, "a"), cursor(0), cursor(1));
+        history.undo();
+        history.commit(insert(0, "b"), cursor(0), cursor(1));
+
+        // going back in time from the "b" branch reaches the abandoned "a" branch
+        let step = history.earlier(1).unwrap();
+        assert_eq!(
+            step.changes,
+            vec![insert(0, "b")[0].invert(), insert(0, "a")[0].clone()]
+        );
+        assert_eq!(step.cursors, cursor(1));
+        assert_eq!(history.current_revision(), 1);
+
+        // redo now follows the branch that was jumped into
+        history.undo();
+        assert_eq!(history.redo().unwrap().changes, insert(0, "a"));
+
+        let step = history.later(1).unwrap();
+        assert_eq!(
+            step.changes,
+            vec![insert(0, "a")[0].invert(), insert(0, "b")[0].clone()]
+        );
+        assert!(history.later(1).is_none());
+
+        history.earlier(usize::MAX);
+        assert_eq!(history.current_revision(), 0);
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::Debug;
 
 use crate::event_loop::event::Event;
+use crate::prompt::PromptKind;
+use crate::register::Registers;
+use crate::text_object::{CharSearch, SearchDirection, TextObjectKind, TextObjectScope};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum EditorMode {
@@ -26,6 +29,7 @@ pub enum VimMode {
     Insert,
     Visual,
     Command,
+    Search,
 }
 
 impl std::fmt::Display for VimMode {
@@ -35,21 +39,50 @@ impl std::fmt::Display for VimMode {
             Self::Insert => write!(f, "insert"),
             Self::Visual => write!(f, "visual"),
             Self::Command => write!(f, "command"),
+            Self::Search => write!(f, "search"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy)]
+/// What an operator does to the text its motion moves over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Operator {
+    /// `d`
+    Delete,
+    /// `c`, deletes the text and starts inserting in its place.
+    Change,
+    /// `y`
+    Yank,
+    /// `>`
+    Indent,
+    /// `<`
+    Outdent,
+    /// `=`, indents lines by how deep they are nested in brackets.
+    Reindent,
+}
+
+/// How a motion covers text: a run of characters, whole lines, or the same columns of every line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MotionType {
+    Charwise,
+    Linewise,
+    Blockwise,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Command {
     MoveCursorLeft,
     MoveCursorDown,
     MoveCursorUp,
+    /// Moves down a row of the view, which is only part of the line when lines wrap.
+    MoveCursorDisplayDown,
+    /// Moves up a row of the view, which is only part of the line when lines wrap.
+    MoveCursorDisplayUp,
     MoveCursorRight,
     MoveCursorRightOverLines,
     MoveCursorLineStart,
     MoveCursorLineEnd,
     MoveToMatchingPair,
-    DeleteWholeLine,
     MoveToTop,
     MoveToBottom,
     MoveToFirstNonSpace,
@@ -59,20 +92,146 @@ pub enum Command {
     DeletePrevChar,
     DeleteCurrChar,
     MoveToNextWord,
+    MoveToNextBigWord,
+    MoveToPrevWord,
+    MoveToPrevBigWord,
+    MoveToWordEnd,
+    MoveToBigWordEnd,
+    MoveToPrevWordEnd,
+    MoveToPrevBigWordEnd,
+    /// Moves to a character on the cursor line, and remembers the search for `;` and `,`.
+    FindChar(CharSearch),
+    /// Repeats the last character search.
+    RepeatCharSearch,
+    /// Repeats the last character search in the opposite direction.
+    RepeatCharSearchReversed,
+    /// Applies `operator` to the text from the cursor to where `motion` moves it, or to the text
+    /// object it selects. `force` is set by `v`, `V` or `<c-v>` typed before the motion.
+    Operate {
+        operator: Operator,
+        motion: Box<Command>,
+        force: Option<MotionType>,
+    },
+    /// A text object, only meaningful as the motion of an operator.
+    SelectTextObject {
+        kind: TextObjectKind,
+        scope: TextObjectScope,
+    },
+    /// The cursor line and the lines below it, the motion of a doubled operator like `dd`.
+    SelectLines,
+    /// Puts the text of a register after the cursor, or before it. The cursor ends up on the
+    /// text put, or right after it with `cursor_after`.
+    Put {
+        before: bool,
+        cursor_after: bool,
+    },
+    /// Types the text of a register, for `<c-r>` in insert mode.
+    InsertRegister(char),
+    ListRegisters,
     TypeChar(char),
+    /// Inserts a tab, or spaces when `expandtab` or `softtabstop` are set.
+    InsertTab,
+    Undo,
+    Redo,
+    Earlier,
+    Later,
     Save,
+    /// Saves even when the file was changed by something else since it was read.
+    ForceSave,
     SaveAll,
+    /// Saves the active buffer only when it has unsaved changes.
+    Update,
+    /// Points the active buffer to another file, without writing it.
+    Rename(String),
+    /// Points the active buffer to another file and writes it there. Refuses to overwrite a file
+    /// that exists unless `force` is set.
+    SaveAs {
+        path: String,
+        force: bool,
+    },
     PageUp,
     PageDown,
     Quit,
+    /// Quits even when there are buffers with unsaved changes.
+    ForceQuit,
+    /// Reloads the active buffer from disk, refusing when it has unsaved changes.
+    Reload,
+    /// Reloads the active buffer from disk, discarding unsaved changes.
+    ForceReload,
+    /// Checks whether files were changed outside of the editor.
+    CheckTime,
+    /// Opens a file, or switches to its buffer when it is already open.
+    Edit(String),
+    NextBuffer,
+    PrevBuffer,
+    /// Switches to a buffer by number or by part of its name.
+    SwitchBuffer(String),
+    /// Switches to the buffer that was shown before the current one.
+    AlternateBuffer,
+    /// Closes a buffer, the current one when none is given. Refuses to close a buffer with
+    /// unsaved changes unless `force` is set.
+    DeleteBuffer {
+        buffer: Option<String>,
+        force: bool,
+    },
+    ListBuffers,
+    /// Searches for `pattern`, or for the last pattern when it's empty.
+    Search {
+        pattern: String,
+        direction: SearchDirection,
+    },
+    /// Moves the cursor to the first match of a pattern that is still being typed, from where the
+    /// search started.
+    PreviewSearch {
+        pattern: String,
+        direction: SearchDirection,
+    },
+    /// Abandons the search being typed, moving the cursor back to where it started.
+    CancelSearch,
+    /// Stops highlighting the matches of the last pattern until the next search.
+    ClearSearchHighlight,
+    /// Repeats the last search in the same direction.
+    SearchNext,
+    /// Repeats the last search in the opposite direction.
+    SearchPrev,
+    /// Searches for the whole word under or after the cursor.
+    SearchWordUnderCursor(SearchDirection),
+    /// Replaces matches of `pattern` in a range of lines, with the range and flags as typed by the
+    /// user. An empty pattern uses the last search pattern.
+    Substitute {
+        range: String,
+        pattern: String,
+        replacement: String,
+        flags: String,
+    },
+    /// The user picked `answer` on a prompt.
+    AnswerPrompt {
+        kind: PromptKind,
+        answer: char,
+    },
+    /// Changes an option, `value` is omitted for boolean options.
+    SetOption {
+        name: String,
+        value: Option<String>,
+    },
 }
 
 pub struct ResolvedKeymap {
     pub commands: Vec<Command>,
     pub mode: Option<EditorMode>,
+    /// How many times the commands are repeated, or how far they go, when a count was typed.
+    pub count: Option<usize>,
+    /// The register the commands read from or write to, when one was typed like `"a`.
+    pub register: Option<char>,
 }
 
 pub trait Keymapper: Debug {
-    fn parse_event(&mut self, event: Option<Event>) -> Option<ResolvedKeymap>;
+    /// Turns an event into commands. `registers` are there for keymaps that type the content of
+    /// a register, like `<c-r>` on the command line.
+    fn parse_event(
+        &mut self,
+        event: Option<Event>,
+        registers: &Registers,
+    ) -> Option<ResolvedKeymap>;
     fn mode(&self) -> EditorMode;
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 pub mod buffer_manager;
+pub mod clipboard;
 pub mod command_handler;
 pub mod config;
 pub mod cursor;
@@ -8,27 +9,46 @@ pub mod editing_plugin;
 pub mod error;
 pub mod event_loop;
 pub mod geometry;
+pub mod history;
 pub mod key_mapper;
+pub mod message;
+pub mod options;
+pub mod prompt;
+pub mod register;
 pub mod renderer;
+pub mod search;
 pub mod startup_options;
 pub mod status_provider;
+pub mod substitute;
 pub mod text_object;
 pub mod view_manager;
 
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use command_handler::{CommandContext, CommandHandler, CommandHandlerChain};
 
-use crate::buffer_manager::{BufferId, BufferManager};
+use crate::buffer_manager::{BufferId, BufferListener, BufferManager};
+use crate::clipboard::ClipboardProvider;
 use crate::config::Config;
 use crate::editing_plugin::EditingPlugin;
 use crate::error::Result;
 use crate::event_loop::EventLoop;
+use crate::event_loop::event::{Event, KeyCode, KeyEvent};
+use crate::key_mapper::{Command, ResolvedKeymap};
+use crate::message::Message;
+use crate::options::EditorOptions;
+use crate::prompt::Prompt;
+use crate::register::Registers;
 use crate::renderer::{RenderContext, Renderer};
+use crate::search::{CompiledPattern, MatchIndex, SearchState};
 use crate::startup_options::StartupOptions;
 use crate::view_manager::ViewManager;
 
+/// How often the journal of modified buffers is persisted to their swap files.
+const SWAP_FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
 pub enum EditorKind {
     Modal,
     NonModal,
@@ -44,6 +64,14 @@ where
     event_loop: E,
     config: Arc<Config>,
     should_quit: bool,
+    message: Option<Message>,
+    prompt: Option<Prompt>,
+    options: EditorOptions,
+    search: SearchState,
+    registers: Registers,
+    compiled_search: CompiledPattern,
+    match_index: MatchIndex,
+    last_swap_flush: Instant,
     views: ViewManager,
     buffers: BufferManager,
     editing_plugin: Box<dyn EditingPlugin>,
@@ -63,6 +91,7 @@ where
         options: StartupOptions,
     ) -> Result<Self> {
         let mut buffers = BufferManager::new();
+        buffers.set_tabstop(config.tabstop);
         let size = renderer.get_size(editing_plugin.dock_height())?;
 
         if !options.files.is_empty() {
@@ -75,6 +104,12 @@ where
             buffers.load_startup_buffer(size)?;
         }
 
+        if options.recover {
+            for buffer in buffers.iter_mut() {
+                buffer.recover_from_swap()?;
+            }
+        }
+
         // When the editor starts, it is guaranteed to have at least one buffer. Which will either
         // be a scratch buffer with a welcome message, the first user specified file or the
         // directory view.
@@ -82,18 +117,32 @@ where
 
         let command_handler = editing_plugin.create_command_handler();
         let file_command_handler = command_handler::FileCommandHandler;
+        let buffer_command_handler = command_handler::BufferCommandHandler;
+        let substitute_command_handler = command_handler::SubstituteCommandHandler::default();
+        let option_command_handler = command_handler::OptionCommandHandler;
         let mut command_handler_chain = CommandHandlerChain::default();
+        command_handler_chain.add_handler(Box::new(option_command_handler));
         command_handler_chain.add_handler(Box::new(file_command_handler));
+        command_handler_chain.add_handler(Box::new(substitute_command_handler));
+        command_handler_chain.add_handler(Box::new(buffer_command_handler));
         command_handler_chain.add_handler(command_handler);
 
         Ok(Self {
             views,
+            options: EditorOptions::from(config.as_ref()),
+            search: SearchState::default(),
+            registers: Registers::default(),
+            compiled_search: CompiledPattern::default(),
+            match_index: MatchIndex::default(),
             config,
             buffers,
             renderer,
             event_loop,
             editing_plugin,
             should_quit: false,
+            message: None,
+            prompt: None,
+            last_swap_flush: Instant::now(),
             command_handler_chain,
         })
     }
@@ -102,26 +151,115 @@ where
         self.renderer.setup()?;
 
         while !self.should_quit {
+            if self.prompt.is_none() {
+                self.prompt = self
+                    .buffers
+                    .next_swap_conflict()
+                    .and_then(Prompt::swap_exists);
+            }
+
             let event = self.event_loop.maybe_event()?;
 
-            if let Some(resolved_keymap) = self.editing_plugin.parse_event(event) {
+            if matches!(event, Some(Event::Key(_))) {
+                self.message = None;
+            }
+
+            let resolved_keymap = match event {
+                Some(Event::Key(key)) if self.prompt.is_some() => self.answer_prompt(key),
+                // files may have been changed while the user was away
+                Some(Event::FocusGained) => Some(ResolvedKeymap {
+                    commands: vec![Command::CheckTime],
+                    mode: Some(self.editing_plugin.mode()),
+                    count: None,
+                    register: None,
+                }),
+                Some(Event::FocusLost) => None,
+                event => {
+                    self.refresh_registers();
+                    self.editing_plugin.parse_event(event, &self.registers)
+                }
+            };
+
+            if let Some(resolved_keymap) = resolved_keymap {
                 self.command_handler_chain
                     .handle_commands(&mut CommandContext {
                         resolved_keymap: &resolved_keymap,
-                        buffers: &mut self.buffers.buffers,
+                        buffers: &mut self.buffers,
                         views: &mut self.views,
                         should_quit: &mut self.should_quit,
+                        message: &mut self.message,
+                        options: &mut self.options,
+                        search: &mut self.search,
+                        registers: &mut self.registers,
+                        prompt: &mut self.prompt,
                     });
             }
 
+            if let Err(e) = self.buffers.poll_loaders() {
+                self.message = Some(Message::error(e.to_string()));
+            }
+
+            self.buffers.notify_listeners();
+
+            if self.last_swap_flush.elapsed() >= SWAP_FLUSH_INTERVAL {
+                self.buffers.flush_swap_files();
+                self.last_swap_flush = Instant::now();
+            }
+
             self.render_step()?;
         }
 
+        self.buffers.remove_swap_files();
         self.renderer.shutdown()?;
 
         Ok(())
     }
 
+    /// Registers a subsystem that wants to follow the changes made to buffers.
+    pub fn add_buffer_listener(&mut self, listener: Box<dyn BufferListener>) {
+        self.buffers.subscribe(listener);
+    }
+
+    /// Backs the `"+` and `"*` registers with the system clipboard.
+    pub fn set_clipboard_provider(&mut self, provider: Box<dyn ClipboardProvider>) {
+        self.registers.set_clipboard_provider(provider);
+    }
+
+    /// Brings the registers that mirror the editor, the last search and the current file name, up
+    /// to date before keymaps or commands read them.
+    fn refresh_registers(&mut self) {
+        let view = self.views.get_active_view();
+        let file_name = self
+            .buffers
+            .get(view.buffer_id)
+            .and_then(|buffer| buffer.path())
+            .map(|path| path.display().to_string());
+
+        self.registers
+            .refresh(self.search.pattern.as_deref(), file_name);
+    }
+
+    fn answer_prompt(&mut self, key: KeyEvent) -> Option<ResolvedKeymap> {
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+
+        let prompt = self.prompt.as_ref()?;
+        let choice = prompt.choice(c.to_ascii_lowercase())?;
+        let command = Command::AnswerPrompt {
+            kind: prompt.kind,
+            answer: choice.key,
+        };
+        self.prompt = None;
+
+        Some(ResolvedKeymap {
+            commands: vec![command],
+            mode: Some(self.editing_plugin.mode()),
+            count: None,
+            register: None,
+        })
+    }
+
     fn render_step(&mut self) -> Result<()> {
         let views = self.views.get_visible();
         let buffers = views
@@ -129,11 +267,27 @@ where
             .map(|v| self.buffers.get(v.buffer_id).unwrap())
             .collect::<Vec<_>>();
 
+        let search_highlight = self
+            .search
+            .highlighted(&self.options, &mut self.compiled_search);
+        let view = self.views.get_active_view();
+        let buffer = self.buffers.get(view.buffer_id).unwrap();
+        let search_count = search_highlight
+            .filter(|_| !buffer.is_large())
+            .map(|pattern| {
+                self.match_index
+                    .count(buffer, pattern, view.cursors.first().unwrap())
+            });
+
         self.renderer.render(&mut RenderContext {
             mode: self.editing_plugin.mode(),
             views: &self.views,
             buffers: &buffers,
             layout: &self.views.layout,
+            message: self.message.as_ref(),
+            prompt: self.prompt.as_ref(),
+            search_highlight,
+            search_count,
             editing_plugin: self.editing_plugin.as_ref(),
         })?;
 
@@ -0,0 +1,284 @@
+use crate::buffer_manager::{Encoding, LineEnding};
+use crate::config::Config;
+use crate::error::{GlyphError, Result};
+
+/// An option that can be changed while the editor is running.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EditorOption {
+    /// The line ending used when writing the active buffer.
+    FileFormat(LineEnding),
+    /// The encoding used when writing the active buffer.
+    FileEncoding(Encoding),
+    /// Whether the active buffer is written with a byte order mark.
+    Bomb(bool),
+    /// Whether buffers without unsaved changes are reloaded when their file changes on disk.
+    Autoread(bool),
+    /// Whether searches ignore case.
+    IgnoreCase(bool),
+    /// Whether searches with an uppercase letter match case even when `ignorecase` is set.
+    SmartCase(bool),
+    /// How many columns a tab character takes.
+    TabStop(usize),
+    /// Whether the `Tab` key inserts spaces instead of tab characters.
+    ExpandTab(bool),
+    /// How many columns an indent level takes, `0` uses `tabstop`.
+    ShiftWidth(usize),
+    /// How many columns the `Tab` key moves to, `0` turns it off and a negative value uses
+    /// `shiftwidth`.
+    SoftTabStop(isize),
+    /// Whether lines wider than the active view are broken into several rows.
+    Wrap(bool),
+    /// Whether wrapped lines break after a blank rather than in the middle of a word.
+    LineBreak(bool),
+    /// Whether wrapped rows are indented like the start of their line.
+    BreakIndent(bool),
+    /// Drawn at the start of every wrapped row.
+    ShowBreak(String),
+}
+
+/// How many columns a tab character takes by default.
+pub const DEFAULT_TABSTOP: usize = 8;
+
+/// Options that apply to the whole editor rather than to a single buffer.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EditorOptions {
+    pub autoread: bool,
+    pub ignorecase: bool,
+    pub smartcase: bool,
+    pub tabstop: usize,
+    pub expandtab: bool,
+    pub shiftwidth: usize,
+    pub softtabstop: isize,
+}
+
+impl Default for EditorOptions {
+    fn default() -> Self {
+        Self {
+            autoread: false,
+            ignorecase: false,
+            smartcase: false,
+            tabstop: DEFAULT_TABSTOP,
+            expandtab: false,
+            shiftwidth: DEFAULT_TABSTOP,
+            softtabstop: 0,
+        }
+    }
+}
+
+impl EditorOptions {
+    /// How many columns an indent level takes.
+    pub fn shift_width(&self) -> usize {
+        match self.shiftwidth {
+            0 => self.tabstop,
+            shiftwidth => shiftwidth,
+        }
+    }
+
+    /// How many columns the `Tab` key moves to, or `None` when it inserts a tab character as is.
+    pub fn soft_tab_width(&self) -> Option<usize> {
+        match self.softtabstop {
+            0 => None,
+            ..0 => Some(self.shift_width()),
+            softtabstop => Some(softtabstop as usize),
+        }
+    }
+
+    /// Whether a search for `pattern` ignores case, following `ignorecase` and `smartcase`.
+    pub fn search_ignores_case(&self, pattern: &str) -> bool {
+        self.ignorecase && !(self.smartcase && pattern.chars().any(char::is_uppercase))
+    }
+}
+
+impl From<&Config> for EditorOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            autoread: config.autoread,
+            ignorecase: config.ignorecase,
+            smartcase: config.smartcase,
+            tabstop: config.tabstop,
+            expandtab: config.expandtab,
+            shiftwidth: config.shiftwidth,
+            softtabstop: config.softtabstop,
+        }
+    }
+}
+
+impl EditorOption {
+    /// Parses an option from its name and value, as typed by the user.
+    ///
+    /// Boolean options are given without a value, and may be prefixed by `no` to disable them.
+    pub fn parse(name: &str, value: Option<&str>) -> Result<Self> {
+        match name {
+            "fileformat" | "ff" => {
+                let value = expect_value(name, value)?;
+                value
+                    .parse()
+                    .map(Self::FileFormat)
+                    .map_err(|_| invalid_argument(name, value))
+            }
+            "fileencoding" | "fenc" => {
+                let value = expect_value(name, value)?;
+                value
+                    .parse()
+                    .map(Self::FileEncoding)
+                    .map_err(|_| invalid_argument(name, value))
+            }
+            "bomb" => Ok(Self::Bomb(true)),
+            "nobomb" => Ok(Self::Bomb(false)),
+            "autoread" | "ar" => Ok(Self::Autoread(true)),
+            "noautoread" | "noar" => Ok(Self::Autoread(false)),
+            "ignorecase" | "ic" => Ok(Self::IgnoreCase(true)),
+            "noignorecase" | "noic" => Ok(Self::IgnoreCase(false)),
+            "smartcase" | "scs" => Ok(Self::SmartCase(true)),
+            "nosmartcase" | "noscs" => Ok(Self::SmartCase(false)),
+            "tabstop" | "ts" => match parse_number(name, value)? {
+                0 => Err(GlyphError::ArgumentMustBePositive(format!("{name}=0"))),
+                tabstop => Ok(Self::TabStop(tabstop as usize)),
+            },
+            "expandtab" | "et" => Ok(Self::ExpandTab(true)),
+            "noexpandtab" | "noet" => Ok(Self::ExpandTab(false)),
+            "shiftwidth" | "sw" => match parse_number(name, value)? {
+                ..0 => Err(GlyphError::ArgumentMustBePositive(format!(
+                    "{name}={}",
+                    value.unwrap_or_default()
+                ))),
+                shiftwidth => Ok(Self::ShiftWidth(shiftwidth as usize)),
+            },
+            "softtabstop" | "sts" => parse_number(name, value).map(Self::SoftTabStop),
+            "wrap" => Ok(Self::Wrap(true)),
+            "nowrap" => Ok(Self::Wrap(false)),
+            "linebreak" | "lbr" => Ok(Self::LineBreak(true)),
+            "nolinebreak" | "nolbr" => Ok(Self::LineBreak(false)),
+            "breakindent" | "bri" => Ok(Self::BreakIndent(true)),
+            "nobreakindent" | "nobri" => Ok(Self::BreakIndent(false)),
+            "showbreak" | "sbr" => Ok(Self::ShowBreak(value.unwrap_or_default().to_string())),
+            _ => Err(GlyphError::UnknownOption(name.to_string())),
+        }
+    }
+}
+
+fn expect_value<'a>(name: &str, value: Option<&'a str>) -> Result<&'a str> {
+    value.ok_or_else(|| GlyphError::InvalidArgument(name.to_string()))
+}
+
+fn parse_number(name: &str, value: Option<&str>) -> Result<isize> {
+    let value = expect_value(name, value)?;
+    value
+        .parse()
+        .map_err(|_| GlyphError::NumberRequired(format!("{name}={value}")))
+}
+
+fn invalid_argument(name: &str, value: &str) -> GlyphError {
+    GlyphError::InvalidArgument(format!("{name}={value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_format() {
+        assert_eq!(
+            EditorOption::parse("ff", Some("dos")).unwrap(),
+            EditorOption::FileFormat(LineEnding::Crlf)
+        );
+        assert_eq!(
+            EditorOption::parse("fileformat", Some("mac")).unwrap(),
+            EditorOption::FileFormat(LineEnding::Cr)
+        );
+        assert!(EditorOption::parse("ff", Some("windows")).is_err());
+        assert!(EditorOption::parse("ff", None).is_err());
+        assert!(EditorOption::parse("nonsense", None).is_err());
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert_eq!(
+            EditorOption::parse("fenc", Some("latin1")).unwrap(),
+            EditorOption::FileEncoding(Encoding::Latin1)
+        );
+        assert_eq!(
+            EditorOption::parse("nobomb", None).unwrap(),
+            EditorOption::Bomb(false)
+        );
+    }
+
+    #[test]
+    fn test_parse_autoread() {
+        assert_eq!(
+            EditorOption::parse("ar", None).unwrap(),
+            EditorOption::Autoread(true)
+        );
+        assert_eq!(
+            EditorOption::parse("noautoread", None).unwrap(),
+            EditorOption::Autoread(false)
+        );
+    }
+
+    #[test]
+    fn test_search_case() {
+        assert_eq!(
+            EditorOption::parse("noic", None).unwrap(),
+            EditorOption::IgnoreCase(false)
+        );
+        assert_eq!(
+            EditorOption::parse("smartcase", None).unwrap(),
+            EditorOption::SmartCase(true)
+        );
+
+        let mut options = EditorOptions {
+            ignorecase: true,
+            ..Default::default()
+        };
+        assert!(options.search_ignores_case("Foo"));
+
+        options.smartcase = true;
+        assert!(options.search_ignores_case("foo"));
+        assert!(!options.search_ignores_case("Foo"));
+    }
+
+    #[test]
+    fn test_tab_options() {
+        assert_eq!(
+            EditorOption::parse("ts", Some("4")).unwrap(),
+            EditorOption::TabStop(4)
+        );
+        assert_eq!(
+            EditorOption::parse("sts", Some("-1")).unwrap(),
+            EditorOption::SoftTabStop(-1)
+        );
+        assert!(EditorOption::parse("ts", Some("0")).is_err());
+        assert!(EditorOption::parse("sw", Some("four")).is_err());
+
+        let mut options = EditorOptions {
+            tabstop: 4,
+            shiftwidth: 0,
+            ..Default::default()
+        };
+        assert_eq!(options.shift_width(), 4);
+        assert_eq!(options.soft_tab_width(), None);
+
+        options.softtabstop = -1;
+        assert_eq!(options.soft_tab_width(), Some(4));
+    }
+
+    #[test]
+    fn test_wrap_options() {
+        assert_eq!(
+            EditorOption::parse("nowrap", None).unwrap(),
+            EditorOption::Wrap(false)
+        );
+        assert_eq!(
+            EditorOption::parse("lbr", None).unwrap(),
+            EditorOption::LineBreak(true)
+        );
+        assert_eq!(
+            EditorOption::parse("sbr", Some("> ")).unwrap(),
+            EditorOption::ShowBreak("> ".to_string())
+        );
+        assert_eq!(
+            EditorOption::parse("showbreak", None).unwrap(),
+            EditorOption::ShowBreak(String::new())
+        );
+    }
+}
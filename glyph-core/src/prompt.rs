@@ -0,0 +1,92 @@
+use crate::buffer_manager::{Buffer, BufferId};
+
+/// What a prompt is asking about, sent back with the answer so the command handlers know what to
+/// do with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PromptKind {
+    /// A swap file from another session was found when opening the buffer.
+    SwapExists(BufferId),
+    /// A substitution with the `c` flag asks before replacing each match.
+    ConfirmSubstitute,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PromptChoice {
+    pub key: char,
+    pub label: String,
+}
+
+/// A question the editor asks the user, shown in the dock and answered by pressing the key of one
+/// of its choices. Every other key is ignored while a prompt is open.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub question: String,
+    pub choices: Vec<PromptChoice>,
+}
+
+impl Prompt {
+    /// Asks what to do with the swap file left behind for `buffer` by another session.
+    pub fn swap_exists(buffer: &Buffer) -> Option<Self> {
+        let info = buffer.swap_conflict()?;
+        let owner = match info.is_running() {
+            true => format!("pid {}, still running", info.pid),
+            false => format!("pid {}", info.pid),
+        };
+
+        Some(Self {
+            kind: PromptKind::SwapExists(buffer.id),
+            question: format!(
+                "Swap file found for \"{}\" ({owner})",
+                buffer.display_name()
+            ),
+            choices: vec![
+                PromptChoice::new('r', "[r]ecover"),
+                PromptChoice::new('o', "[o]pen read-only"),
+                PromptChoice::new('d', "[d]elete it"),
+                PromptChoice::new('q', "[q]uit"),
+            ],
+        })
+    }
+
+    /// Asks whether the match under the cursor should be replaced.
+    pub fn confirm_substitute(replacement: &str) -> Self {
+        Self {
+            kind: PromptKind::ConfirmSubstitute,
+            question: format!("replace with {replacement}"),
+            choices: vec![
+                PromptChoice::new('y', "[y]es"),
+                PromptChoice::new('n', "[n]o"),
+                PromptChoice::new('a', "[a]ll"),
+                PromptChoice::new('q', "[q]uit"),
+                PromptChoice::new('l', "[l]ast"),
+            ],
+        }
+    }
+
+    /// The choice for a key press, if any.
+    pub fn choice(&self, key: char) -> Option<&PromptChoice> {
+        self.choices.iter().find(|choice| choice.key == key)
+    }
+}
+
+impl PromptChoice {
+    pub fn new(key: char, label: impl Into<String>) -> Self {
+        Self {
+            key,
+            label: label.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Prompt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let choices = self
+            .choices
+            .iter()
+            .map(|choice| choice.label.as_str())
+            .collect::<Vec<_>>();
+
+        write!(f, "{}: {}", self.question, choices.join(", "))
+    }
+}
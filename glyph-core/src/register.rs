@@ -0,0 +1,339 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+
+use crate::clipboard::{ClipboardKind, ClipboardProvider};
+use crate::error::{GlyphError, Result};
+use crate::key_mapper::MotionType;
+
+/// The text of a register, with how it was taken, which decides how it's put back. Blockwise text
+/// has a line for each row of the block.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Register {
+    pub text: String,
+    pub motion_type: MotionType,
+}
+
+impl Register {
+    pub fn new(text: impl Into<String>, motion_type: MotionType) -> Self {
+        Self {
+            text: text.into(),
+            motion_type,
+        }
+    }
+
+    /// Adds `other` at the end, as `"A` does. Text added to or added as whole lines goes on lines
+    /// of its own.
+    fn append(&mut self, other: Register) {
+        match (self.motion_type, other.motion_type) {
+            (MotionType::Linewise, _) => {
+                self.text.push_str(&other.text);
+                if !self.text.ends_with('\n') {
+                    self.text.push('\n');
+                }
+            }
+            (_, MotionType::Linewise) => {
+                self.text.push('\n');
+                self.text.push_str(&other.text);
+                self.motion_type = MotionType::Linewise;
+            }
+            _ => self.text.push_str(&other.text),
+        }
+    }
+}
+
+/// The registers text is yanked and deleted into, and put back from.
+///
+/// - `"` the unnamed register, which holds whatever was written last.
+/// - `0` the last yank, `1` to `9` the last deletes of a line or more, newest first.
+/// - `a` to `z` the named registers, which `A` to `Z` append to.
+/// - `-` the last delete within a line.
+/// - `_` the black hole, which discards what's written to it.
+/// - `/`, `.` and `%` the last search pattern, the last inserted text and the current file name,
+///   which can only be read.
+/// - `+` and `*` the system clipboard and primary selection, through the clipboard provider.
+#[derive(Debug, Default)]
+pub struct Registers {
+    unnamed: Option<Register>,
+    numbered: [Option<Register>; 10],
+    named: BTreeMap<char, Register>,
+    small_delete: Option<Register>,
+    last_inserted: Option<String>,
+    last_search: Option<String>,
+    file_name: Option<String>,
+    clipboard: Option<Box<dyn ClipboardProvider>>,
+    /// The last text written to `+` or `*`, put back when the provider can't read the clipboard.
+    last_clipboard: Option<Register>,
+}
+
+impl Registers {
+    /// Whether `name` is the name of a register, read-only ones included.
+    pub fn is_valid(name: char) -> bool {
+        matches!(
+            name,
+            '"' | '0'..='9' | 'a'..='z' | 'A'..='Z' | '-' | '_' | '/' | '.' | '%' | '+' | '*'
+        )
+    }
+
+    pub fn set_clipboard_provider(&mut self, provider: Box<dyn ClipboardProvider>) {
+        self.clipboard = Some(provider);
+    }
+
+    /// The content of register `name`, or `None` when nothing was written to it yet.
+    pub fn get(&self, name: char) -> Option<Register> {
+        let charwise = |text: &Option<String>| {
+            text.as_ref()
+                .map(|text| Register::new(text.as_str(), MotionType::Charwise))
+        };
+
+        match name {
+            '"' => self.unnamed.clone(),
+            '0'..='9' => self.numbered[name as usize - '0' as usize].clone(),
+            'a'..='z' | 'A'..='Z' => self.named.get(&name.to_ascii_lowercase()).cloned(),
+            '-' => self.small_delete.clone(),
+            '/' => charwise(&self.last_search),
+            '.' => charwise(&self.last_inserted),
+            '%' => charwise(&self.file_name),
+            '+' => self.read_clipboard(ClipboardKind::Clipboard),
+            '*' => self.read_clipboard(ClipboardKind::Primary),
+            _ => None,
+        }
+    }
+
+    /// The clipboard text, taken as whole lines when it ends with a line break. When it's what was
+    /// last written there the register keeps how it was taken, so blocks come back as blocks.
+    /// Without a provider that can read, the last text written to the clipboard or the last yank
+    /// is used instead.
+    fn read_clipboard(&self, kind: ClipboardKind) -> Option<Register> {
+        let text = self
+            .clipboard
+            .as_ref()
+            .and_then(|provider| provider.get_contents(kind));
+
+        let Some(text) = text else {
+            return self
+                .last_clipboard
+                .clone()
+                .or_else(|| self.numbered[0].clone());
+        };
+
+        match &self.last_clipboard {
+            Some(register) if register.text == text => Some(register.clone()),
+            _ if text.ends_with('\n') => Some(Register::new(text, MotionType::Linewise)),
+            _ => Some(Register::new(text, MotionType::Charwise)),
+        }
+    }
+
+    /// Stores yanked text in register `name`, or in `0` when no register was given.
+    pub fn yank(&mut self, name: Option<char>, register: Register) -> Result<()> {
+        match name {
+            None | Some('"') => {
+                self.numbered[0] = Some(register.clone());
+                self.unnamed = Some(register);
+                Ok(())
+            }
+            Some(name) => self.write(name, register),
+        }
+    }
+
+    /// Stores deleted text in register `name`. Without one, a delete of a line or more shifts
+    /// `1` to `9` down to make room, and a smaller one goes to `-`.
+    pub fn delete(&mut self, name: Option<char>, register: Register) -> Result<()> {
+        match name {
+            None | Some('"') => {
+                if register.motion_type == MotionType::Linewise || register.text.contains('\n') {
+                    self.numbered[1..].rotate_right(1);
+                    self.numbered[1] = Some(register.clone());
+                } else {
+                    self.small_delete = Some(register.clone());
+                }
+                self.unnamed = Some(register);
+                Ok(())
+            }
+            Some(name) => self.write(name, register),
+        }
+    }
+
+    fn write(&mut self, name: char, register: Register) -> Result<()> {
+        let written = match name {
+            '_' => return Ok(()),
+            '0'..='9' => self.numbered[name as usize - '0' as usize]
+                .insert(register)
+                .clone(),
+            'a'..='z' => {
+                self.named.insert(name, register.clone());
+                register
+            }
+            'A'..='Z' => match self.named.entry(name.to_ascii_lowercase()) {
+                Entry::Occupied(mut entry) => {
+                    entry.get_mut().append(register);
+                    entry.get().clone()
+                }
+                Entry::Vacant(entry) => entry.insert(register).clone(),
+            },
+            '-' => self.small_delete.insert(register).clone(),
+            '+' | '*' => {
+                let kind = ClipboardKind::from_register(name).expect("name is a clipboard");
+                self.last_clipboard = Some(register.clone());
+                self.unnamed = Some(register.clone());
+                // the registers keep the text even when it can't reach the system clipboard
+                return self.write_clipboard(kind, &register.text);
+            }
+            name => return Err(GlyphError::InvalidRegister(name)),
+        };
+
+        self.unnamed = Some(written);
+        Ok(())
+    }
+
+    fn write_clipboard(&self, kind: ClipboardKind, text: &str) -> Result<()> {
+        match &self.clipboard {
+            Some(provider) => provider.set_contents(kind, text),
+            None => Ok(()),
+        }
+    }
+
+    /// Remembers the text typed during the last insert, for `".`.
+    pub fn set_last_inserted(&mut self, text: String) {
+        self.last_inserted = Some(text);
+    }
+
+    /// Keeps the registers that mirror the state of the editor, `"/` and `"%`, up to date.
+    pub fn refresh(&mut self, last_search: Option<&str>, file_name: Option<String>) {
+        self.last_search = last_search.map(str::to_string);
+        self.file_name = file_name;
+    }
+
+    /// The registers that hold something, in the order `:registers` lists them.
+    pub fn list(&self) -> Vec<(char, Register)> {
+        ['"']
+            .into_iter()
+            .chain('0'..='9')
+            .chain('a'..='z')
+            .chain(['-', '.', '%', '/', '+', '*'])
+            .filter_map(|name| self.get(name).map(|register| (name, register)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charwise(text: &str) -> Register {
+        Register::new(text, MotionType::Charwise)
+    }
+
+    fn linewise(text: &str) -> Register {
+        Register::new(text, MotionType::Linewise)
+    }
+
+    #[test]
+    fn test_yank_and_delete_registers() {
+        let mut registers = Registers::default();
+
+        registers.yank(None, charwise("word")).unwrap();
+        assert_eq!(registers.get('0'), Some(charwise("word")));
+        assert_eq!(registers.get('"'), Some(charwise("word")));
+
+        registers.delete(None, linewise("first\n")).unwrap();
+        registers.delete(None, linewise("second\n")).unwrap();
+        assert_eq!(registers.get('1'), Some(linewise("second\n")));
+        assert_eq!(registers.get('2'), Some(linewise("first\n")));
+        assert_eq!(registers.get('0'), Some(charwise("word")));
+
+        // a delete within a line leaves the numbered registers alone
+        registers.delete(None, charwise("x")).unwrap();
+        assert_eq!(registers.get('-'), Some(charwise("x")));
+        assert_eq!(registers.get('1'), Some(linewise("second\n")));
+        assert_eq!(registers.get('"'), Some(charwise("x")));
+
+        registers.delete(Some('_'), charwise("gone")).unwrap();
+        assert_eq!(registers.get('"'), Some(charwise("x")));
+        assert_eq!(registers.get('_'), None);
+    }
+
+    #[test]
+    fn test_named_registers() {
+        let mut registers = Registers::default();
+
+        registers.yank(Some('a'), charwise("foo")).unwrap();
+        registers.yank(Some('A'), charwise("bar")).unwrap();
+        assert_eq!(registers.get('a'), Some(charwise("foobar")));
+        assert_eq!(registers.get('"'), Some(charwise("foobar")));
+        assert_eq!(registers.get('0'), None);
+
+        registers.yank(Some('A'), linewise("line\n")).unwrap();
+        assert_eq!(registers.get('a'), Some(linewise("foobar\nline\n")));
+
+        registers.yank(Some('B'), charwise("new")).unwrap();
+        assert_eq!(registers.get('b'), Some(charwise("new")));
+
+        assert!(registers.yank(Some('%'), charwise("file")).is_err());
+    }
+
+    #[derive(Debug)]
+    struct WriteOnlyClipboard;
+
+    impl ClipboardProvider for WriteOnlyClipboard {
+        fn name(&self) -> &str {
+            "write-only"
+        }
+
+        fn set_contents(&self, _: ClipboardKind, _: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_contents(&self, _: ClipboardKind) -> Option<String> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_clipboard_registers_fall_back() {
+        let mut registers = Registers::default();
+        registers.set_clipboard_provider(Box::new(WriteOnlyClipboard));
+
+        assert_eq!(registers.get('+'), None);
+
+        registers.yank(None, charwise("yanked")).unwrap();
+        assert_eq!(registers.get('+'), Some(charwise("yanked")));
+
+        registers.yank(Some('+'), linewise("copied\n")).unwrap();
+        assert_eq!(registers.get('+'), Some(linewise("copied\n")));
+        assert_eq!(registers.get('"'), Some(linewise("copied\n")));
+        assert_eq!(registers.get('0'), Some(charwise("yanked")));
+    }
+
+    #[derive(Debug)]
+    struct BrokenClipboard;
+
+    impl ClipboardProvider for BrokenClipboard {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn set_contents(&self, _: ClipboardKind, _: &str) -> Result<()> {
+            Err(GlyphError::ClipboardFailed {
+                provider: self.name().to_string(),
+                reason: "no display".to_string(),
+            })
+        }
+
+        fn get_contents(&self, _: ClipboardKind) -> Option<String> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_clipboard_failures_keep_the_registers() {
+        let mut registers = Registers::default();
+        registers.set_clipboard_provider(Box::new(BrokenClipboard));
+
+        assert!(matches!(
+            registers.yank(Some('+'), charwise("copied")),
+            Err(GlyphError::ClipboardFailed { .. })
+        ));
+        assert_eq!(registers.get('+'), Some(charwise("copied")));
+        assert_eq!(registers.get('"'), Some(charwise("copied")));
+    }
+}
@@ -2,7 +2,11 @@ use crate::buffer_manager::Buffer;
 use crate::editing_plugin::EditingPlugin;
 use crate::geometry::Size;
 use crate::key_mapper::EditorMode;
+use crate::message::Message;
+use crate::prompt::Prompt;
 use crate::renderer::error::Result;
+use crate::search::SearchCount;
+use crate::text_object::SearchPattern;
 use crate::view_manager::{LayoutTreeNode, ViewManager};
 
 pub mod error;
@@ -13,6 +17,12 @@ pub struct RenderContext<'ctx> {
     pub views: &'ctx ViewManager,
     pub layout: &'ctx LayoutTreeNode,
     pub buffers: &'ctx [&'ctx Buffer],
+    pub message: Option<&'ctx Message>,
+    pub prompt: Option<&'ctx Prompt>,
+    /// The pattern whose matches are highlighted, if any.
+    pub search_highlight: Option<&'ctx SearchPattern>,
+    /// Which match of the highlighted pattern the cursor of the active view is on.
+    pub search_count: Option<SearchCount>,
     pub editing_plugin: &'ctx dyn EditingPlugin,
 }
 
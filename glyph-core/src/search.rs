@@ -0,0 +1,264 @@
+use crate::buffer_manager::{Buffer, BufferId};
+use crate::cursor::Cursor;
+use crate::error::{GlyphError, Result};
+use crate::geometry::Point;
+use crate::options::EditorOptions;
+use crate::text_object::{SearchDirection, SearchPattern};
+
+/// Counting stops past this many matches, so a common pattern in a large file doesn't have to be
+/// counted in full.
+const MAX_SEARCH_COUNT: usize = 999;
+
+/// Matches are only counted on the block of this many lines the cursor is on and the ones before
+/// and after it, so a change to the buffer doesn't rescan all of it.
+const SEARCH_COUNT_BLOCK: usize = 5000;
+
+/// The last search, repeated by `n` and `N`, and the one being typed.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SearchState {
+    pub pattern: Option<String>,
+    pub direction: SearchDirection,
+    /// Whether `smartcase` applies to the pattern, it doesn't when searching for the word under
+    /// the cursor.
+    pub smartcase: bool,
+    /// Whether the matches of the last pattern are highlighted, until `:nohlsearch`.
+    pub highlight: bool,
+    pub preview: Option<SearchPreview>,
+}
+
+/// A pattern that is still being typed, which the cursor jumps to as it changes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SearchPreview {
+    pub pattern: String,
+    /// Where the cursor and the view were when the search started, restored when it's cancelled.
+    pub origin: Cursor,
+    pub scroll_offset: Point<usize>,
+    pub skipped_rows: usize,
+}
+
+/// Which match the cursor is on, out of every match in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SearchCount {
+    /// The last match starting at the cursor or before it, from 1. It's 0 when the cursor is
+    /// before the first match, and `None` when matches before the cursor weren't counted.
+    pub current: Option<usize>,
+    pub total: usize,
+    /// Whether only part of the buffer was counted, so there may be more matches.
+    pub incomplete: bool,
+}
+
+impl std::fmt::Display for SearchCount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let current = match self.current {
+            Some(current) => current.to_string(),
+            None => String::from("?"),
+        };
+
+        match self.incomplete {
+            true => write!(f, "[{current}/>{}]", self.total),
+            false => write!(f, "[{current}/{}]", self.total),
+        }
+    }
+}
+
+impl SearchState {
+    /// Makes `word` the last pattern, matched as a whole word.
+    pub fn set_word(&mut self, word: &str, direction: SearchDirection) {
+        self.pattern = Some(format!(r"\b{}\b", regex_syntax::escape(word)));
+        self.direction = direction;
+        self.smartcase = false;
+    }
+
+    /// Compiles the last pattern, following the case options.
+    pub fn compile(&self, options: &EditorOptions) -> Result<SearchPattern> {
+        let pattern = self
+            .pattern
+            .as_deref()
+            .ok_or(GlyphError::NoPreviousPattern)?;
+
+        SearchPattern::new(pattern, self.ignores_case(pattern, options))
+    }
+
+    /// The pattern whose matches are highlighted: the one being typed, or else the last one.
+    /// Patterns that don't compile aren't highlighted.
+    pub fn highlighted<'a>(
+        &self,
+        options: &EditorOptions,
+        compiled: &'a mut CompiledPattern,
+    ) -> Option<&'a SearchPattern> {
+        let (pattern, ignore_case) = match self.preview.as_ref() {
+            Some(preview) if preview.pattern.is_empty() => return None,
+            Some(preview) => (
+                preview.pattern.as_str(),
+                options.search_ignores_case(&preview.pattern),
+            ),
+            None if self.highlight => {
+                let pattern = self.pattern.as_deref()?;
+                (pattern, self.ignores_case(pattern, options))
+            }
+            None => return None,
+        };
+
+        compiled.get(pattern, ignore_case)
+    }
+
+    fn ignores_case(&self, pattern: &str, options: &EditorOptions) -> bool {
+        match self.smartcase {
+            true => options.search_ignores_case(pattern),
+            false => options.ignorecase,
+        }
+    }
+}
+
+/// The highlighted pattern, compiled once rather than on every frame. It's compiled again when
+/// the pattern or whether it ignores case changes.
+#[derive(Debug, Default)]
+pub struct CompiledPattern {
+    key: Option<(String, bool)>,
+    pattern: Option<SearchPattern>,
+}
+
+impl CompiledPattern {
+    fn get(&mut self, pattern: &str, ignore_case: bool) -> Option<&SearchPattern> {
+        let is_current = self
+            .key
+            .as_ref()
+            .is_some_and(|(current, ignores)| current == pattern && *ignores == ignore_case);
+
+        if !is_current {
+            self.pattern = SearchPattern::new(pattern, ignore_case).ok();
+            self.key = Some((pattern.to_string(), ignore_case));
+        }
+
+        self.pattern.as_ref()
+    }
+}
+
+/// Where the matches of a pattern start around the cursor, kept until the buffer or the pattern
+/// changes, or the cursor moves to another block of lines, so they aren't searched for again on
+/// every frame.
+#[derive(Debug, Default)]
+pub struct MatchIndex {
+    key: Option<(BufferId, u64, String, bool, usize)>,
+    starts: Vec<usize>,
+    /// Whether counting stopped at [`MAX_SEARCH_COUNT`].
+    capped: bool,
+    /// Whether lines after the counted ones were left out.
+    partial: bool,
+}
+
+impl MatchIndex {
+    /// Counts the matches of `pattern` in `buffer`, and which one the cursor is on. Only the lines
+    /// from a block before the cursor to a block after it are searched.
+    pub fn count(
+        &mut self,
+        buffer: &Buffer,
+        pattern: &SearchPattern,
+        cursor: &Cursor,
+    ) -> SearchCount {
+        let content = buffer.content();
+        let block = cursor.y / SEARCH_COUNT_BLOCK;
+        let first_line = block.saturating_sub(1) * SEARCH_COUNT_BLOCK;
+        let key = (
+            buffer.id,
+            buffer.revision(),
+            pattern.as_str().to_string(),
+            pattern.ignores_case(),
+            first_line,
+        );
+
+        if self.key.as_ref() != Some(&key) {
+            let last_line = usize::min((block + 2) * SEARCH_COUNT_BLOCK, content.len_lines());
+            self.starts.clear();
+            self.capped = false;
+            self.partial = last_line < content.len_lines();
+
+            for line_idx in first_line..last_line {
+                let matches = content.find_in_lines(pattern, line_idx..line_idx + 1);
+                self.starts.extend(matches.into_iter().map(|m| m.start));
+
+                if self.starts.len() > MAX_SEARCH_COUNT {
+                    self.starts.truncate(MAX_SEARCH_COUNT);
+                    self.capped = true;
+                    break;
+                }
+            }
+
+            self.key = Some(key);
+        }
+
+        let cursor_idx = content.point_to_char(Point::new(cursor.x, cursor.y));
+        let current = self.starts.partition_point(|&start| start <= cursor_idx);
+        // past the last match counted, the cursor may be past others that weren't
+        let past_counted = self.capped && self.starts.last().is_some_and(|&s| s < cursor_idx);
+
+        SearchCount {
+            current: (first_line == 0 && !past_counted).then_some(current),
+            total: self.starts.len(),
+            incomplete: self.capped || self.partial || first_line > 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_manager::scratch_buffer;
+
+    #[test]
+    fn test_count_matches() {
+        let buffer = scratch_buffer("foo bar\nbaz foo\nfoobar\n");
+        let pattern = SearchPattern::new("foo", false).unwrap();
+        let mut index = MatchIndex::default();
+
+        let count = |index: &mut MatchIndex, x, y| {
+            let cursor = Cursor { x, y, virtual_x: x };
+            index.count(&buffer, &pattern, &cursor).to_string()
+        };
+
+        assert_eq!(count(&mut index, 0, 0), "[1/3]");
+        assert_eq!(count(&mut index, 2, 1), "[1/3]");
+        assert_eq!(count(&mut index, 4, 1), "[2/3]");
+        assert_eq!(count(&mut index, 5, 2), "[3/3]");
+
+        let incomplete = SearchCount {
+            current: Some(12),
+            total: 999,
+            incomplete: true,
+        };
+        assert_eq!(incomplete.to_string(), "[12/>999]");
+
+        let unknown = SearchCount {
+            current: None,
+            ..incomplete
+        };
+        assert_eq!(unknown.to_string(), "[?/>999]");
+    }
+
+    #[test]
+    fn test_count_matches_around_the_cursor() {
+        let lines = SEARCH_COUNT_BLOCK * 4;
+        let buffer = scratch_buffer(&"foo\n".repeat(lines));
+        let pattern = SearchPattern::new("foo", false).unwrap();
+        let mut index = MatchIndex::default();
+
+        let count = |index: &mut MatchIndex, y| {
+            let cursor = Cursor {
+                x: 0,
+                y,
+                virtual_x: 0,
+            };
+            index.count(&buffer, &pattern, &cursor)
+        };
+
+        // the lines past the block after the cursor aren't counted
+        let count_at_top = count(&mut index, 3);
+        assert_eq!(count_at_top.current, Some(4));
+        assert!(count_at_top.incomplete);
+
+        // neither are the lines before the block before the cursor, so where it is isn't known
+        let count_at_bottom = count(&mut index, lines - 1);
+        assert_eq!(count_at_bottom.current, None);
+        assert!(count_at_bottom.incomplete);
+    }
+}
@@ -9,6 +9,10 @@ pub struct StartupOptions {
     pub config: Option<String>,
     #[arg(long)]
     pub verbose: bool,
+    /// Recovers the given files from their swap files, or lists the files that can be recovered
+    /// when none is given.
+    #[arg(short, long)]
+    pub recover: bool,
 }
 
 impl StartupOptions {
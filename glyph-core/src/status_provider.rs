@@ -3,12 +3,14 @@ use std::fmt::Debug;
 use crate::buffer_manager::Buffer;
 use crate::geometry::Point;
 use crate::key_mapper::EditorMode;
+use crate::search::SearchCount;
 
 pub struct StatuslineContext<'ctx> {
     pub current_mode: EditorMode,
     pub cursor_position: Point<usize>,
     pub buffer_info: &'ctx Buffer,
     pub width: usize,
+    pub search_count: Option<SearchCount>,
 }
 
 pub trait StatuslineProvider: Debug {
@@ -0,0 +1,216 @@
+use std::ops::Range;
+
+use crate::error::{GlyphError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum LineAddress {
+    /// `.`, the line of the cursor.
+    Current,
+    /// `$`, the last line.
+    Last,
+    /// A line number, starting at 1.
+    Number(usize),
+    /// `'x`, the line of a mark.
+    Mark(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Address {
+    line: LineAddress,
+    /// Added by a trailing `+N` or `-N`.
+    offset: isize,
+}
+
+/// The lines an ex command applies to, like `%`, `.,$`, `3,7` or `'<,'>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineRange {
+    start: Address,
+    end: Address,
+}
+
+impl LineRange {
+    /// Parses a range as typed by the user. An empty range is the line of the cursor.
+    pub fn parse(range: &str) -> Result<Self> {
+        let invalid = || GlyphError::InvalidRange(range.to_string());
+
+        if range == "%" {
+            return Ok(Self {
+                start: Address::new(LineAddress::Number(1)),
+                end: Address::new(LineAddress::Last),
+            });
+        }
+
+        let (start, end) = match range.split_once([',', ';']) {
+            Some((start, end)) => (start, Some(end)),
+            None => (range, None),
+        };
+
+        let start = parse_address(start).ok_or_else(invalid)?;
+        let end = match end {
+            Some(end) => parse_address(end).ok_or_else(invalid)?,
+            None => start,
+        };
+
+        Ok(Self { start, end })
+    }
+
+    /// The lines of the range, counted from 0, given the line of the cursor and the number of
+    /// lines. A range given backwards is swapped.
+    pub fn resolve(&self, cursor_line: usize, len_lines: usize) -> Result<Range<usize>> {
+        let start = self.start.resolve(cursor_line, len_lines)?;
+        let end = self.end.resolve(cursor_line, len_lines)?;
+
+        Ok(start.min(end)..start.max(end) + 1)
+    }
+}
+
+impl Address {
+    fn new(line: LineAddress) -> Self {
+        Self { line, offset: 0 }
+    }
+
+    fn resolve(&self, cursor_line: usize, len_lines: usize) -> Result<usize> {
+        let line = match self.line {
+            LineAddress::Current => cursor_line,
+            LineAddress::Last => len_lines.saturating_sub(1),
+            // line 0 is the same as line 1, like in vim
+            LineAddress::Number(number) => number.saturating_sub(1),
+            // marks are parsed, but nothing sets them yet, not even `'<` and `'>` as there is no
+            // visual mode
+            LineAddress::Mark(_) => return Err(GlyphError::MarkNotSet),
+        };
+
+        line.checked_add_signed(self.offset)
+            .filter(|&line| line < len_lines.max(1))
+            .ok_or_else(|| GlyphError::InvalidRange(self.to_string()))
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            LineAddress::Current => write!(f, ".")?,
+            LineAddress::Last => write!(f, "$")?,
+            LineAddress::Number(number) => write!(f, "{number}")?,
+            LineAddress::Mark(mark) => write!(f, "'{mark}")?,
+        }
+
+        match self.offset {
+            0 => Ok(()),
+            offset => write!(f, "{offset:+}"),
+        }
+    }
+}
+
+/// Parses an address followed by any number of offsets, `.` is implied when only offsets are
+/// given.
+fn parse_address(address: &str) -> Option<Address> {
+    let mut chars = address.trim().chars().peekable();
+
+    let line = match chars.peek().copied().unwrap_or('.') {
+        '.' => {
+            chars.next();
+            LineAddress::Current
+        }
+        '$' => {
+            chars.next();
+            LineAddress::Last
+        }
+        '\'' => {
+            chars.next();
+            LineAddress::Mark(chars.next()?)
+        }
+        c if c.is_ascii_digit() => LineAddress::Number(take_number(&mut chars)?),
+        _ => LineAddress::Current,
+    };
+
+    let mut offset = 0isize;
+    while let Some(sign) = chars.next() {
+        let amount = match chars.peek() {
+            Some(c) if c.is_ascii_digit() => take_number(&mut chars)? as isize,
+            _ => 1,
+        };
+
+        match sign {
+            '+' => offset += amount,
+            '-' => offset -= amount,
+            _ => return None,
+        }
+    }
+
+    Some(Address { line, offset })
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<usize> {
+    let mut number = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        number.push(digit);
+    }
+
+    number.parse().ok()
+}
+
+/// The flags given after a substitution.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SubstituteFlags {
+    /// `g`, replaces every match of a line instead of only the first one.
+    pub global: bool,
+    /// `i` ignores case and `I` matches case, overriding the case options.
+    pub ignore_case: Option<bool>,
+    /// `c`, asks before replacing each match.
+    pub confirm: bool,
+    /// `n`, counts the matches without replacing them.
+    pub count_only: bool,
+}
+
+impl SubstituteFlags {
+    pub fn parse(flags: &str) -> Result<Self> {
+        let mut parsed = Self::default();
+
+        for (idx, flag) in flags.char_indices() {
+            match flag {
+                'g' => parsed.global = true,
+                'i' => parsed.ignore_case = Some(true),
+                'I' => parsed.ignore_case = Some(false),
+                'c' => parsed.confirm = true,
+                'n' => parsed.count_only = true,
+                _ => return Err(GlyphError::TrailingCharacters(flags[idx..].to_string())),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_line_range() {
+        let resolve = |range: &str| LineRange::parse(range).unwrap().resolve(4, 10);
+
+        assert_eq!(resolve("").unwrap(), 4..5);
+        assert_eq!(resolve("%").unwrap(), 0..10);
+        assert_eq!(resolve(".,$").unwrap(), 4..10);
+        assert_eq!(resolve("2,3").unwrap(), 1..3);
+        assert_eq!(resolve(".+1,$-2").unwrap(), 5..8);
+        assert_eq!(resolve("-,+").unwrap(), 3..6);
+
+        // backwards ranges are swapped
+        assert_eq!(resolve("7,2").unwrap(), 1..7);
+
+        assert!(resolve("11").is_err());
+        assert!(LineRange::parse("'<,'>").is_ok());
+        assert!(LineRange::parse("x").is_err());
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let flags = SubstituteFlags::parse("gIc").unwrap();
+        assert!(flags.global && flags.confirm && !flags.count_only);
+        assert_eq!(flags.ignore_case, Some(false));
+
+        assert!(SubstituteFlags::parse("gx").is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use ropey::Rope;
+
+/// A single reversible modification made to a [`TextObject`].
+///
+/// Changes carry the text they inserted or removed so they can be inverted without looking at the
+/// content they were applied to.
+///
+/// [`TextObject`]: super::TextObject
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Change {
+    Insert { char_idx: usize, text: String },
+    Delete { char_idx: usize, text: String },
+}
+
+impl Change {
+    pub fn invert(&self) -> Self {
+        match self {
+            Self::Insert { char_idx, text } => Self::Delete {
+                char_idx: *char_idx,
+                text: text.clone(),
+            },
+            Self::Delete { char_idx, text } => Self::Insert {
+                char_idx: *char_idx,
+                text: text.clone(),
+            },
+        }
+    }
+
+    pub(crate) fn apply(&self, rope: &mut Rope) {
+        match self {
+            Self::Insert { char_idx, text } => rope.insert(*char_idx, text),
+            Self::Delete { char_idx, text } => {
+                let end = char_idx + text.chars().count();
+                rope.remove(*char_idx..end);
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::borrow::Cow;
+
+use super::grapheme::line_graphemes;
+use super::{SearchDirection, TextObject};
+use crate::geometry::Point;
+
+/// A search for a character on the cursor line, as done by `f`, `t`, `F` and `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharSearch {
+    pub ch: char,
+    pub direction: SearchDirection,
+    /// Whether the cursor stops next to the character rather than on it, for `t` and `T`.
+    pub till: bool,
+}
+
+impl CharSearch {
+    /// The same search the other way around, for `,`.
+    pub fn reversed(self) -> Self {
+        let direction = match self.direction {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        };
+
+        Self { direction, ..self }
+    }
+}
+
+impl TextObject {
+    /// Where the `count`th match of `search` on the line of `point` takes the cursor, or `None`
+    /// when the line doesn't have that many. Only grapheme clusters made of that single character
+    /// match, so `fe` doesn't stop on an `e` with a combining accent.
+    pub fn find_char(
+        &self,
+        point: Point<usize>,
+        search: CharSearch,
+        count: usize,
+    ) -> Option<Point<usize>> {
+        let line = self
+            .get_line(point.y)
+            .map(Cow::<str>::from)
+            .unwrap_or_default();
+        // the tabstop doesn't matter, only char columns are used
+        let graphemes = line_graphemes(&line, 1)
+            .filter(|grapheme| !matches!(grapheme.text, "\n" | "\r\n" | "\r"))
+            .collect::<Vec<_>>();
+
+        let ch = search.ch.to_string();
+        let current = graphemes.partition_point(|grapheme| grapheme.x < point.x);
+        let is_match = |&i: &usize| graphemes[i].text == ch;
+        let nth = count.saturating_sub(1);
+
+        let target = match search.direction {
+            SearchDirection::Forward => {
+                let found = (current + 1..graphemes.len()).filter(is_match).nth(nth)?;
+                found - usize::from(search.till)
+            }
+            SearchDirection::Backward => {
+                let found = (0..current).rev().filter(is_match).nth(nth)?;
+                found + usize::from(search.till)
+            }
+        };
+
+        Some(Point::new(graphemes[target].x, point.y))
+    }
+}
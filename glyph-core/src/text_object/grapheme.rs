@@ -0,0 +1,123 @@
+use ropey::RopeSlice;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
+use unicode_width::UnicodeWidthStr;
+
+/// A grapheme cluster of a line, with where it's drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Grapheme<'a> {
+    pub text: &'a str,
+    /// The column of its first char, like `Cursor::x`.
+    pub x: usize,
+    /// The display column it's drawn at.
+    pub column: usize,
+    /// How many display columns it takes.
+    pub width: usize,
+}
+
+/// How many display columns a grapheme cluster takes outside of a tab. Line breaks, other control
+/// characters and clusters that would take no room at all are drawn as a single blank column, so
+/// the cursor can still sit on them.
+pub fn grapheme_width(grapheme: &str) -> usize {
+    match is_blank(grapheme) {
+        true => 1,
+        false => grapheme.width(),
+    }
+}
+
+fn is_blank(grapheme: &str) -> bool {
+    grapheme.chars().any(char::is_control) || grapheme.width() == 0
+}
+
+impl<'a> Grapheme<'a> {
+    /// What to print for the cluster, a space when it's drawn blank.
+    pub fn symbol(&self) -> &'a str {
+        match is_blank(self.text) {
+            true => " ",
+            false => self.text,
+        }
+    }
+}
+
+/// The grapheme clusters of `line`, laid out from the first display column with tabs reaching
+/// the next multiple of `tabstop`.
+pub fn line_graphemes(line: &str, tabstop: usize) -> impl Iterator<Item = Grapheme<'_>> {
+    let mut x = 0;
+    let mut column = 0;
+
+    line.graphemes(true).map(move |text| {
+        let width = match text {
+            "\t" => tabstop - column % tabstop,
+            text => grapheme_width(text),
+        };
+        let grapheme = Grapheme {
+            text,
+            x,
+            column,
+            width,
+        };
+        x += text.chars().count();
+        column += grapheme.width;
+        grapheme
+    })
+}
+
+/// The char index of the grapheme boundary before `char_idx`, or 0 at the start of the text.
+pub(super) fn prev_boundary(text: RopeSlice<'_>, char_idx: usize) -> usize {
+    let byte_idx = text.char_to_byte(char_idx);
+    let (mut chunk, mut chunk_byte_idx, _, _) = text.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, text.len_bytes(), true);
+
+    loop {
+        match cursor.prev_boundary(chunk, chunk_byte_idx) {
+            Ok(Some(boundary)) => return text.byte_to_char(boundary),
+            Ok(None) => return 0,
+            Err(GraphemeIncomplete::PrevChunk) => {
+                (chunk, chunk_byte_idx, _, _) = text.chunk_at_byte(chunk_byte_idx - 1);
+            }
+            Err(GraphemeIncomplete::PreContext(idx)) => {
+                let (context, context_byte_idx, _, _) = text.chunk_at_byte(idx - 1);
+                cursor.provide_context(context, context_byte_idx);
+            }
+            Err(_) => unreachable!("only the previous chunk or context is ever requested"),
+        }
+    }
+}
+
+/// The char index of the grapheme boundary after `char_idx`, or the length of the text at its end.
+pub(super) fn next_boundary(text: RopeSlice<'_>, char_idx: usize) -> usize {
+    let byte_idx = text.char_to_byte(char_idx);
+    let (mut chunk, mut chunk_byte_idx, _, _) = text.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, text.len_bytes(), true);
+
+    loop {
+        match cursor.next_boundary(chunk, chunk_byte_idx) {
+            Ok(Some(boundary)) => return text.byte_to_char(boundary),
+            Ok(None) => return text.len_chars(),
+            Err(GraphemeIncomplete::NextChunk) => {
+                (chunk, chunk_byte_idx, _, _) = text.chunk_at_byte(chunk_byte_idx + chunk.len());
+            }
+            Err(GraphemeIncomplete::PreContext(idx)) => {
+                let (context, context_byte_idx, _, _) = text.chunk_at_byte(idx - 1);
+                cursor.provide_context(context, context_byte_idx);
+            }
+            Err(_) => unreachable!("only the next chunk or context is ever requested"),
+        }
+    }
+}
+
+pub(super) fn is_boundary(text: RopeSlice<'_>, char_idx: usize) -> bool {
+    let byte_idx = text.char_to_byte(char_idx);
+    let (chunk, chunk_byte_idx, _, _) = text.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, text.len_bytes(), true);
+
+    loop {
+        match cursor.is_boundary(chunk, chunk_byte_idx) {
+            Ok(is_boundary) => return is_boundary,
+            Err(GraphemeIncomplete::PreContext(idx)) => {
+                let (context, context_byte_idx, _, _) = text.chunk_at_byte(idx - 1);
+                cursor.provide_context(context, context_byte_idx);
+            }
+            Err(_) => unreachable!("only context is ever requested"),
+        }
+    }
+}
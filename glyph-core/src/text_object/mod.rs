@@ -1,11 +1,37 @@
+mod change;
+mod char_search;
+mod grapheme;
+mod search;
+mod selection;
+mod transaction;
+mod word;
+mod wrap;
+
+use std::borrow::Cow;
+use std::ops::Range;
+
+pub use change::Change;
+pub use char_search::CharSearch;
+pub use grapheme::{Grapheme, grapheme_width, line_graphemes};
 use ropey::iter::Lines;
 use ropey::{Rope, RopeSlice};
+pub use search::{Replacement, SearchPattern};
+pub use selection::{TextObjectKind, TextObjectScope};
+pub use transaction::{Assoc, Transaction};
+pub use wrap::{DisplayRow, WrapOptions, find_display_row};
 
 use crate::geometry::Point;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TextObject {
     inner: Rope,
+    changes: Vec<Change>,
+    /// Every change applied since the journal was last cleared, including the ones replayed from
+    /// history. Persisted to swap files so unsaved work can be recovered after a crash.
+    journal: Vec<Change>,
+    /// Every change that buffer listeners weren't told about yet.
+    unnotified: Vec<Change>,
+    revision: u64,
 }
 
 const OPENING_PAIRS: &[char] = &['(', '{', '[', '<'];
@@ -19,10 +45,6 @@ fn is_word_char(ch: char) -> bool {
     ch.is_alphanumeric() || ch == '_'
 }
 
-fn is_punctuation_char(ch: char) -> bool {
-    !ch.is_whitespace() && !is_word_char(ch)
-}
-
 fn is_matching_pair(needle: char, char: char) -> bool {
     matches!(
         (needle, char),
@@ -45,8 +67,18 @@ fn find_pair_search_direction(char: char) -> SearchDirection {
     SearchDirection::Backward
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-enum SearchDirection {
+/// A match of a substitution, with the text that replaces it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubstituteMatch {
+    pub line_idx: usize,
+    /// Char range of the match.
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SearchDirection {
+    #[default]
     Forward,
     Backward,
 }
@@ -55,7 +87,156 @@ impl TextObject {
     pub fn new(content: String) -> Self {
         Self {
             inner: Rope::from(content),
+            changes: vec![],
+            journal: vec![],
+            unnotified: vec![],
+            revision: 0,
+        }
+    }
+
+    /// Increases every time the content changes, including undo and redo.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Whether there are changes that were made but not yet taken by [`TextObject::take_changes`].
+    pub fn has_pending_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Takes every change recorded since the last call, in the order they were applied.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Applies changes without recording them, used when replaying history.
+    pub(crate) fn apply_changes(&mut self, changes: &[Change]) {
+        if changes.is_empty() {
+            return;
+        }
+
+        for change in changes {
+            change.apply(&mut self.inner);
+        }
+        self.journal.extend_from_slice(changes);
+        self.unnotified.extend_from_slice(changes);
+        self.revision += 1;
+    }
+
+    /// Adds text to the end without recording it, used while a file is still being loaded.
+    pub(crate) fn append(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
         }
+
+        let change = Change::Insert {
+            char_idx: self.inner.len_chars(),
+            text: text.to_string(),
+        };
+        change.apply(&mut self.inner);
+        self.unnotified.push(change);
+        self.revision += 1;
+    }
+
+    /// Replaces the content without recording it anywhere, forgetting every change made so far.
+    pub(crate) fn reset(&mut self, content: String) {
+        self.inner = Rope::from(content);
+        self.changes.clear();
+        self.journal.clear();
+        self.unnotified.clear();
+        self.revision += 1;
+    }
+
+    pub(crate) fn take_unnotified(&mut self) -> Vec<Change> {
+        std::mem::take(&mut self.unnotified)
+    }
+
+    pub(crate) fn journal(&self) -> &[Change] {
+        &self.journal
+    }
+
+    pub(crate) fn clear_journal(&mut self) {
+        self.journal.clear();
+    }
+
+    /// The content as it was before any change in the journal was applied.
+    pub(crate) fn journal_base(&self) -> Rope {
+        let mut base = self.inner.clone();
+        for change in self.journal.iter().rev() {
+            change.invert().apply(&mut base);
+        }
+        base
+    }
+
+    /// Replaces the whole content, recording it as changes like any other edit.
+    pub fn replace_content(&mut self, content: &str) {
+        if self.inner == content {
+            return;
+        }
+
+        let mut transaction = Transaction::new();
+        transaction.replace(0..self.inner.len_chars(), content);
+        self.apply(&transaction);
+    }
+
+    /// Applies every edit of `transaction` and returns the transaction that undoes it.
+    ///
+    /// # Panics
+    ///
+    /// Panics when an edit is out of bounds, in which case nothing is applied.
+    pub fn apply(&mut self, transaction: &Transaction) -> Transaction {
+        let len_chars = self.inner.len_chars();
+        assert!(
+            transaction.edits().all(|(range, _)| range.end <= len_chars),
+            "transaction doesn't fit the content"
+        );
+
+        let removed_texts = transaction
+            .edits()
+            .map(|(range, _)| self.inner.slice(range.clone()).to_string())
+            .collect();
+        let inverse = transaction.inverse(removed_texts);
+
+        // edits are applied from the last to the first, so the offsets of the ones still to be
+        // applied stay valid
+        for (range, text) in transaction.edits().rev() {
+            self.remove(range.clone());
+            self.insert(range.start, text);
+        }
+
+        inverse
+    }
+
+    fn insert(&mut self, char_idx: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let change = Change::Insert {
+            char_idx,
+            text: text.to_string(),
+        };
+        change.apply(&mut self.inner);
+        self.journal.push(change.clone());
+        self.unnotified.push(change.clone());
+        self.changes.push(change);
+        self.revision += 1;
+    }
+
+    fn remove(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let change = Change::Delete {
+            char_idx: range.start,
+            text: self.inner.slice(range).to_string(),
+        };
+        change.apply(&mut self.inner);
+        self.journal.push(change.clone());
+        self.unnotified.push(change.clone());
+        self.changes.push(change);
+        self.revision += 1;
     }
 
     pub fn get_line(&self, line_idx: usize) -> Option<RopeSlice<'_>> {
@@ -67,10 +248,19 @@ impl TextObject {
         self.inner.line(line_idx)
     }
 
+    /// The text in a range of chars.
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'_> {
+        self.inner.slice(range)
+    }
+
     pub fn line_len(&self, line_idx: usize) -> usize {
         self.line(line_idx).len_chars()
     }
 
+    pub fn len_chars(&self) -> usize {
+        self.inner.len_chars()
+    }
+
     pub fn len_lines(&self) -> usize {
         self.inner.len_lines()
     }
@@ -79,13 +269,181 @@ impl TextObject {
         self.inner.lines()
     }
 
-    pub fn delete_whole_line(&mut self, line: usize) {
+    /// Number of lines as they would be counted in a file, where a trailing line break ends the
+    /// last line instead of starting a new, empty one.
+    pub fn len_file_lines(&self) -> usize {
         let len_lines = self.len_lines();
-        assert!(line < len_lines);
+        let last_line_is_empty = self.line_len(len_lines - 1) == 0;
+
+        match last_line_is_empty {
+            true => len_lines - 1,
+            false => len_lines,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.inner.len_bytes());
+        for chunk in self.inner.chunks() {
+            bytes.extend_from_slice(chunk.as_bytes());
+        }
+        bytes
+    }
 
-        let line_start = self.inner.line_to_char(line);
-        let line_end = self.inner.line_to_char(line + 1);
-        self.inner.remove(line_start.saturating_sub(1)..line_end);
+    /// The char index of a position, where `x` is the column in chars.
+    pub fn point_to_char(&self, point: Point<usize>) -> usize {
+        self.inner.line_to_char(point.y) + point.x
+    }
+
+    pub fn char_to_point(&self, char_idx: usize) -> Point<usize> {
+        let line_idx = self.inner.char_to_line(char_idx);
+        Point::new(char_idx - self.inner.line_to_char(line_idx), line_idx)
+    }
+
+    /// The char index where the grapheme cluster before `char_idx` starts.
+    pub fn prev_grapheme_boundary(&self, char_idx: usize) -> usize {
+        grapheme::prev_boundary(self.inner.slice(..), char_idx)
+    }
+
+    /// The char index where the grapheme cluster after the one at `char_idx` starts.
+    pub fn next_grapheme_boundary(&self, char_idx: usize) -> usize {
+        grapheme::next_boundary(self.inner.slice(..), char_idx)
+    }
+
+    /// The display column `point` is drawn at, which is further right than its char column when
+    /// the line has tabs or wide characters, and closer to the left when it has combining marks.
+    /// Columns past the end of the line are one cell wide each.
+    pub fn display_column(&self, point: Point<usize>, tabstop: usize) -> usize {
+        let Some(line) = self.get_line(point.y) else {
+            return point.x;
+        };
+        let line = Cow::<str>::from(line);
+
+        let (mut line_len, mut line_width) = (0, 0);
+        for grapheme in line_graphemes(&line, tabstop) {
+            if grapheme.x >= point.x {
+                return grapheme.column;
+            }
+            line_len = grapheme.x + grapheme.text.chars().count();
+            line_width = grapheme.column + grapheme.width;
+        }
+
+        line_width + point.x.saturating_sub(line_len)
+    }
+
+    /// The char column of the grapheme cluster drawn over display `column` of a line, or the
+    /// length of the line when it's too short to reach it.
+    pub fn column_to_x(&self, line_idx: usize, column: usize, tabstop: usize) -> usize {
+        let Some(line) = self.get_line(line_idx) else {
+            return 0;
+        };
+        let line = Cow::<str>::from(line);
+
+        line_graphemes(&line, tabstop)
+            .find(|grapheme| grapheme.column + grapheme.width > column)
+            .map_or(line.chars().count(), |grapheme| grapheme.x)
+    }
+
+    /// The rows line `line_idx` takes when it's broken to fit in `options.width` columns.
+    pub fn wrap_line(
+        &self,
+        line_idx: usize,
+        tabstop: usize,
+        options: WrapOptions<'_>,
+    ) -> Vec<DisplayRow> {
+        let line = self
+            .get_line(line_idx)
+            .map(Cow::<str>::from)
+            .unwrap_or_default();
+        wrap::wrap_line(&line, tabstop, options)
+    }
+
+    /// Moves `point` back to the start of the grapheme cluster it's in, so it never sits between
+    /// a base character and its combining marks. Points past the end of their line are kept.
+    pub fn snap_to_grapheme(&self, point: Point<usize>) -> Point<usize> {
+        if point.y >= self.len_lines() || point.x >= self.line_len(point.y) {
+            return point;
+        }
+
+        let char_idx = self.point_to_char(point);
+        match grapheme::is_boundary(self.inner.slice(..), char_idx) {
+            true => point,
+            false => self.char_to_point(self.prev_grapheme_boundary(char_idx)),
+        }
+    }
+
+    /// Finds the first match of `pattern` starting at `char_idx` or after it, or the last one
+    /// starting before it when searching backward, without wrapping around. Returns a char range.
+    pub fn search(
+        &self,
+        pattern: &SearchPattern,
+        char_idx: usize,
+        direction: SearchDirection,
+    ) -> Option<Range<usize>> {
+        let byte_idx = self.inner.char_to_byte(char_idx.min(self.len_chars()));
+        let range = pattern.find(&self.inner, byte_idx, direction)?;
+        Some(self.inner.byte_to_char(range.start)..self.inner.byte_to_char(range.end))
+    }
+
+    /// Finds the char range of every match of `pattern` in `lines`, in order.
+    pub fn find_in_lines(&self, pattern: &SearchPattern, lines: Range<usize>) -> Vec<Range<usize>> {
+        let mut matches = vec![];
+        let lines = lines.start.min(self.len_lines())..lines.end.min(self.len_lines());
+
+        for line_idx in lines {
+            let line = Cow::from(self.inner.line(line_idx));
+            let mut byte_idx = 0;
+            let mut char_idx = self.inner.line_to_char(line_idx);
+
+            for range in pattern.find_in_line(&line) {
+                let start = char_idx + line[byte_idx..range.start].chars().count();
+                let end = start + line[range.start..range.end].chars().count();
+                matches.push(start..end);
+
+                byte_idx = range.end;
+                char_idx = end;
+            }
+        }
+
+        matches
+    }
+
+    /// Finds the matches of `pattern` in `lines` and what they are replaced with, in order. Only
+    /// the first match of every line is taken unless `global` is set.
+    pub fn substitute_matches(
+        &self,
+        pattern: &SearchPattern,
+        lines: Range<usize>,
+        replacement: &Replacement,
+        global: bool,
+    ) -> Vec<SubstituteMatch> {
+        let mut matches = vec![];
+
+        for line_idx in lines {
+            let line = Cow::from(self.inner.line(line_idx));
+            let line_start = self.inner.line_to_char(line_idx);
+
+            // matches come in order, so byte offsets are turned into chars by counting from the
+            // previous one
+            let mut byte_idx = 0;
+            let mut char_idx = line_start;
+            let mut to_char = |byte: usize| {
+                char_idx += line[byte_idx..byte].chars().count();
+                byte_idx = byte;
+                char_idx
+            };
+
+            for (range, text) in pattern.replace_in_line(&line, replacement, global) {
+                let start = to_char(range.start);
+                let end = to_char(range.end);
+                matches.push(SubstituteMatch {
+                    line_idx,
+                    range: start..end,
+                    text,
+                });
+            }
+        }
+
+        matches
     }
 
     pub fn find_matching_pair(&self, point: Point<usize>) -> Point<usize> {
@@ -192,96 +550,6 @@ impl TextObject {
 
         Point::new(0, line_idx)
     }
-
-    pub fn delete_prev_char(&mut self, position: Point<usize>) {
-        let line_start_char = self.inner.line_to_char(position.y);
-        let position_char = line_start_char + position.x;
-        self.inner.remove(position_char - 1..position_char);
-    }
-
-    pub fn delete_curr_char(&mut self, position: Point<usize>) {
-        let line_start_char = self.inner.line_to_char(position.y);
-        let position_char = line_start_char + position.x;
-        self.inner.remove(position_char..position_char + 1);
-    }
-
-    pub fn insert_char_at(&mut self, position: Point<usize>, ch: char) {
-        let line_start_char = self.inner.line_to_char(position.y);
-        let position_char = line_start_char + position.x;
-        self.inner.insert_char(position_char, ch);
-    }
-
-    pub fn find_next_word_boundary(&self, point: Point<usize>) -> Point<usize> {
-        if point.y >= self.len_lines() {
-            return point;
-        }
-
-        let line = self.line(point.y);
-        if point.x >= line.len_chars().saturating_sub(2) {
-            return self.find_next_line_start(point.y + 1);
-        }
-
-        let current_char = line.char(point.x);
-        let line_start_char = self.inner.line_to_char(point.y);
-        let mut char_idx = line_start_char + point.x;
-
-        // Step 1: Skip current word/punctuation group
-        if is_word_char(current_char) {
-            // Skip all word characters
-            for ch in self.inner.chars_at(char_idx) {
-                if !is_word_char(ch) {
-                    break;
-                }
-                char_idx += 1;
-            }
-        } else if is_punctuation_char(current_char) {
-            // Skip all punctuation characters
-            for ch in self.inner.chars_at(char_idx) {
-                if !is_punctuation_char(ch) {
-                    break;
-                }
-                char_idx += 1;
-            }
-        }
-
-        // Step 2: Skip whitespace
-        for ch in self.inner.chars_at(char_idx) {
-            if !ch.is_whitespace() {
-                break;
-            }
-            char_idx += 1;
-        }
-
-        // Convert back to line/column coordinates
-        if char_idx >= self.inner.len_chars() {
-            // At EOF
-            let last_line = self.len_lines().saturating_sub(1);
-            let last_line_len = self.line_len(last_line).saturating_sub(1);
-            return Point::new(last_line_len, last_line);
-        }
-
-        let result_line = self.inner.char_to_line(char_idx);
-        let result_line_start = self.inner.line_to_char(result_line);
-        let result_col = char_idx - result_line_start;
-
-        Point::new(result_col, result_line)
-    }
-
-    fn find_next_line_start(&self, line_idx: usize) -> Point<usize> {
-        if line_idx >= self.len_lines() {
-            // At EOF
-            let last_line = self.len_lines().saturating_sub(1);
-            let last_line_len = self.line_len(last_line).saturating_sub(1);
-            return Point::new(last_line_len, last_line);
-        }
-
-        self.line(line_idx)
-            .chars()
-            .enumerate()
-            .find(|(_, ch)| !ch.is_whitespace()) // find first non-whitespace character
-            .map(|(col, _)| Point::new(col, line_idx)) // that's where the cursor should move
-            .unwrap_or(Point::new(0, line_idx)) // if only whitespace, then return start of line
-    }
 }
 
 #[cfg(test)]
@@ -316,4 +584,660 @@ mod tests {
         let pair_position = text_object.find_matching_pair(Point::new(0, 3));
         assert_eq!(pair_position, Point::new(31, 0));
     }
+
+    #[test]
+    fn test_transaction_apply_and_invert() {
+        let mut text_object = TextObject::new("hello world\nfoo\n".into());
+
+        // offsets are all relative to the original content, regardless of insertion order
+        let mut transaction = Transaction::new();
+        transaction
+            .replace(12..15, "bar")
+            .insert(0, ">> ")
+            .delete(5..11);
+
+        let inverse = text_object.apply(&transaction);
+        assert_eq!(text_object.to_bytes(), b">> hello\nbar\n");
+
+        text_object.apply(&inverse);
+        assert_eq!(text_object.to_bytes(), b"hello world\nfoo\n");
+    }
+
+    #[test]
+    fn test_transaction_map_position() {
+        let mut transaction = Transaction::new();
+        transaction.insert(2, "xy").delete(5..8);
+
+        assert_eq!(transaction.map_position(1, Assoc::After), 1);
+        assert_eq!(transaction.map_position(2, Assoc::Before), 2);
+        assert_eq!(transaction.map_position(2, Assoc::After), 4);
+        assert_eq!(transaction.map_position(4, Assoc::After), 6);
+
+        // positions in deleted text collapse to where the deletion started
+        assert_eq!(transaction.map_position(6, Assoc::After), 7);
+        assert_eq!(transaction.map_position(8, Assoc::After), 7);
+        assert_eq!(transaction.map_position(10, Assoc::After), 9);
+    }
+
+    #[test]
+    #[should_panic(expected = "edits can't overlap")]
+    fn test_transaction_rejects_overlapping_edits() {
+        Transaction::new().delete(2..5).insert(3, "x");
+    }
+
+    #[test]
+    fn test_search() {
+        let text_object = TextObject::new("foo bar\nbaz foo\nfoobar\n".into());
+        let pattern = SearchPattern::new("foo", false).unwrap();
+
+        let forward = |idx| text_object.search(&pattern, idx, SearchDirection::Forward);
+        assert_eq!(forward(0), Some(0..3));
+        assert_eq!(forward(1), Some(12..15));
+        assert_eq!(forward(13), Some(16..19));
+        assert_eq!(forward(17), None);
+
+        let backward = |idx| text_object.search(&pattern, idx, SearchDirection::Backward);
+        assert_eq!(backward(16), Some(12..15));
+        assert_eq!(backward(13), Some(12..15));
+        assert_eq!(backward(12), Some(0..3));
+        assert_eq!(backward(0), None);
+
+        // matches may span lines, and `^` matches at the start of every line
+        let pattern = SearchPattern::new("bar\nbaz", false).unwrap();
+        assert_eq!(
+            text_object.search(&pattern, 0, SearchDirection::Forward),
+            Some(4..11)
+        );
+        let pattern = SearchPattern::new("^foo", false).unwrap();
+        assert_eq!(
+            text_object.search(&pattern, 1, SearchDirection::Forward),
+            Some(16..19)
+        );
+    }
+
+    #[test]
+    fn test_search_ignoring_case_and_words() {
+        let text_object = TextObject::new("Foo foobar héllo FOO\n".into());
+
+        let pattern = SearchPattern::new("foo", true).unwrap();
+        assert_eq!(
+            text_object.search(&pattern, 1, SearchDirection::Forward),
+            Some(4..7)
+        );
+
+        // unicode word boundaries around non-ascii text fall back to searching by line
+        let pattern = SearchPattern::new(r"\bfoo\b", true).unwrap();
+        assert_eq!(
+            text_object.search(&pattern, 1, SearchDirection::Forward),
+            Some(17..20)
+        );
+        assert_eq!(
+            text_object.search(&pattern, 17, SearchDirection::Backward),
+            Some(0..3)
+        );
+
+        assert!(SearchPattern::new("(foo", false).is_err());
+    }
+
+    #[test]
+    fn test_grapheme_boundaries() {
+        // a decomposed é, a Hangul syllable made of jamo, a flag and a ZWJ family
+        let text_object = TextObject::new(
+            "e\u{301}x \u{1112}\u{1161}\u{11ab} \u{1f1e7}\u{1f1f7} \u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\r\n".into(),
+        );
+
+        let mut boundaries = vec![0];
+        while *boundaries.last().unwrap() < text_object.len_chars() {
+            let next = text_object.next_grapheme_boundary(*boundaries.last().unwrap());
+            boundaries.push(next);
+        }
+        assert_eq!(boundaries, vec![0, 2, 3, 4, 7, 8, 10, 11, 16, 18]);
+
+        for window in boundaries.windows(2) {
+            assert_eq!(text_object.prev_grapheme_boundary(window[1]), window[0]);
+        }
+        assert_eq!(text_object.prev_grapheme_boundary(0), 0);
+
+        assert_eq!(
+            text_object.snap_to_grapheme(Point::new(1, 0)),
+            Point::new(0, 0)
+        );
+        assert_eq!(
+            text_object.snap_to_grapheme(Point::new(6, 0)),
+            Point::new(4, 0)
+        );
+        assert_eq!(
+            text_object.snap_to_grapheme(Point::new(13, 0)),
+            Point::new(11, 0)
+        );
+        assert_eq!(
+            text_object.snap_to_grapheme(Point::new(8, 0)),
+            Point::new(8, 0)
+        );
+    }
+
+    #[test]
+    fn test_display_column() {
+        // wide CJK characters and emoji, a combining mark and a zero width space
+        let text_object = TextObject::new("a\u{4f60}\u{597d}e\u{301}\u{1f600}\u{200b}b\n".into());
+
+        let columns = (0..10)
+            .map(|x| text_object.display_column(Point::new(x, 0), 8))
+            .collect::<Vec<_>>();
+        assert_eq!(columns, vec![0, 1, 3, 5, 6, 6, 8, 9, 10, 11]);
+
+        let widths = line_graphemes("\u{4f60}e\u{301}\u{200b}\t", 8)
+            .map(|grapheme| (grapheme.symbol(), grapheme.width))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            widths,
+            vec![("\u{4f60}", 2), ("e\u{301}", 1), (" ", 1), (" ", 4)]
+        );
+    }
+
+    #[test]
+    fn test_tab_columns() {
+        let text_object = TextObject::new("\tab\tc\n  \tx\n".into());
+
+        let columns = (0..6)
+            .map(|x| text_object.display_column(Point::new(x, 0), 4))
+            .collect::<Vec<_>>();
+        assert_eq!(columns, vec![0, 4, 5, 6, 8, 9]);
+        assert_eq!(text_object.display_column(Point::new(3, 1), 4), 4);
+        assert_eq!(text_object.display_column(Point::new(3, 1), 8), 8);
+
+        // columns inside a tab belong to it
+        assert_eq!(text_object.column_to_x(0, 2, 4), 0);
+        assert_eq!(text_object.column_to_x(0, 7, 4), 3);
+        assert_eq!(text_object.column_to_x(1, 5, 8), 2);
+        assert_eq!(text_object.column_to_x(1, usize::MAX, 8), 5);
+    }
+
+    /// The text the text object at `point` covers.
+    fn select(
+        text_object: &TextObject,
+        kind: TextObjectKind,
+        scope: TextObjectScope,
+        point: Point<usize>,
+        count: usize,
+    ) -> Option<String> {
+        let range = text_object.select_text_object(kind, scope, point, count)?;
+        Some(text_object.inner.slice(range).to_string())
+    }
+
+    #[test]
+    fn test_select_words() {
+        let text_object = TextObject::new("  foo.bar baz  qux\n".into());
+        let (word, big_word) = (TextObjectKind::Word, TextObjectKind::BigWord);
+        let (inner, around) = (TextObjectScope::Inner, TextObjectScope::Around);
+
+        assert_eq!(
+            select(&text_object, word, inner, Point::new(3, 0), 1).as_deref(),
+            Some("foo")
+        );
+        assert_eq!(
+            select(&text_object, word, inner, Point::new(3, 0), 3).as_deref(),
+            Some("foo.bar")
+        );
+        assert_eq!(
+            select(&text_object, word, inner, Point::new(0, 0), 1).as_deref(),
+            Some("  ")
+        );
+        assert_eq!(
+            select(&text_object, word, around, Point::new(7, 0), 1).as_deref(),
+            Some("bar ")
+        );
+        assert_eq!(
+            select(&text_object, word, around, Point::new(7, 0), 2).as_deref(),
+            Some("bar baz  ")
+        );
+        assert_eq!(
+            select(&text_object, word, around, Point::new(9, 0), 1).as_deref(),
+            Some(" baz")
+        );
+        assert_eq!(
+            select(&text_object, big_word, inner, Point::new(3, 0), 1).as_deref(),
+            Some("foo.bar")
+        );
+        // no blanks after the last word, so the ones before it are taken
+        assert_eq!(
+            select(&text_object, big_word, around, Point::new(16, 0), 1).as_deref(),
+            Some("  qux")
+        );
+    }
+
+    #[test]
+    fn test_select_sentences_and_paragraphs() {
+        let text = "One. Two (really!) three.\nFour\n\n\nFive.\n";
+        let text_object = TextObject::new(text.into());
+        let (sentence, paragraph) = (TextObjectKind::Sentence, TextObjectKind::Paragraph);
+        let (inner, around) = (TextObjectScope::Inner, TextObjectScope::Around);
+
+        // a sentence can end with closing brackets after its `.`, `!` or `?`
+        let point = Point::new(6, 0);
+        assert_eq!(
+            select(&text_object, sentence, inner, point, 1).as_deref(),
+            Some("Two (really!)")
+        );
+        assert_eq!(
+            select(&text_object, sentence, around, Point::new(0, 0), 1).as_deref(),
+            Some("One. ")
+        );
+        assert_eq!(
+            select(&text_object, sentence, inner, point, 3).as_deref(),
+            Some("Two (really!) three.\nFour")
+        );
+        assert_eq!(
+            select(&text_object, sentence, inner, Point::new(0, 2), 1),
+            None
+        );
+
+        let point = Point::new(0, 1);
+        assert_eq!(
+            select(&text_object, paragraph, inner, point, 1).as_deref(),
+            Some("One. Two (really!) three.\nFour\n")
+        );
+        assert_eq!(
+            select(&text_object, paragraph, around, point, 1).as_deref(),
+            Some("One. Two (really!) three.\nFour\n\n\n")
+        );
+        assert_eq!(
+            select(&text_object, paragraph, around, Point::new(0, 2), 1).as_deref(),
+            Some("\n\nFive.\n")
+        );
+        assert_eq!(
+            select(&text_object, paragraph, around, Point::new(0, 4), 1).as_deref(),
+            Some("\n\nFive.\n")
+        );
+    }
+
+    #[test]
+    fn test_select_pairs_and_quotes() {
+        let code_sample = ["fn main() {", "    let s = f(a, (b, \"c d\"));", "}"].join("\n");
+        let text_object = TextObject::new(code_sample);
+        let parens = TextObjectKind::from_key('b').unwrap();
+        let braces = TextObjectKind::from_key('{').unwrap();
+        let quotes = TextObjectKind::from_key('"').unwrap();
+        let (inner, around) = (TextObjectScope::Inner, TextObjectScope::Around);
+
+        let point = Point::new(19, 1);
+        assert_eq!(
+            select(&text_object, parens, inner, point, 1).as_deref(),
+            Some("b, \"c d\"")
+        );
+        assert_eq!(
+            select(&text_object, parens, around, point, 2).as_deref(),
+            Some("(a, (b, \"c d\"))")
+        );
+        assert_eq!(
+            select(&text_object, parens, inner, Point::new(18, 1), 1).as_deref(),
+            Some("b, \"c d\"")
+        );
+        assert_eq!(select(&text_object, parens, inner, point, 3), None);
+
+        // a block spanning lines keeps the lines of its braces
+        assert_eq!(
+            select(&text_object, braces, inner, point, 1).as_deref(),
+            Some("    let s = f(a, (b, \"c d\"));\n")
+        );
+
+        assert_eq!(
+            select(&text_object, quotes, inner, Point::new(22, 1), 1).as_deref(),
+            Some("c d")
+        );
+        assert_eq!(
+            select(&text_object, quotes, around, Point::new(22, 1), 1).as_deref(),
+            Some(" \"c d\"")
+        );
+        assert_eq!(
+            select(&text_object, quotes, inner, Point::new(4, 1), 1).as_deref(),
+            Some("c d")
+        );
+        assert_eq!(
+            select(&text_object, quotes, inner, Point::new(27, 1), 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_select_tags() {
+        let text_object = TextObject::new("<ul>\n  <li>a <b>b</b><br></li>\n</ul>\n".into());
+        let (inner, around) = (TextObjectScope::Inner, TextObjectScope::Around);
+
+        assert_eq!(
+            select(
+                &text_object,
+                TextObjectKind::Tag,
+                inner,
+                Point::new(14, 1),
+                1
+            )
+            .as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            select(
+                &text_object,
+                TextObjectKind::Tag,
+                inner,
+                Point::new(7, 1),
+                1
+            )
+            .as_deref(),
+            Some("a <b>b</b><br>")
+        );
+        assert_eq!(
+            select(
+                &text_object,
+                TextObjectKind::Tag,
+                around,
+                Point::new(14, 1),
+                2
+            )
+            .as_deref(),
+            Some("<li>a <b>b</b><br></li>")
+        );
+        assert_eq!(
+            select(
+                &text_object,
+                TextObjectKind::Tag,
+                inner,
+                Point::new(14, 1),
+                3
+            )
+            .as_deref(),
+            Some("\n  <li>a <b>b</b><br></li>\n")
+        );
+        assert_eq!(
+            select(
+                &text_object,
+                TextObjectKind::Tag,
+                inner,
+                Point::new(14, 1),
+                4
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_wrap_line() {
+        let text_object = TextObject::new("  the quick brown fox jumps\n\n".into());
+        let mut options = WrapOptions {
+            width: 10,
+            linebreak: false,
+            breakindent: false,
+            showbreak: "",
+        };
+
+        let ranges = |rows: Vec<DisplayRow>| {
+            rows.iter()
+                .map(|row| (row.start, row.end, row.indent))
+                .collect::<Vec<_>>()
+        };
+
+        let rows = text_object.wrap_line(0, 8, options);
+        assert_eq!(ranges(rows), vec![(0, 10, 0), (10, 20, 0), (20, 27, 0)]);
+
+        options.linebreak = true;
+        let rows = text_object.wrap_line(0, 8, options);
+        assert_eq!(
+            ranges(rows),
+            vec![(0, 6, 0), (6, 12, 0), (12, 22, 0), (22, 27, 0)]
+        );
+
+        // the indent makes room for itself, down to the minimum width
+        options.width = 24;
+        options.breakindent = true;
+        options.showbreak = "> ";
+        let rows = text_object.wrap_line(0, 8, options);
+        assert_eq!(ranges(rows.clone()), vec![(0, 22, 0), (22, 27, 4)]);
+        assert_eq!(rows[1].screen_x(rows[1].column), 4);
+        assert_eq!(find_display_row(&rows, 27), 1);
+
+        let rows = text_object.wrap_line(1, 8, options);
+        assert_eq!(ranges(rows), vec![(0, 0, 0)]);
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let text_object = TextObject::new("foo.bar  baz-qux\n\n  x, y\nend.\n".into());
+        type Motion = fn(&TextObject, Point<usize>, bool) -> Point<usize>;
+        type Case<'a> = (&'a str, Motion, bool, &'a [(usize, usize)]);
+
+        // every motion is repeated from the first position, the last one being where it stops
+        let cases: [Case<'_>; 8] = [
+            (
+                "w",
+                TextObject::find_next_word_start,
+                false,
+                &[
+                    (0, 0),
+                    (3, 0),
+                    (4, 0),
+                    (9, 0),
+                    (12, 0),
+                    (13, 0),
+                    (0, 1),
+                    (2, 2),
+                    (3, 2),
+                    (5, 2),
+                    (0, 3),
+                    (3, 3),
+                    (3, 3),
+                ],
+            ),
+            (
+                "W",
+                TextObject::find_next_word_start,
+                true,
+                &[(0, 0), (9, 0), (0, 1), (2, 2), (5, 2), (0, 3), (3, 3)],
+            ),
+            (
+                "b",
+                TextObject::find_prev_word_start,
+                false,
+                &[
+                    (3, 3),
+                    (0, 3),
+                    (5, 2),
+                    (3, 2),
+                    (2, 2),
+                    (0, 1),
+                    (13, 0),
+                    (12, 0),
+                    (9, 0),
+                    (4, 0),
+                    (3, 0),
+                    (0, 0),
+                    (0, 0),
+                ],
+            ),
+            (
+                "B",
+                TextObject::find_prev_word_start,
+                true,
+                &[(3, 3), (0, 3), (5, 2), (2, 2), (0, 1), (9, 0), (0, 0)],
+            ),
+            (
+                "e",
+                TextObject::find_next_word_end,
+                false,
+                &[
+                    (0, 0),
+                    (2, 0),
+                    (3, 0),
+                    (6, 0),
+                    (11, 0),
+                    (12, 0),
+                    (15, 0),
+                    (2, 2),
+                    (3, 2),
+                    (5, 2),
+                    (2, 3),
+                    (3, 3),
+                    (3, 3),
+                ],
+            ),
+            (
+                "E",
+                TextObject::find_next_word_end,
+                true,
+                &[(0, 0), (6, 0), (15, 0), (3, 2), (5, 2), (3, 3)],
+            ),
+            (
+                "ge",
+                TextObject::find_prev_word_end,
+                false,
+                &[
+                    (3, 3),
+                    (2, 3),
+                    (5, 2),
+                    (3, 2),
+                    (2, 2),
+                    (0, 1),
+                    (15, 0),
+                    (12, 0),
+                    (11, 0),
+                    (6, 0),
+                    (3, 0),
+                    (2, 0),
+                    (0, 0),
+                ],
+            ),
+            (
+                "gE",
+                TextObject::find_prev_word_end,
+                true,
+                &[(3, 3), (5, 2), (3, 2), (0, 1), (15, 0), (6, 0), (0, 0)],
+            ),
+        ];
+
+        for (name, motion, big_word, positions) in cases {
+            let expected = positions
+                .iter()
+                .map(|&(x, y)| Point::new(x, y))
+                .collect::<Vec<_>>();
+            let mut actual = vec![expected[0]];
+            for _ in 1..expected.len() {
+                let point = motion(&text_object, *actual.last().unwrap(), big_word);
+                actual.push(point);
+            }
+
+            assert_eq!(actual, expected, "motion {name}");
+        }
+
+        // `G` puts the cursor after the final line break
+        let end = Point::new(0, 4);
+        assert_eq!(
+            text_object.find_prev_word_start(end, false),
+            Point::new(3, 3)
+        );
+        assert_eq!(text_object.find_prev_word_end(end, false), Point::new(3, 3));
+        assert_eq!(text_object.find_next_word_start(end, false), end);
+        assert_eq!(text_object.find_next_word_end(end, false), end);
+    }
+
+    #[test]
+    fn test_word_motion_over_graphemes() {
+        let text_object = TextObject::new("cafe\u{301} bar \u{1f468}\u{200d}\u{1f469} x\n".into());
+
+        let next = |x| text_object.find_next_word_start(Point::new(x, 0), false);
+        assert_eq!(next(0), Point::new(6, 0));
+        assert_eq!(next(6), Point::new(10, 0));
+        assert_eq!(next(10), Point::new(14, 0));
+    }
+
+    #[test]
+    fn test_word_operator_ends() {
+        let text_object = TextObject::new("foo bar\n  baz\n\nqux\n".into());
+        let dw = |x, y, count| text_object.find_word_operator_end(Point::new(x, y), false, count);
+        let cw = |x, y, count| text_object.find_change_word_end(Point::new(x, y), false, count);
+
+        assert_eq!(dw(0, 0, 1), Point::new(4, 0));
+        // the line break after the last word stays
+        assert_eq!(dw(4, 0, 1), Point::new(7, 0));
+        assert_eq!(dw(0, 0, 2), Point::new(7, 0));
+        assert_eq!(dw(0, 0, 3), Point::new(5, 1));
+        assert_eq!(dw(2, 1, 1), Point::new(5, 1));
+        assert_eq!(dw(0, 3, 1), Point::new(3, 3));
+
+        assert_eq!(cw(0, 0, 1), Point::new(2, 0));
+        assert_eq!(cw(2, 0, 1), Point::new(2, 0));
+        assert_eq!(cw(1, 0, 2), Point::new(6, 0));
+    }
+
+    #[test]
+    fn test_find_char() {
+        let text_object = TextObject::new("a(b, c(d)) e\u{301}e\n".into());
+        let search = |ch, direction, till| CharSearch {
+            ch,
+            direction,
+            till,
+        };
+        let find = |x, search, count| text_object.find_char(Point::new(x, 0), search, count);
+
+        let f = search(')', SearchDirection::Forward, false);
+        assert_eq!(find(0, f, 1), Some(Point::new(8, 0)));
+        assert_eq!(find(0, f, 2), Some(Point::new(9, 0)));
+        assert_eq!(find(0, f, 3), None);
+        // the character under the cursor is never a match
+        assert_eq!(find(8, f, 1), Some(Point::new(9, 0)));
+
+        let t = search(')', SearchDirection::Forward, true);
+        assert_eq!(find(0, t, 1), Some(Point::new(7, 0)));
+        assert_eq!(find(7, t, 1), Some(Point::new(7, 0)));
+
+        let big_t = search('(', SearchDirection::Backward, true);
+        assert_eq!(find(9, big_t, 1), Some(Point::new(7, 0)));
+        assert_eq!(find(9, big_t.reversed(), 1), None);
+        assert_eq!(find(9, big_t, 2), Some(Point::new(2, 0)));
+
+        // an `e` with a combining accent isn't an `e`
+        let e = search('e', SearchDirection::Forward, false);
+        assert_eq!(find(0, e, 1), Some(Point::new(13, 0)));
+    }
+
+    #[test]
+    fn test_find_in_lines() {
+        let text_object = TextObject::new("foo bar\nbaz foo\nfoobar\n".into());
+        let pattern = SearchPattern::new("o+", false).unwrap();
+
+        assert_eq!(text_object.find_in_lines(&pattern, 0..1), vec![1..3]);
+        // lines past the end of the buffer are ignored
+        assert_eq!(
+            text_object.find_in_lines(&pattern, 1..10),
+            vec![13..15, 17..19]
+        );
+    }
+
+    #[test]
+    fn test_substitute_matches() {
+        let mut text_object = TextObject::new("key = value\nfoo = bar = baz\nnone\n".into());
+        let pattern = SearchPattern::new(r"(\w+) = (\w+)", false).unwrap();
+        let replacement = Replacement::parse(r"$2: \1 [&]");
+
+        let matches = text_object.substitute_matches(&pattern, 0..3, &replacement, true);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1].line_idx, 1);
+        assert_eq!(matches[1].range, 12..21);
+
+        let mut transaction = Transaction::new();
+        for m in matches {
+            transaction.replace(m.range, m.text);
+        }
+        text_object.apply(&transaction);
+        assert_eq!(
+            text_object.to_bytes(),
+            b"value: key [key = value]\nbar: foo [foo = bar] = baz\nnone\n"
+        );
+
+        // `$` matches once per line, before the line break
+        let pattern = SearchPattern::new("$", false).unwrap();
+        let replacement = Replacement::parse(";$$");
+        let matches = text_object.substitute_matches(&pattern, 0..3, &replacement, true);
+        let ranges = matches.iter().map(|m| m.range.clone()).collect::<Vec<_>>();
+        assert_eq!(ranges, vec![24..24, 51..51, 56..56]);
+        assert_eq!(matches[0].text, ";$");
+    }
 }
@@ -0,0 +1,414 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use regex_automata::hybrid::dfa::{Cache, DFA};
+use regex_automata::hybrid::regex::Regex;
+use regex_automata::util::captures::Captures;
+use regex_automata::util::start;
+use regex_automata::util::syntax;
+use regex_automata::{Anchored, Input, meta};
+use ropey::Rope;
+
+use super::SearchDirection;
+use crate::error::{GlyphError, Result};
+
+/// A compiled search pattern.
+///
+/// Searches step a lazy DFA through the bytes of the rope, so the content never has to be copied
+/// into a single string, and matches may span several lines. The lazy DFA gives up on non-ASCII
+/// text when the pattern has Unicode word boundaries, in which case the search falls back to
+/// matching one line at a time.
+#[derive(Debug)]
+pub struct SearchPattern {
+    pattern: String,
+    ignore_case: bool,
+    regex: Regex,
+    line_regex: meta::Regex,
+}
+
+/// The lazy DFA reached a byte it can't handle.
+struct GaveUp;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum ReplacementPart {
+    Text(String),
+    Group(usize),
+}
+
+/// What the matches of a substitution are replaced with.
+///
+/// `&`, `\0` and `$0` insert the whole match, `\1` to `\9` and `$1` to `$9` insert capture
+/// groups, `\r` and `\n` insert a line break and `\t` a tab. A backslash makes any other character
+/// literal, and `$$` inserts a dollar sign.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Replacement {
+    parts: Vec<ReplacementPart>,
+}
+
+impl Replacement {
+    pub fn parse(replacement: &str) -> Self {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = replacement.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            let group = match ch {
+                '&' => Some(0),
+                '\\' | '$' => chars
+                    .next_if(char::is_ascii_digit)
+                    .and_then(|c| c.to_digit(10)),
+                _ => None,
+            };
+
+            if let Some(group) = group {
+                if !text.is_empty() {
+                    parts.push(ReplacementPart::Text(std::mem::take(&mut text)));
+                }
+                parts.push(ReplacementPart::Group(group as usize));
+                continue;
+            }
+
+            match ch {
+                '\\' => match chars.next() {
+                    Some('r' | 'n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some(escaped) => text.push(escaped),
+                    None => text.push('\\'),
+                },
+                '$' if chars.next_if_eq(&'$').is_some() => text.push('$'),
+                ch => text.push(ch),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(ReplacementPart::Text(text));
+        }
+
+        Self { parts }
+    }
+
+    fn expand(&self, captures: &Captures, haystack: &str) -> String {
+        let mut expanded = String::new();
+
+        for part in self.parts.iter() {
+            match part {
+                ReplacementPart::Text(text) => expanded.push_str(text),
+                ReplacementPart::Group(group) => {
+                    if let Some(span) = captures.get_group(*group) {
+                        expanded.push_str(&haystack[span]);
+                    }
+                }
+            }
+        }
+
+        expanded
+    }
+}
+
+impl SearchPattern {
+    /// Compiles `pattern`, in which `^` and `$` match at the start and end of every line.
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self> {
+        let syntax = syntax::Config::new()
+            .case_insensitive(ignore_case)
+            .multi_line(true);
+        let invalid = || GlyphError::InvalidPattern(pattern.to_string());
+
+        let regex = Regex::builder()
+            .syntax(syntax)
+            .dfa(DFA::config().unicode_word_boundary(true))
+            .build(pattern)
+            .map_err(|_| invalid())?;
+        let line_regex = meta::Regex::builder()
+            .syntax(syntax)
+            .build(pattern)
+            .map_err(|_| invalid())?;
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            ignore_case,
+            regex,
+            line_regex,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn ignores_case(&self) -> bool {
+        self.ignore_case
+    }
+
+    /// Returns the byte range of every match in `line`.
+    pub(super) fn find_in_line<'h>(
+        &'h self,
+        line: &'h str,
+    ) -> impl Iterator<Item = Range<usize>> + 'h {
+        let line_len = line_len_for_matches(line);
+
+        self.line_regex
+            .find_iter(line)
+            .take_while(move |m| m.start() < line_len)
+            .map(|m| m.range())
+    }
+
+    /// Returns the byte range of the matches in `line` with the text replacing each of them, only
+    /// the first match unless `global` is set.
+    pub(super) fn replace_in_line(
+        &self,
+        line: &str,
+        replacement: &Replacement,
+        global: bool,
+    ) -> Vec<(Range<usize>, String)> {
+        let line_len = line_len_for_matches(line);
+
+        let matches = self
+            .line_regex
+            .captures_iter(line)
+            .take_while(|captures| captures.get_match().is_some_and(|m| m.start() < line_len))
+            .map(|captures| {
+                let span = captures.get_match().expect("checked above").span();
+                (span.range(), replacement.expand(&captures, line))
+            });
+
+        match global {
+            true => matches.collect(),
+            false => matches.take(1).collect(),
+        }
+    }
+
+    /// Finds the first match starting at `byte_idx` or after it when searching forward, or the
+    /// last match starting before it when searching backward. Returns a byte range.
+    pub(super) fn find(
+        &self,
+        rope: &Rope,
+        byte_idx: usize,
+        direction: SearchDirection,
+    ) -> Option<Range<usize>> {
+        let mut cache = self.regex.create_cache();
+        let (forward_cache, reverse_cache) = cache.as_parts_mut();
+        let forward = self.regex.forward();
+        let reverse = self.regex.reverse();
+
+        let found = match direction {
+            SearchDirection::Forward => find_forward(rope, forward, forward_cache, byte_idx)
+                .and_then(|end| match end {
+                    Some(end) => Ok(scan_backward(
+                        rope,
+                        reverse,
+                        reverse_cache,
+                        end,
+                        byte_idx,
+                        Anchored::Yes,
+                        Stop::Leftmost,
+                    )?
+                    .map(|start| start..end)),
+                    None => Ok(None),
+                }),
+            SearchDirection::Backward => {
+                // matches are searched from the end of the line, so the ones going past the
+                // starting point are found as well
+                let line_idx = rope.byte_to_line(byte_idx);
+                let line_end = match line_idx + 1 < rope.len_lines() {
+                    true => rope.line_to_byte(line_idx + 1),
+                    false => rope.len_bytes(),
+                };
+
+                scan_backward(
+                    rope,
+                    reverse,
+                    reverse_cache,
+                    line_end,
+                    0,
+                    Anchored::No,
+                    Stop::Before(byte_idx),
+                )
+                .and_then(|start| match start {
+                    Some(start) => Ok(find_forward_anchored(rope, forward, forward_cache, start)?
+                        .map(|end| start..end)),
+                    None => Ok(None),
+                })
+            }
+        };
+
+        match found {
+            Ok(range) => range,
+            Err(GaveUp) => self.find_by_line(rope, byte_idx, direction),
+        }
+    }
+
+    fn find_by_line(
+        &self,
+        rope: &Rope,
+        byte_idx: usize,
+        direction: SearchDirection,
+    ) -> Option<Range<usize>> {
+        let start_line = rope.byte_to_line(byte_idx);
+
+        let match_in_line = |line_idx: usize| {
+            let line_start = rope.line_to_byte(line_idx);
+            let line = Cow::from(rope.line(line_idx));
+            let offset = byte_idx.saturating_sub(line_start).min(line.len());
+
+            let found = match direction {
+                SearchDirection::Forward => {
+                    let input = Input::new(line.as_ref()).range(offset..);
+                    self.line_regex.find(input)
+                }
+                SearchDirection::Backward => self
+                    .line_regex
+                    .find_iter(line.as_ref())
+                    .take_while(|m| line_idx < start_line || m.start() < offset)
+                    .last(),
+            };
+
+            found.map(|m| line_start + m.start()..line_start + m.end())
+        };
+
+        match direction {
+            SearchDirection::Forward => (start_line..rope.len_lines()).find_map(match_in_line),
+            SearchDirection::Backward => (0..=start_line).rev().find_map(match_in_line),
+        }
+    }
+}
+
+/// Matches starting at or past the returned offset are dropped, as the position after the line
+/// break belongs to the next line.
+fn line_len_for_matches(line: &str) -> usize {
+    line.strip_suffix('\n')
+        .map_or(line.len() + 1, |_| line.len())
+}
+
+/// Where a backward scan stops.
+#[derive(Debug, Clone, Copy)]
+enum Stop {
+    /// At the last match start before the scan runs out of matches.
+    Leftmost,
+    /// At the first match start found before the given offset.
+    Before(usize),
+}
+
+/// Returns where the leftmost match starting at `from` or after it ends.
+fn find_forward(
+    rope: &Rope,
+    dfa: &DFA,
+    cache: &mut Cache,
+    from: usize,
+) -> Result<Option<usize>, GaveUp> {
+    scan_forward(rope, dfa, cache, from, Anchored::No)
+}
+
+/// Returns where the match starting exactly at `from` ends.
+fn find_forward_anchored(
+    rope: &Rope,
+    dfa: &DFA,
+    cache: &mut Cache,
+    from: usize,
+) -> Result<Option<usize>, GaveUp> {
+    scan_forward(rope, dfa, cache, from, Anchored::Yes)
+}
+
+fn scan_forward(
+    rope: &Rope,
+    dfa: &DFA,
+    cache: &mut Cache,
+    from: usize,
+    anchored: Anchored,
+) -> Result<Option<usize>, GaveUp> {
+    let look_behind = from.checked_sub(1).map(|idx| rope.byte(idx));
+    let config = start::Config::new()
+        .anchored(anchored)
+        .look_behind(look_behind);
+    let mut sid = dfa.start_state(cache, &config).map_err(|_| GaveUp)?;
+    let mut end = None;
+    let mut at = from;
+
+    for byte in rope.bytes_at(from) {
+        sid = dfa.next_state(cache, sid, byte).map_err(|_| GaveUp)?;
+
+        // match states are entered one byte after the match, so the match ends right before the
+        // byte that was just consumed
+        if sid.is_match() {
+            end = Some(at);
+        } else if sid.is_dead() {
+            return Ok(end);
+        } else if sid.is_quit() {
+            return Err(GaveUp);
+        }
+
+        at += 1;
+    }
+
+    sid = dfa.next_eoi_state(cache, sid).map_err(|_| GaveUp)?;
+    if sid.is_match() {
+        end = Some(at);
+    }
+
+    Ok(end)
+}
+
+/// Runs the reverse DFA from `from` down to `to`, returning match starts.
+fn scan_backward(
+    rope: &Rope,
+    dfa: &DFA,
+    cache: &mut Cache,
+    from: usize,
+    to: usize,
+    anchored: Anchored,
+    stop: Stop,
+) -> Result<Option<usize>, GaveUp> {
+    let look_behind = (from < rope.len_bytes()).then(|| rope.byte(from));
+    let config = start::Config::new()
+        .anchored(anchored)
+        .look_behind(look_behind);
+    let mut sid = dfa.start_state(cache, &config).map_err(|_| GaveUp)?;
+    let mut start = None;
+    let mut bytes = rope.bytes_at(from);
+    let mut at = from;
+
+    // returns whether the scan is over
+    let mut found = |match_start: usize| match stop {
+        Stop::Leftmost => {
+            start = Some(match_start);
+            false
+        }
+        Stop::Before(limit) if match_start < limit => {
+            start = Some(match_start);
+            true
+        }
+        Stop::Before(_) => false,
+    };
+
+    while at > to {
+        let byte = bytes.prev().expect("the scan stays within the rope");
+        at -= 1;
+        sid = dfa.next_state(cache, sid, byte).map_err(|_| GaveUp)?;
+
+        if sid.is_match() {
+            if found(at + 1) {
+                return Ok(start);
+            }
+        } else if sid.is_dead() {
+            return Ok(start);
+        } else if sid.is_quit() {
+            return Err(GaveUp);
+        }
+    }
+
+    // the byte before `to` is only looked at, it can't be part of a match
+    sid = match to.checked_sub(1) {
+        Some(idx) => dfa.next_state(cache, sid, rope.byte(idx)),
+        None => dfa.next_eoi_state(cache, sid),
+    }
+    .map_err(|_| GaveUp)?;
+
+    if sid.is_quit() {
+        return Err(GaveUp);
+    }
+
+    if sid.is_match() {
+        found(to);
+    }
+
+    Ok(start)
+}
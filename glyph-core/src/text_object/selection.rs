@@ -0,0 +1,555 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::word::{CharClass, char_class};
+use super::{TextObject, line_graphemes};
+use crate::geometry::Point;
+
+/// What a text object selects, named after the key that picks it in vim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TextObjectKind {
+    /// `w`, a run of word characters, of other non-blank characters or of blanks.
+    Word,
+    /// `W`, a run of non-blank characters or of blanks.
+    BigWord,
+    /// `s`, text up to a `.`, `!` or `?` followed by a blank.
+    Sentence,
+    /// `p`, lines up to a blank line, or blank lines up to a non-blank one.
+    Paragraph,
+    /// `(`, `[`, `{` and `<` with their closing bracket, which can be on another line.
+    Pair { open: char, close: char },
+    /// `"`, `'` and `` ` ``, which are always on the same line.
+    Quote(char),
+    /// `t`, an XML or HTML element.
+    Tag,
+}
+
+/// Whether a text object is selected with what surrounds it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TextObjectScope {
+    /// `i`, without the blanks, brackets, quotes or tags around it.
+    Inner,
+    /// `a`, with them.
+    Around,
+}
+
+impl TextObjectKind {
+    /// The text object picked by `key` after `i` or `a`.
+    pub fn from_key(key: char) -> Option<Self> {
+        let kind = match key {
+            'w' => Self::Word,
+            'W' => Self::BigWord,
+            's' => Self::Sentence,
+            'p' => Self::Paragraph,
+            '(' | ')' | 'b' => Self::Pair {
+                open: '(',
+                close: ')',
+            },
+            '{' | '}' | 'B' => Self::Pair {
+                open: '{',
+                close: '}',
+            },
+            '[' | ']' => Self::Pair {
+                open: '[',
+                close: ']',
+            },
+            '<' | '>' => Self::Pair {
+                open: '<',
+                close: '>',
+            },
+            '"' | '\'' | '`' => Self::Quote(key),
+            't' => Self::Tag,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+
+    /// Whether the text object is made of whole lines.
+    pub fn is_linewise(&self) -> bool {
+        matches!(self, Self::Paragraph)
+    }
+}
+
+/// A sentence of a paragraph, as char offsets from the start of the paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sentence {
+    start: usize,
+    end: usize,
+    /// Where the blanks after the sentence end.
+    blanks_end: usize,
+}
+
+/// An XML or HTML tag, opening or closing an element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tag {
+    range: Range<usize>,
+    name: String,
+    is_closing: bool,
+}
+
+impl TextObject {
+    /// The char range of the text object at `point`, or `None` when there's no such text object
+    /// there. A `count` selects that many words, sentences or paragraphs, or the `count`th pair of
+    /// brackets or tags around `point`.
+    pub fn select_text_object(
+        &self,
+        kind: TextObjectKind,
+        scope: TextObjectScope,
+        point: Point<usize>,
+        count: usize,
+    ) -> Option<Range<usize>> {
+        let count = count.max(1);
+
+        match kind {
+            TextObjectKind::Word => self.select_word(point, scope, count, false),
+            TextObjectKind::BigWord => self.select_word(point, scope, count, true),
+            TextObjectKind::Sentence => self.select_sentence(point, scope, count),
+            TextObjectKind::Paragraph => self.select_paragraph(point.y, scope, count),
+            TextObjectKind::Pair { open, close } => {
+                self.select_pair(point, open, close, scope, count)
+            }
+            TextObjectKind::Quote(quote) => self.select_quote(point, quote, scope, count),
+            TextObjectKind::Tag => self.select_tag(point, scope, count),
+        }
+    }
+
+    fn select_word(
+        &self,
+        point: Point<usize>,
+        scope: TextObjectScope,
+        count: usize,
+        big_word: bool,
+    ) -> Option<Range<usize>> {
+        let line = Cow::<str>::from(self.get_line(point.y)?);
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        // graphemes are classified by their first character, so combining marks stay with it
+        let mut runs: Vec<(Range<usize>, CharClass)> = vec![];
+        for grapheme in line_graphemes(line, 1) {
+            let class = char_class(grapheme.text.chars().next()?, big_word);
+            let end = grapheme.x + grapheme.text.chars().count();
+            match runs.last_mut() {
+                Some((range, last_class)) if *last_class == class => range.end = end,
+                _ => runs.push((grapheme.x..end, class)),
+            }
+        }
+
+        let first = runs
+            .iter()
+            .position(|(range, _)| range.contains(&point.x))?;
+        let is_blank = |idx: usize| runs[idx].1 == CharClass::Blank;
+        let mut start = runs[first].0.start;
+        let mut last = first;
+
+        match scope {
+            TextObjectScope::Inner => last = (first + count - 1).min(runs.len() - 1),
+            TextObjectScope::Around => {
+                // every word comes with the blanks after it, or the blanks with the word after
+                // them when starting on blanks
+                let starts_on_blank = is_blank(first);
+                let mut next = first + 1;
+                for n in 0..count {
+                    if n > 0 && next < runs.len() {
+                        last = next;
+                        next += 1;
+                    }
+                    if next < runs.len() && is_blank(next) != is_blank(last) {
+                        last = next;
+                        next += 1;
+                    }
+                }
+
+                // without blanks after the words, the ones before them are taken instead
+                if !starts_on_blank && !is_blank(last) && first > 0 && is_blank(first - 1) {
+                    start = runs[first - 1].0.start;
+                }
+            }
+        }
+
+        let line_start = self.inner.line_to_char(point.y);
+        Some(line_start + start..line_start + runs[last].0.end)
+    }
+
+    fn select_sentence(
+        &self,
+        point: Point<usize>,
+        scope: TextObjectScope,
+        count: usize,
+    ) -> Option<Range<usize>> {
+        let lines = self.paragraph_lines(point.y)?;
+        let paragraph_start = self.inner.line_to_char(lines.start);
+        let paragraph_end = self.inner.line_to_char(lines.end);
+        let text = self
+            .inner
+            .slice(paragraph_start..paragraph_end)
+            .chars()
+            .collect::<Vec<_>>();
+
+        let sentences = split_sentences(&text);
+        let cursor = self.point_to_char(point) - paragraph_start;
+        let first = sentences.iter().position(|s| cursor < s.blanks_end)?;
+        let last = (first + count - 1).min(sentences.len() - 1);
+
+        let range = match scope {
+            // on the blanks between two sentences, only the blanks are selected
+            TextObjectScope::Inner if cursor >= sentences[first].end => {
+                sentences[first].end..sentences[first].blanks_end
+            }
+            TextObjectScope::Inner => sentences[first].start..sentences[last].end,
+            TextObjectScope::Around if cursor >= sentences[first].end => {
+                let last = (first + count).min(sentences.len() - 1);
+                sentences[first].end..sentences[last].end
+            }
+            // the last sentence of a paragraph has no blanks after it but the line break, so the
+            // ones before it are taken instead
+            TextObjectScope::Around if last == sentences.len() - 1 && first > 0 => {
+                sentences[first - 1].end..sentences[last].end
+            }
+            TextObjectScope::Around if last == sentences.len() - 1 => {
+                sentences[first].start..sentences[last].end
+            }
+            TextObjectScope::Around => sentences[first].start..sentences[last].blanks_end,
+        };
+
+        Some(paragraph_start + range.start..paragraph_start + range.end)
+    }
+
+    fn select_paragraph(
+        &self,
+        line_idx: usize,
+        scope: TextObjectScope,
+        count: usize,
+    ) -> Option<Range<usize>> {
+        let len_lines = self.len_file_lines();
+        if line_idx >= len_lines {
+            return None;
+        }
+
+        // paragraphs and the blank lines between them are runs of lines, like the words of a line
+        let is_blank = |idx: usize| self.line(idx).chars().all(char::is_whitespace);
+        let run_end = |start: usize| {
+            (start..len_lines)
+                .find(|&idx| is_blank(idx) != is_blank(start))
+                .unwrap_or(len_lines)
+        };
+
+        let starts_on_blank = is_blank(line_idx);
+        let mut start = line_idx;
+        while start > 0 && is_blank(start - 1) == starts_on_blank {
+            start -= 1;
+        }
+
+        let mut end = start;
+        let runs = match scope {
+            TextObjectScope::Inner => count,
+            TextObjectScope::Around => count * 2,
+        };
+        for _ in 0..runs {
+            if end < len_lines {
+                end = run_end(end);
+            }
+        }
+
+        // without blank lines after the paragraph, the ones before it are taken instead
+        let has_trailing_blanks = is_blank(end - 1);
+        if matches!(scope, TextObjectScope::Around) && !has_trailing_blanks {
+            while start > 0 && is_blank(start - 1) {
+                start -= 1;
+            }
+        }
+
+        Some(self.inner.line_to_char(start)..self.inner.line_to_char(end))
+    }
+
+    fn select_pair(
+        &self,
+        point: Point<usize>,
+        open: char,
+        close: char,
+        scope: TextObjectScope,
+        count: usize,
+    ) -> Option<Range<usize>> {
+        let cursor = self.point_to_char(point);
+
+        let mut open_idx = match self.inner.get_char(cursor) {
+            Some(ch) if ch == open => cursor,
+            _ => self.find_unclosed(cursor, open, close)?,
+        };
+        for _ in 1..count {
+            open_idx = self.find_unclosed(open_idx, open, close)?;
+        }
+        let close_idx = self.find_closing(open_idx, open, close)?;
+
+        if matches!(scope, TextObjectScope::Around) {
+            return Some(open_idx..close_idx + 1);
+        }
+
+        // a block spanning lines keeps the lines of its brackets, like `di{` on a function body
+        let mut start = open_idx + 1;
+        let mut end = close_idx;
+        if self.inner.get_char(start) == Some('\n') {
+            start += 1;
+        }
+
+        let close_line = self.inner.char_to_line(close_idx);
+        let close_line_start = self.inner.line_to_char(close_line);
+        let is_close_indented = self
+            .inner
+            .slice(close_line_start..close_idx)
+            .chars()
+            .all(char::is_whitespace);
+        if close_line > self.inner.char_to_line(open_idx) && is_close_indented {
+            end = close_line_start;
+        }
+
+        Some(start.min(end)..end)
+    }
+
+    /// The char index of the `open` bracket before `char_idx` that isn't closed before it.
+    fn find_unclosed(&self, char_idx: usize, open: char, close: char) -> Option<usize> {
+        let mut chars = self.inner.chars_at(char_idx);
+        let mut idx = char_idx;
+        let mut depth = 0;
+
+        while let Some(ch) = chars.prev() {
+            idx -= 1;
+            match ch {
+                ch if ch == close => depth += 1,
+                ch if ch == open && depth == 0 => return Some(idx),
+                ch if ch == open => depth -= 1,
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// The char index of the bracket that closes the `open` one at `open_idx`.
+    fn find_closing(&self, open_idx: usize, open: char, close: char) -> Option<usize> {
+        let mut depth = 0;
+
+        for (offset, ch) in self.inner.chars_at(open_idx + 1).enumerate() {
+            match ch {
+                ch if ch == open => depth += 1,
+                ch if ch == close && depth == 0 => return Some(open_idx + 1 + offset),
+                ch if ch == close => depth -= 1,
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    fn select_quote(
+        &self,
+        point: Point<usize>,
+        quote: char,
+        scope: TextObjectScope,
+        count: usize,
+    ) -> Option<Range<usize>> {
+        let line = self.get_line(point.y)?.chars().collect::<Vec<_>>();
+
+        // quotes pair up from the start of the line, so `"a" b "c"` never selects ` b `
+        let quotes = (0..line.len())
+            .filter(|&idx| line[idx] == quote && (idx == 0 || line[idx - 1] != '\\'))
+            .collect::<Vec<_>>();
+        let pairs = quotes.chunks_exact(2).collect::<Vec<_>>();
+
+        let (open, close) = match pairs
+            .iter()
+            .find(|pair| (pair[0]..=pair[1]).contains(&point.x))
+        {
+            Some(pair) => (pair[0], pair[1]),
+            // past the pairs or between them, the quotes after the cursor are taken
+            None => {
+                let next = quotes.iter().position(|&idx| idx > point.x)?;
+                (quotes[next], *quotes.get(next + 1)?)
+            }
+        };
+
+        let range = match scope {
+            // a count includes the quotes, but not the blanks around them
+            TextObjectScope::Inner if count > 1 => open..close + 1,
+            TextObjectScope::Inner => open + 1..close,
+            TextObjectScope::Around => {
+                let is_blank = |idx: usize| matches!(line.get(idx), Some(' ' | '\t'));
+                let mut end = close + 1;
+                while is_blank(end) {
+                    end += 1;
+                }
+
+                // without blanks after the quotes, the ones before them are taken instead
+                let mut start = open;
+                if end == close + 1 {
+                    while start > 0 && is_blank(start - 1) {
+                        start -= 1;
+                    }
+                }
+
+                start..end
+            }
+        };
+
+        let line_start = self.inner.line_to_char(point.y);
+        Some(line_start + range.start..line_start + range.end)
+    }
+
+    fn select_tag(
+        &self,
+        point: Point<usize>,
+        scope: TextObjectScope,
+        count: usize,
+    ) -> Option<Range<usize>> {
+        let cursor = self.point_to_char(point);
+        let text = self.inner.chars().collect::<Vec<_>>();
+
+        // elements are paired with a stack, so an unclosed tag like `<br>` is skipped over
+        let mut open_tags: Vec<Tag> = vec![];
+        let mut elements = vec![];
+        for tag in find_tags(&text) {
+            if !tag.is_closing {
+                open_tags.push(tag);
+                continue;
+            }
+
+            if let Some(idx) = open_tags.iter().rposition(|open| open.name == tag.name) {
+                let open = open_tags.drain(idx..).next()?;
+                elements.push((open.range, tag.range));
+            }
+        }
+
+        let mut enclosing = elements
+            .into_iter()
+            .filter(|(open, close)| open.start <= cursor && cursor < close.end)
+            .collect::<Vec<_>>();
+        // the innermost element starts last
+        enclosing.sort_by_key(|(open, _)| std::cmp::Reverse(open.start));
+        let (open, close) = enclosing.get(count - 1)?;
+
+        match scope {
+            TextObjectScope::Inner => Some(open.end..close.start),
+            TextObjectScope::Around => Some(open.start..close.end),
+        }
+    }
+
+    /// The lines of the paragraph `line_idx` is in, or `None` when it's a blank line.
+    fn paragraph_lines(&self, line_idx: usize) -> Option<Range<usize>> {
+        let len_lines = self.len_file_lines();
+        let is_blank = |idx: usize| self.line(idx).chars().all(char::is_whitespace);
+        if line_idx >= len_lines || is_blank(line_idx) {
+            return None;
+        }
+
+        let start = (0..line_idx)
+            .rev()
+            .find(|&idx| is_blank(idx))
+            .map_or(0, |idx| idx + 1);
+        let end = (line_idx..len_lines)
+            .find(|&idx| is_blank(idx))
+            .unwrap_or(len_lines);
+
+        Some(start..end)
+    }
+}
+
+/// Splits the text of a paragraph into sentences, which end at a `.`, `!` or `?`, maybe followed
+/// by closing brackets and quotes, and then by a blank or the end of the paragraph.
+fn split_sentences(text: &[char]) -> Vec<Sentence> {
+    let skip_blanks = |mut idx: usize| {
+        while idx < text.len() && text[idx].is_whitespace() {
+            idx += 1;
+        }
+        idx
+    };
+
+    let mut sentences = vec![];
+    let mut start = skip_blanks(0);
+    let mut idx = start;
+
+    while idx < text.len() {
+        if !matches!(text[idx], '.' | '!' | '?') {
+            idx += 1;
+            continue;
+        }
+
+        let mut end = idx + 1;
+        while end < text.len() && matches!(text[end], ')' | ']' | '"' | '\'') {
+            end += 1;
+        }
+
+        if end < text.len() && !text[end].is_whitespace() {
+            idx = end;
+            continue;
+        }
+
+        let blanks_end = skip_blanks(end);
+        sentences.push(Sentence {
+            start,
+            end,
+            blanks_end,
+        });
+        start = blanks_end;
+        idx = blanks_end;
+    }
+
+    if start < text.len() {
+        let end = text
+            .iter()
+            .rposition(|ch| !ch.is_whitespace())
+            .map_or(start, |idx| idx + 1);
+        sentences.push(Sentence {
+            start,
+            end,
+            blanks_end: text.len(),
+        });
+    }
+
+    sentences
+}
+
+/// Finds the tags in `text`, leaving out self-closing ones, comments and declarations.
+fn find_tags(text: &[char]) -> Vec<Tag> {
+    let mut tags = vec![];
+    let mut idx = 0;
+
+    while idx < text.len() {
+        if text[idx] != '<' {
+            idx += 1;
+            continue;
+        }
+
+        let Some(len) = text[idx + 1..]
+            .iter()
+            .position(|&ch| ch == '>' || ch == '<')
+        else {
+            break;
+        };
+        let end = idx + 1 + len;
+        if text[end] == '<' {
+            idx = end;
+            continue;
+        }
+
+        let inside = &text[idx + 1..end];
+        let is_closing = inside.first() == Some(&'/');
+        let name = inside
+            .iter()
+            .skip(is_closing as usize)
+            .take_while(|ch| ch.is_alphanumeric() || matches!(ch, '-' | '_' | ':' | '.'))
+            .collect::<String>();
+        let is_self_closing = inside.last() == Some(&'/');
+
+        if !name.is_empty() && !is_self_closing {
+            tags.push(Tag {
+                range: idx..end + 1,
+                name,
+                is_closing,
+            });
+        }
+
+        idx = end + 1;
+    }
+
+    tags
+}
@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+/// Which side of an insertion a position ends up on when text is inserted exactly where it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Assoc {
+    /// The position stays before the inserted text.
+    Before,
+    /// The position moves past the inserted text, like a cursor that is typing.
+    After,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Edit {
+    range: Range<usize>,
+    text: String,
+}
+
+/// A set of insertions and deletions described against a single version of a [`TextObject`].
+///
+/// Every offset is a char index into the content as it was before the transaction, so edits can
+/// be added in any order without accounting for the ones before them. Edits can't overlap, and
+/// the whole transaction is checked against the content before anything is applied.
+///
+/// Applying a transaction returns its inverse, and positions such as cursors can be carried over
+/// to the new content with [`Transaction::map_position`].
+///
+/// [`TextObject`]: super::TextObject
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Transaction {
+    /// Sorted by position, never overlapping.
+    edits: Vec<Edit>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, char_idx: usize, text: impl Into<String>) -> &mut Self {
+        self.replace(char_idx..char_idx, text)
+    }
+
+    pub fn delete(&mut self, range: Range<usize>) -> &mut Self {
+        self.replace(range, String::new())
+    }
+
+    /// Replaces the text in `range` with `text`.
+    ///
+    /// # Panics
+    ///
+    /// Panics when `range` overlaps an edit already in the transaction, or starts where another
+    /// edit starts.
+    pub fn replace(&mut self, range: Range<usize>, text: impl Into<String>) -> &mut Self {
+        assert!(range.start <= range.end);
+
+        let idx = self
+            .edits
+            .partition_point(|edit| edit.range.start < range.start);
+        let overlaps_next = self
+            .edits
+            .get(idx)
+            .is_some_and(|next| next.range.start < range.end || next.range.start == range.start);
+        let overlaps_prev = idx
+            .checked_sub(1)
+            .is_some_and(|prev| self.edits[prev].range.end > range.start);
+        assert!(!overlaps_next && !overlaps_prev, "edits can't overlap");
+
+        let text = text.into();
+        if !range.is_empty() || !text.is_empty() {
+            self.edits.insert(idx, Edit { range, text });
+        }
+
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Where `char_idx` ends up once the transaction is applied. Positions inside deleted text move
+    /// to where the deletion started.
+    pub fn map_position(&self, char_idx: usize, assoc: Assoc) -> usize {
+        let mut added = 0;
+        let mut removed = 0;
+
+        for edit in self.edits.iter() {
+            if char_idx < edit.range.start {
+                break;
+            }
+
+            let inserted = edit.text.chars().count();
+            if char_idx < edit.range.end || char_idx == edit.range.start {
+                let start = edit.range.start + added - removed;
+                return match assoc {
+                    Assoc::Before => start,
+                    Assoc::After => start + inserted,
+                };
+            }
+
+            added += inserted;
+            removed += edit.range.len();
+        }
+
+        char_idx + added - removed
+    }
+
+    pub(super) fn edits(&self) -> impl DoubleEndedIterator<Item = (&Range<usize>, &str)> {
+        self.edits
+            .iter()
+            .map(|edit| (&edit.range, edit.text.as_str()))
+    }
+
+    /// Builds the transaction that undoes this one, from the text each edit removed. Its offsets
+    /// point into the content as it is after this transaction was applied.
+    pub(super) fn inverse(&self, removed_texts: Vec<String>) -> Self {
+        let mut added = 0;
+        let mut removed = 0;
+
+        let edits = self
+            .edits
+            .iter()
+            .zip(removed_texts)
+            .map(|(edit, removed_text)| {
+                let start = edit.range.start + added - removed;
+                let inserted = edit.text.chars().count();
+                added += inserted;
+                removed += edit.range.len();
+
+                Edit {
+                    range: start..start + inserted,
+                    text: removed_text,
+                }
+            })
+            .collect();
+
+        Self { edits }
+    }
+}
@@ -0,0 +1,236 @@
+use super::{TextObject, is_word_char};
+use crate::geometry::Point;
+
+/// What a character is when telling words apart. With `WORD`s, every non-blank character is a
+/// word character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum CharClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+pub(super) fn char_class(ch: char, big_word: bool) -> CharClass {
+    match ch {
+        ch if ch.is_whitespace() => CharClass::Blank,
+        _ if big_word => CharClass::Word,
+        ch if is_word_char(ch) => CharClass::Word,
+        _ => CharClass::Punctuation,
+    }
+}
+
+// The motions walk the whole text by grapheme cluster, classified by their first character, with
+// line breaks counting as blanks. Empty lines are words of their own for `w`, `b` and `ge`, but
+// not for `e`, like in vim.
+impl TextObject {
+    /// `w` and `W`, the start of the next word, or the end of the text when there's none.
+    pub fn find_next_word_start(&self, point: Point<usize>, big_word: bool) -> Point<usize> {
+        let char_idx = self.point_to_char(point);
+        if char_idx >= self.len_chars() {
+            return point;
+        }
+
+        match self.next_word_start(char_idx, big_word) {
+            Some(char_idx) => self.char_to_point(char_idx),
+            None => self.char_to_point(self.last_grapheme_idx()),
+        }
+    }
+
+    /// Where `dw` and `dW` stop deleting, `count` words after `point`. Unlike `w`, the end never
+    /// goes past the line the last word is on, so its line break is kept.
+    pub fn find_word_operator_end(
+        &self,
+        point: Point<usize>,
+        big_word: bool,
+        count: usize,
+    ) -> Point<usize> {
+        let mut char_idx = self.point_to_char(point);
+        let mut line_idx = point.y;
+
+        for _ in 0..count {
+            if char_idx >= self.len_chars() {
+                break;
+            }
+
+            line_idx = self.char_to_point(char_idx).y;
+            match self.next_word_start(char_idx, big_word) {
+                Some(next) => char_idx = next,
+                None => {
+                    char_idx = self.len_chars();
+                    break;
+                }
+            }
+        }
+
+        match self.char_to_point(char_idx) {
+            end if end.y > line_idx => {
+                Point::new(self.line_len(line_idx).saturating_sub(1), line_idx)
+            }
+            end => end,
+        }
+    }
+
+    /// Where `cw` and `cW` stop changing, at the end of the word under `point` even when it's
+    /// already there, then at the end of the `count - 1` words after it.
+    pub fn find_change_word_end(
+        &self,
+        point: Point<usize>,
+        big_word: bool,
+        count: usize,
+    ) -> Point<usize> {
+        let char_idx = self.point_to_char(point);
+        let next = self.next_grapheme_boundary(char_idx);
+        let at_word_end = next >= self.len_chars()
+            || self.class_at(next, big_word) != self.class_at(char_idx, big_word);
+
+        let mut end = match at_word_end {
+            true => point,
+            false => self.find_next_word_end(point, big_word),
+        };
+        for _ in 1..count {
+            end = self.find_next_word_end(end, big_word);
+        }
+
+        end
+    }
+
+    /// The start of the next word after `char_idx`, or `None` when the text ends first.
+    fn next_word_start(&self, mut char_idx: usize, big_word: bool) -> Option<usize> {
+        let len_chars = self.len_chars();
+
+        let class = self.class_at(char_idx, big_word);
+        char_idx = self.next_grapheme_boundary(char_idx);
+        if class != CharClass::Blank {
+            while char_idx < len_chars && self.class_at(char_idx, big_word) == class {
+                char_idx = self.next_grapheme_boundary(char_idx);
+            }
+        }
+
+        while char_idx < len_chars
+            && self.class_at(char_idx, big_word) == CharClass::Blank
+            && !self.is_empty_line_at(char_idx)
+        {
+            char_idx = self.next_grapheme_boundary(char_idx);
+        }
+
+        (char_idx < len_chars).then_some(char_idx)
+    }
+
+    /// `b` and `B`, the start of the word before the cursor, or of the one it's in when it isn't
+    /// at its start.
+    pub fn find_prev_word_start(&self, point: Point<usize>, big_word: bool) -> Point<usize> {
+        let mut char_idx = self.point_to_char(point);
+        if char_idx == 0 {
+            return point;
+        }
+
+        char_idx = self.prev_grapheme_boundary(char_idx);
+        while char_idx > 0
+            && self.class_at(char_idx, big_word) == CharClass::Blank
+            && !self.is_empty_line_at(char_idx)
+        {
+            char_idx = self.prev_grapheme_boundary(char_idx);
+        }
+
+        let class = self.class_at(char_idx, big_word);
+        if class != CharClass::Blank {
+            while char_idx > 0 {
+                let prev = self.prev_grapheme_boundary(char_idx);
+                if self.class_at(prev, big_word) != class {
+                    break;
+                }
+                char_idx = prev;
+            }
+        }
+
+        self.char_to_point(char_idx)
+    }
+
+    /// `e` and `E`, the end of the word the cursor is in, or of the next one when it's already
+    /// at its end.
+    pub fn find_next_word_end(&self, point: Point<usize>, big_word: bool) -> Point<usize> {
+        let len_chars = self.len_chars();
+        let mut char_idx = self.point_to_char(point);
+        if char_idx >= len_chars {
+            return point;
+        }
+
+        char_idx = self.next_grapheme_boundary(char_idx);
+        while char_idx < len_chars && self.class_at(char_idx, big_word) == CharClass::Blank {
+            char_idx = self.next_grapheme_boundary(char_idx);
+        }
+
+        if char_idx >= len_chars {
+            return self.char_to_point(self.last_grapheme_idx());
+        }
+
+        let class = self.class_at(char_idx, big_word);
+        loop {
+            let next = self.next_grapheme_boundary(char_idx);
+            if next >= len_chars || self.class_at(next, big_word) != class {
+                break;
+            }
+            char_idx = next;
+        }
+
+        self.char_to_point(char_idx)
+    }
+
+    /// `ge` and `gE`, the end of the word before the cursor.
+    pub fn find_prev_word_end(&self, point: Point<usize>, big_word: bool) -> Point<usize> {
+        let mut char_idx = self.point_to_char(point);
+        if char_idx == 0 {
+            return point;
+        }
+
+        // past the end of the text, after its final line break
+        let class = match char_idx < self.len_chars() {
+            true => self.class_at(char_idx, big_word),
+            false => CharClass::Blank,
+        };
+        char_idx = self.prev_grapheme_boundary(char_idx);
+        if class != CharClass::Blank {
+            while self.class_at(char_idx, big_word) == class {
+                if char_idx == 0 {
+                    return Point::default();
+                }
+                char_idx = self.prev_grapheme_boundary(char_idx);
+            }
+        }
+
+        while self.class_at(char_idx, big_word) == CharClass::Blank
+            && !self.is_empty_line_at(char_idx)
+        {
+            if char_idx == 0 {
+                break;
+            }
+            char_idx = self.prev_grapheme_boundary(char_idx);
+        }
+
+        self.char_to_point(char_idx)
+    }
+
+    fn class_at(&self, char_idx: usize, big_word: bool) -> CharClass {
+        char_class(self.inner.char(char_idx), big_word)
+    }
+
+    /// Whether `char_idx` is the line break of an empty line.
+    fn is_empty_line_at(&self, char_idx: usize) -> bool {
+        self.inner.char(char_idx) == '\n'
+            && (char_idx == 0 || self.inner.char(char_idx - 1) == '\n')
+    }
+
+    /// Where the last grapheme cluster of the text starts, not counting the final line break, or
+    /// that line break when the last line is empty.
+    fn last_grapheme_idx(&self) -> usize {
+        let mut end = self.len_chars();
+        if end > 0 && self.inner.char(end - 1) == '\n' {
+            end -= 1;
+        }
+
+        match end == 0 || self.inner.char(end - 1) == '\n' {
+            true => end,
+            false => self.prev_grapheme_boundary(end),
+        }
+    }
+}
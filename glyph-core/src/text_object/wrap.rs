@@ -0,0 +1,144 @@
+use unicode_width::UnicodeWidthStr;
+
+use super::grapheme::{Grapheme, line_graphemes};
+
+/// How few columns wrapped rows keep for text, however deep `breakindent` and `showbreak` push it.
+const MIN_WRAPPED_WIDTH: usize = 20;
+
+/// How lines wider than a view are broken into rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WrapOptions<'a> {
+    /// How many columns a row has.
+    pub width: usize,
+    /// Whether lines break after a blank rather than in the middle of a word.
+    pub linebreak: bool,
+    /// Whether wrapped rows are indented like the start of their line.
+    pub breakindent: bool,
+    /// Drawn at the start of every wrapped row.
+    pub showbreak: &'a str,
+}
+
+/// A part of a line drawn on a row of its own.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DisplayRow {
+    /// The char column of the first grapheme cluster on the row.
+    pub start: usize,
+    /// The char column right after the last grapheme cluster on the row, not counting the line
+    /// break.
+    pub end: usize,
+    /// The display column of the line the row starts at.
+    pub column: usize,
+    /// How many columns the text is pushed right by, for `breakindent` and `showbreak`.
+    pub indent: usize,
+}
+
+impl DisplayRow {
+    /// A row with the whole of a line, for when lines aren't wrapped.
+    pub fn unwrapped(line: &str) -> Self {
+        Self {
+            end: line.trim_end_matches(['\n', '\r']).chars().count(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether the row continues a line that didn't fit on the rows above it.
+    pub fn is_wrapped(&self) -> bool {
+        self.start > 0
+    }
+
+    /// The column of the row display `column` of the line is drawn at.
+    pub fn screen_x(&self, column: usize) -> usize {
+        self.indent + column.saturating_sub(self.column)
+    }
+
+    /// The display column of the line drawn at `screen_x` of the row.
+    pub fn line_column(&self, screen_x: usize) -> usize {
+        self.column + screen_x.saturating_sub(self.indent)
+    }
+}
+
+/// Breaks `line` into rows of at most `options.width` columns. Every line takes at least one row,
+/// even when it's empty.
+pub fn wrap_line(line: &str, tabstop: usize, options: WrapOptions<'_>) -> Vec<DisplayRow> {
+    let graphemes = line_graphemes(line, tabstop)
+        .filter(|grapheme| !matches!(grapheme.text, "\n" | "\r\n" | "\r"))
+        .collect::<Vec<_>>();
+
+    let leading_blanks = graphemes
+        .iter()
+        .take_while(|grapheme| is_blank(grapheme))
+        .last()
+        .map_or(0, |grapheme| grapheme.column + grapheme.width);
+    let indent = match options.breakindent {
+        true => leading_blanks + options.showbreak.width(),
+        false => options.showbreak.width(),
+    };
+    let indent = indent.min(options.width.saturating_sub(MIN_WRAPPED_WIDTH));
+
+    let mut rows = vec![];
+    let mut row_start = 0;
+    let mut row_indent = 0;
+    let mut used = 0;
+    // where the row can break with `linebreak`, right after its last blank
+    let mut after_blank = None;
+    let mut i = 0;
+
+    while i < graphemes.len() {
+        let grapheme = graphemes[i];
+
+        // a grapheme wider than a whole row still gets a row of its own
+        if row_indent + used + grapheme.width <= options.width || i == row_start {
+            used += grapheme.width;
+            i += 1;
+            if is_blank(&grapheme) {
+                after_blank = Some(i);
+            }
+            continue;
+        }
+
+        // with `linebreak`, a blank that doesn't fit hangs past the edge of its row rather than
+        // starting the next one
+        let break_at = match (options.linebreak, is_blank(&grapheme)) {
+            (true, true) => i + 1,
+            (true, false) => after_blank.unwrap_or(i),
+            (false, _) => i,
+        };
+
+        rows.push(row(&graphemes, row_start..break_at, row_indent));
+        row_start = break_at;
+        row_indent = indent;
+        used = 0;
+        after_blank = None;
+        i = break_at;
+    }
+
+    rows.push(row(&graphemes, row_start..graphemes.len(), row_indent));
+    rows
+}
+
+/// The index of the row the grapheme cluster at char column `x` is drawn on. Columns past the end
+/// of the line are on its last row.
+pub fn find_display_row(rows: &[DisplayRow], x: usize) -> usize {
+    rows.iter().rposition(|row| row.start <= x).unwrap_or(0)
+}
+
+fn row(graphemes: &[Grapheme<'_>], range: std::ops::Range<usize>, indent: usize) -> DisplayRow {
+    let line_end = graphemes.last().map_or((0, 0), |last| {
+        (last.x + last.text.chars().count(), last.column + last.width)
+    });
+    let (start, column) = graphemes
+        .get(range.start)
+        .map_or(line_end, |first| (first.x, first.column));
+    let end = graphemes.get(range.end).map_or(line_end.0, |next| next.x);
+
+    DisplayRow {
+        start,
+        end,
+        column,
+        indent,
+    }
+}
+
+fn is_blank(grapheme: &Grapheme<'_>) -> bool {
+    matches!(grapheme.text, " " | "\t")
+}
@@ -1,10 +1,11 @@
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use crate::buffer_manager::BufferId;
+use crate::buffer_manager::{Buffer, BufferId, BufferManager};
 use crate::config::{Config, StatuslineMode};
 use crate::cursor::Cursor;
 use crate::geometry::{Point, Rect, Size};
+use crate::text_object::{DisplayRow, WrapOptions, find_display_row};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ViewId(u64);
@@ -43,21 +44,147 @@ impl From<u8> for ViewId {
     }
 }
 
+/// Where a view was in a buffer, restored when the view shows that buffer again.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BufferPosition {
+    pub scroll_offset: Point<usize>,
+    pub skipped_rows: usize,
+    pub cursors: Vec<Cursor>,
+}
+
+/// Options each view has its own copy of, as they depend on how wide the view is.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ViewOptions {
+    pub wrap: bool,
+    pub linebreak: bool,
+    pub breakindent: bool,
+    pub showbreak: String,
+}
+
+impl From<&Config> for ViewOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            wrap: config.wrap,
+            linebreak: config.linebreak,
+            breakindent: config.breakindent,
+            showbreak: config.showbreak.clone(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct View {
     pub id: ViewId,
     pub buffer_id: BufferId,
+    /// The first line shown and how many display columns the view is scrolled sideways by.
     pub scroll_offset: Point<usize>,
+    /// How many rows of the first line are scrolled past when lines wrap, which only happens when
+    /// that line doesn't fit in the view.
+    pub skipped_rows: usize,
     pub cursors: Vec<Cursor>,
+    /// The buffer shown before the current one.
+    pub alternate_buffer: Option<BufferId>,
+    pub options: ViewOptions,
+    positions: BTreeMap<BufferId, BufferPosition>,
 }
 
 impl View {
-    pub fn new(id: ViewId, buffer_id: BufferId) -> Self {
+    pub fn new(id: ViewId, buffer_id: BufferId, options: ViewOptions) -> Self {
         Self {
             id,
             buffer_id,
             scroll_offset: Point::default(),
+            skipped_rows: 0,
             cursors: vec![Cursor::default()],
+            alternate_buffer: None,
+            options,
+            positions: BTreeMap::new(),
+        }
+    }
+
+    /// The rows line `line_idx` of `buffer` takes in a view `width` columns wide. A line is a
+    /// single row however long it is when lines don't wrap.
+    pub fn display_rows(&self, buffer: &Buffer, line_idx: usize, width: usize) -> Vec<DisplayRow> {
+        let content = buffer.content();
+        if !self.options.wrap {
+            let line = content
+                .get_line(line_idx)
+                .map(String::from)
+                .unwrap_or_default();
+            return vec![DisplayRow::unwrapped(&line)];
+        }
+
+        let options = WrapOptions {
+            width,
+            linebreak: self.options.linebreak,
+            breakindent: self.options.breakindent,
+            showbreak: &self.options.showbreak,
+        };
+        content.wrap_line(line_idx, buffer.tabstop(), options)
+    }
+
+    /// The line and row drawn on each of the rows of a view of `size`, from the top down. Rows
+    /// past the end of the buffer are left out.
+    pub fn visible_rows(&self, buffer: &Buffer, size: Size) -> Vec<(usize, DisplayRow)> {
+        let height = size.height as usize;
+
+        (self.scroll_offset.y..buffer.content().len_lines())
+            .flat_map(|line_idx| {
+                self.display_rows(buffer, line_idx, size.width as usize)
+                    .into_iter()
+                    .map(move |row| (line_idx, row))
+            })
+            .skip(self.skipped_rows)
+            .take(height)
+            .collect()
+    }
+
+    /// Shows another buffer, remembering the position in the current one and restoring the
+    /// position the view had last time it showed `buffer_id`.
+    pub fn show_buffer(&mut self, buffer_id: BufferId) {
+        if self.buffer_id == buffer_id {
+            return;
+        }
+
+        let position = self
+            .positions
+            .remove(&buffer_id)
+            .unwrap_or_else(|| BufferPosition {
+                scroll_offset: Point::default(),
+                skipped_rows: 0,
+                cursors: vec![Cursor::default()],
+            });
+
+        let previous = BufferPosition {
+            scroll_offset: std::mem::replace(&mut self.scroll_offset, position.scroll_offset),
+            skipped_rows: std::mem::replace(&mut self.skipped_rows, position.skipped_rows),
+            cursors: std::mem::replace(&mut self.cursors, position.cursors),
+        };
+
+        self.positions.insert(self.buffer_id, previous);
+        self.alternate_buffer = Some(self.buffer_id);
+        self.buffer_id = buffer_id;
+    }
+
+    /// The position of the view in `buffer_id`, whether it is the one being shown or not.
+    pub fn position_in(&self, buffer_id: BufferId) -> Option<BufferPosition> {
+        if self.buffer_id == buffer_id {
+            return Some(BufferPosition {
+                scroll_offset: self.scroll_offset,
+                skipped_rows: self.skipped_rows,
+                cursors: self.cursors.clone(),
+            });
+        }
+
+        self.positions.get(&buffer_id).cloned()
+    }
+
+    /// Drops everything the view remembers about a buffer that was closed.
+    pub fn forget_buffer(&mut self, buffer_id: BufferId) {
+        self.positions.remove(&buffer_id);
+
+        if self.alternate_buffer == Some(buffer_id) {
+            self.alternate_buffer = None;
         }
     }
 }
@@ -185,7 +312,7 @@ impl ViewManager {
         let size = size.into();
         let mut views = BTreeMap::new();
         let view_id = ViewId::new(0);
-        let view = View::new(view_id, initial_buffer);
+        let view = View::new(view_id, initial_buffer, ViewOptions::from(config.as_ref()));
         views.insert(view_id, view);
 
         let layout = LayoutTreeNode::Leaf(LeafView::new(&config, view_id, size.into()));
@@ -227,6 +354,37 @@ impl ViewManager {
             .expect("editor must have at least one view")
     }
 
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut View> {
+        self.views.values_mut()
+    }
+
+    /// Clamps the cursors of every view showing `buffer`, for when its content was replaced.
+    pub fn clamp_cursors(&mut self, buffer: &Buffer) {
+        let views = self.views.values_mut().filter(|v| v.buffer_id == buffer.id);
+        for cursor in views.flat_map(|v| v.cursors.iter_mut()) {
+            cursor.clamp_to_buffer(buffer);
+        }
+    }
+
+    /// Scrolls the active view so that its first cursor is visible. The view scrolls sideways by
+    /// display columns, so a wide character under the cursor is never cut in half, or by rows when
+    /// lines wrap.
+    pub fn scroll_to_cursor(&mut self, buffers: &BufferManager) {
+        let layout = self.get_layout_for_view(self.active_view);
+        let view = self.get_mut_active_view();
+        let buffer = buffers
+            .get(view.buffer_id)
+            .expect("view references non-existing buffer");
+
+        let view_height = layout.usable_rect.height as usize;
+        let view_width = layout.usable_rect.width as usize;
+
+        match view.options.wrap {
+            true => scroll_wrapped_view(view, buffer, view_width, view_height),
+            false => scroll_view(view, buffer, view_width, view_height),
+        }
+    }
+
     pub fn get_layout_for_view(&self, view_id: ViewId) -> LeafView {
         assert!(self.views.contains_key(&view_id));
         let mut finder = LayoutFinder::new(view_id, LeafView::default());
@@ -234,3 +392,98 @@ impl ViewManager {
         finder.leaf
     }
 }
+
+fn scroll_view(view: &mut View, buffer: &Buffer, view_width: usize, view_height: usize) {
+    let cursor = view.cursors.first().unwrap();
+
+    if cursor.y.saturating_sub(view.scroll_offset.y) > view_height - 1 {
+        view.scroll_offset.y = cursor.y - (view_height - 1)
+    }
+
+    if cursor.y.saturating_sub(view.scroll_offset.y) == 0 {
+        let vertical_offset = view.scroll_offset.y.saturating_sub(cursor.y);
+        view.scroll_offset.y = view.scroll_offset.y.saturating_sub(vertical_offset);
+    }
+
+    let column = buffer.display_column(Point::new(cursor.x, cursor.y));
+    let next_column = buffer.display_column(Point::new(cursor.x + 1, cursor.y));
+    let cursor_width = next_column.saturating_sub(column).max(1);
+
+    if column < view.scroll_offset.x {
+        view.scroll_offset.x = column;
+    }
+
+    if column + cursor_width > view.scroll_offset.x + view_width {
+        view.scroll_offset.x = (column + cursor_width).saturating_sub(view_width);
+    }
+}
+
+/// Scrolls a view whose lines wrap by whole lines, only scrolling past the rows of the first line
+/// when the line the cursor is on doesn't fit in the view by itself.
+fn scroll_wrapped_view(view: &mut View, buffer: &Buffer, view_width: usize, view_height: usize) {
+    let cursor = *view.cursors.first().unwrap();
+    let rows_of = |line_idx| view.display_rows(buffer, line_idx, view_width).len();
+    let cursor_row = find_display_row(&view.display_rows(buffer, cursor.y, view_width), cursor.x);
+
+    let (mut top, mut skipped) = (view.scroll_offset.y, view.skipped_rows);
+    // every line takes at least a row, so the lines further up can't be in view
+    if cursor.y >= top + view_height {
+        (top, skipped) = (cursor.y + 1 - view_height, 0);
+    }
+    skipped = skipped.min(rows_of(top).saturating_sub(1));
+
+    if (cursor.y, cursor_row) < (top, skipped) {
+        view.scroll_offset = Point::new(0, cursor.y);
+        view.skipped_rows = cursor_row;
+        return;
+    }
+
+    // the rows from the top of the view down to the cursor, both included
+    let mut rows = (top..cursor.y).map(rows_of).sum::<usize>() + cursor_row + 1 - skipped;
+    while rows > view_height {
+        if top == cursor.y {
+            skipped += rows - view_height;
+            break;
+        }
+
+        rows -= rows_of(top) - skipped;
+        (top, skipped) = (top + 1, 0);
+    }
+
+    view.scroll_offset = Point::new(0, top);
+    view.skipped_rows = skipped;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer_manager::scratch_buffer;
+
+    #[test]
+    fn test_scroll_wrapped_view() {
+        let buffer = scratch_buffer(&format!("short\n{}\nx\n", "a".repeat(70)));
+        let options = ViewOptions {
+            wrap: true,
+            ..Default::default()
+        };
+        let mut view = View::new(ViewId::new(0), buffer.id, options);
+        let mut scroll_to = |x, y| {
+            *view.cursors.first_mut().unwrap() = Cursor { x, y, virtual_x: x };
+            scroll_wrapped_view(&mut view, &buffer, 20, 3);
+            (view.scroll_offset, view.skipped_rows)
+        };
+
+        // the long line takes four rows, which fit along with the one above as long as the cursor
+        // is on its first row
+        assert_eq!(scroll_to(0, 1), (Point::new(0, 0), 0));
+
+        // on its last row, the line doesn't fit in the view, so its first row is scrolled past
+        assert_eq!(scroll_to(65, 1), (Point::new(0, 1), 1));
+
+        // going back up shows the row with the cursor at the top
+        assert_eq!(scroll_to(0, 1), (Point::new(0, 1), 0));
+
+        // whole lines are scrolled past rather than rows of them
+        assert_eq!(scroll_to(0, 2), (Point::new(0, 2), 0));
+    }
+}
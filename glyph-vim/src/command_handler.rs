@@ -1,11 +1,29 @@
+mod operator;
+mod register;
+
 use glyph_core::buffer_manager::Buffer;
-use glyph_core::command_handler::{CommandContext, CommandHandler, CommandHandlerResult};
+use glyph_core::command_handler::{
+    CommandContext, CommandHandler, CommandHandlerResult, apply_transaction,
+};
 use glyph_core::cursor::Cursor;
+use glyph_core::error::{GlyphError, Result};
 use glyph_core::geometry::Point;
 use glyph_core::key_mapper::{Command, VimMode};
-
-#[derive(Debug)]
-pub struct VimBufferCommandHandler;
+use glyph_core::message::Message;
+use glyph_core::search::SearchPreview;
+use glyph_core::text_object::{
+    CharSearch, SearchDirection, SearchPattern, TextObject, Transaction, find_display_row,
+};
+use glyph_core::view_manager::View;
+
+/// Runs the commands of the vim keymaps, remembering the last `f`, `t`, `F` or `T` for `;` and
+/// `,`, and the text typed in insert mode for `".`.
+#[derive(Debug, Default)]
+pub struct VimBufferCommandHandler {
+    last_char_search: Option<CharSearch>,
+    /// What was typed since entering insert mode, which becomes `".` when leaving it.
+    inserted: String,
+}
 
 impl CommandHandler for VimBufferCommandHandler {
     fn handle_commands(&mut self, ctx: &mut CommandContext<'_>) -> CommandHandlerResult {
@@ -16,78 +34,388 @@ impl CommandHandler for VimBufferCommandHandler {
             .expect_vim();
 
         let mut status = CommandHandlerResult::Consumed;
+        let count = ctx.resolved_keymap.count;
+
+        begin_change(ctx);
+
+        // the keys that leave insert mode still run in it, so what was typed is kept once the
+        // editor is in another mode
+        if !matches!(mode, VimMode::Insert) && !self.inserted.is_empty() {
+            ctx.registers
+                .set_last_inserted(std::mem::take(&mut self.inserted));
+        }
 
         for command in ctx.resolved_keymap.commands.iter() {
-            match command {
-                Command::MoveCursorLeft => move_cursor_left(ctx),
-                Command::MoveCursorDown => move_cursor_down(ctx, mode),
-                Command::MoveCursorUp => move_cursor_up(ctx, mode),
-                Command::MoveCursorRight => move_cursor_right(ctx, mode),
-                Command::MoveCursorRightOverLines => move_cursor_right_over_lines(ctx, mode),
-                Command::MoveCursorLineStart => move_cursor_to_line_start(ctx),
-                Command::MoveCursorLineEnd => move_cursor_to_line_end(ctx, mode),
-                Command::DeleteWholeLine => delete_whole_line(ctx, mode),
-                Command::MoveToTop => move_to_top(ctx, mode),
-                Command::MoveToBottom => move_to_bottom(ctx, mode),
-                Command::PageUp => page_up(ctx, mode),
-                Command::PageDown => page_down(ctx, mode),
-                Command::MoveToMatchingPair => move_to_matching_pair(ctx, mode),
-                Command::MoveToFirstNonSpace => move_to_first_non_space(ctx),
-                Command::MoveToLastNonSpace => move_to_last_non_space(ctx),
-                Command::MoveToNextParagraph => move_to_next_paragraph(ctx),
-                Command::MoveToPrevParagraph => move_to_prev_paragraph(ctx),
-                Command::DeletePrevChar => delete_prev_char(ctx, mode),
-                Command::DeleteCurrChar => delete_curr_char(ctx, mode),
-                Command::TypeChar(c) => insert_character(ctx, mode, *c),
-                Command::MoveToNextWord => move_to_next_word(ctx, mode),
-                Command::Save => status = CommandHandlerResult::NotConsumed,
-                Command::SaveAll => status = CommandHandlerResult::NotConsumed,
-                Command::Quit => status = CommandHandlerResult::NotConsumed,
+            if let CommandHandlerResult::NotConsumed =
+                self.handle_command(ctx, command, mode, count)
+            {
+                status = CommandHandlerResult::NotConsumed;
             }
         }
 
-        scroll_view_to_cursor(ctx);
+        // an insert mode session is a single undo step, so changes are only grouped once the
+        // editor leaves insert mode
+        if !matches!(mode, VimMode::Insert) {
+            commit_changes(ctx);
+        }
+
+        ctx.views.scroll_to_cursor(ctx.buffers);
 
         status
     }
 }
 
-fn move_cursor_left(ctx: &mut CommandContext<'_>) {
+impl VimBufferCommandHandler {
+    fn handle_command(
+        &mut self,
+        ctx: &mut CommandContext<'_>,
+        command: &Command,
+        mode: VimMode,
+        count: Option<usize>,
+    ) -> CommandHandlerResult {
+        let times = count.unwrap_or(1);
+
+        match command {
+            Command::MoveCursorLeft => move_cursor_left(ctx, times),
+            Command::MoveCursorDown => move_cursor_down(ctx, mode, times),
+            Command::MoveCursorUp => move_cursor_up(ctx, mode, times),
+            Command::MoveCursorDisplayDown => {
+                (0..times).for_each(|_| move_cursor_display_down(ctx, mode))
+            }
+            Command::MoveCursorDisplayUp => {
+                (0..times).for_each(|_| move_cursor_display_up(ctx, mode))
+            }
+            Command::MoveCursorRight => move_cursor_right(ctx, mode, times),
+            Command::MoveCursorRightOverLines => move_cursor_right_over_lines(ctx, mode, times),
+            Command::MoveCursorLineStart => move_cursor_to_line_start(ctx),
+            Command::MoveCursorLineEnd => move_cursor_to_line_end(ctx, mode, times),
+            Command::MoveToTop => move_to_line(ctx, mode, count.unwrap_or(1)),
+            Command::MoveToBottom => move_to_line(ctx, mode, count.unwrap_or(usize::MAX)),
+            Command::PageUp => page_up(ctx, mode, count),
+            Command::PageDown => page_down(ctx, mode, count),
+            Command::MoveToMatchingPair => match count {
+                Some(percent) => move_to_percentage(ctx, mode, percent),
+                None => move_to_matching_pair(ctx, mode),
+            },
+            Command::MoveToFirstNonSpace => move_to_first_non_space(ctx),
+            Command::MoveToLastNonSpace => move_to_last_non_space(ctx, times),
+            Command::MoveToNextParagraph => (0..times).for_each(|_| move_to_next_paragraph(ctx)),
+            Command::MoveToPrevParagraph => (0..times).for_each(|_| move_to_prev_paragraph(ctx)),
+            Command::DeletePrevChar => (0..times).for_each(|_| {
+                if mode == VimMode::Insert {
+                    self.inserted.pop();
+                }
+                delete_prev_char(ctx, mode);
+            }),
+            Command::DeleteCurrChar => delete_curr_char(ctx, mode, times),
+            Command::TypeChar(c) => {
+                self.inserted.push(*c);
+                insert_character(ctx, *c);
+            }
+            Command::InsertTab => {
+                self.inserted.push('\t');
+                insert_tab(ctx);
+            }
+            Command::MoveToNextWord => {
+                move_by_word(ctx, mode, TextObject::find_next_word_start, false, times)
+            }
+            Command::MoveToNextBigWord => {
+                move_by_word(ctx, mode, TextObject::find_next_word_start, true, times)
+            }
+            Command::MoveToPrevWord => {
+                move_by_word(ctx, mode, TextObject::find_prev_word_start, false, times)
+            }
+            Command::MoveToPrevBigWord => {
+                move_by_word(ctx, mode, TextObject::find_prev_word_start, true, times)
+            }
+            Command::MoveToWordEnd => {
+                move_by_word(ctx, mode, TextObject::find_next_word_end, false, times)
+            }
+            Command::MoveToBigWordEnd => {
+                move_by_word(ctx, mode, TextObject::find_next_word_end, true, times)
+            }
+            Command::MoveToPrevWordEnd => {
+                move_by_word(ctx, mode, TextObject::find_prev_word_end, false, times)
+            }
+            Command::MoveToPrevBigWordEnd => {
+                move_by_word(ctx, mode, TextObject::find_prev_word_end, true, times)
+            }
+            Command::Operate {
+                operator,
+                motion,
+                force,
+            } => self.operate(ctx, *operator, motion, *force, mode, count),
+            // only meaningful as what an operator applies to
+            Command::SelectTextObject { .. } => {}
+            Command::SelectLines => {}
+            Command::FindChar(search) => {
+                self.last_char_search = Some(*search);
+                find_char(ctx, *search, false, times);
+            }
+            Command::RepeatCharSearch => {
+                if let Some(search) = self.last_char_search {
+                    find_char(ctx, search, true, times);
+                }
+            }
+            Command::RepeatCharSearchReversed => {
+                if let Some(search) = self.last_char_search {
+                    find_char(ctx, search.reversed(), true, times);
+                }
+            }
+            Command::Put {
+                before,
+                cursor_after,
+            } => {
+                let result = register::put(ctx, *before, *cursor_after, times);
+                report_error(ctx, result);
+            }
+            Command::InsertRegister(name) => {
+                let result =
+                    register::insert_register(ctx, *name).map(|text| self.inserted.push_str(&text));
+                report_error(ctx, result);
+            }
+            Command::ListRegisters => register::list_registers(ctx),
+            Command::Undo => (0..times).for_each(|_| undo(ctx, mode)),
+            Command::Redo => (0..times).for_each(|_| redo(ctx, mode)),
+            Command::Earlier => earlier(ctx, mode, times),
+            Command::Later => later(ctx, mode, times),
+            Command::Save => return CommandHandlerResult::NotConsumed,
+            Command::ForceSave => return CommandHandlerResult::NotConsumed,
+            Command::SaveAll => return CommandHandlerResult::NotConsumed,
+            Command::Update => return CommandHandlerResult::NotConsumed,
+            Command::Rename(_) => return CommandHandlerResult::NotConsumed,
+            Command::SaveAs { .. } => return CommandHandlerResult::NotConsumed,
+            Command::Quit => return CommandHandlerResult::NotConsumed,
+            Command::ForceQuit => return CommandHandlerResult::NotConsumed,
+            Command::Reload => return CommandHandlerResult::NotConsumed,
+            Command::ForceReload => return CommandHandlerResult::NotConsumed,
+            Command::CheckTime => return CommandHandlerResult::NotConsumed,
+            Command::Edit(_) => return CommandHandlerResult::NotConsumed,
+            Command::NextBuffer => return CommandHandlerResult::NotConsumed,
+            Command::PrevBuffer => return CommandHandlerResult::NotConsumed,
+            Command::SwitchBuffer(_) => return CommandHandlerResult::NotConsumed,
+            Command::AlternateBuffer => return CommandHandlerResult::NotConsumed,
+            Command::DeleteBuffer { .. } => return CommandHandlerResult::NotConsumed,
+            Command::ListBuffers => return CommandHandlerResult::NotConsumed,
+            Command::AnswerPrompt { .. } => return CommandHandlerResult::NotConsumed,
+            Command::SetOption { .. } => return CommandHandlerResult::NotConsumed,
+            Command::Substitute { .. } => return CommandHandlerResult::NotConsumed,
+            Command::Search { pattern, direction } => {
+                let result = search(ctx, pattern, *direction, mode)
+                    .and_then(|_| repeat_search(ctx, false, mode, times - 1));
+                report_error(ctx, result);
+            }
+            Command::PreviewSearch { pattern, direction } => {
+                preview_search(ctx, pattern, *direction, mode)
+            }
+            Command::CancelSearch => cancel_search(ctx),
+            Command::ClearSearchHighlight => ctx.search.highlight = false,
+            Command::SearchNext => {
+                let result = repeat_search(ctx, false, mode, times);
+                report_error(ctx, result);
+            }
+            Command::SearchPrev => {
+                let result = repeat_search(ctx, true, mode, times);
+                report_error(ctx, result);
+            }
+            Command::SearchWordUnderCursor(direction) => {
+                let result = search_word_under_cursor(ctx, *direction, mode)
+                    .and_then(|_| repeat_search(ctx, false, mode, times - 1));
+                report_error(ctx, result);
+            }
+        }
+
+        CommandHandlerResult::Consumed
+    }
+}
+
+fn begin_change(ctx: &mut CommandContext<'_>) {
+    let view = ctx.views.get_active_view();
+    ctx.buffers
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .begin_change(&view.cursors);
+}
+
+fn commit_changes(ctx: &mut CommandContext<'_>) {
+    let view = ctx.views.get_active_view();
+    ctx.buffers
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .commit_changes(&view.cursors);
+}
+
+fn undo(ctx: &mut CommandContext<'_>, mode: VimMode) {
+    let view = ctx.views.get_mut_active_view();
+    let buffer = ctx
+        .buffers
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer");
+
+    if let Some(cursors) = buffer.undo() {
+        restore_cursors(view, buffer, cursors, mode);
+    }
+}
+
+fn redo(ctx: &mut CommandContext<'_>, mode: VimMode) {
+    let view = ctx.views.get_mut_active_view();
+    let buffer = ctx
+        .buffers
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer");
+
+    if let Some(cursors) = buffer.redo() {
+        restore_cursors(view, buffer, cursors, mode);
+    }
+}
+
+fn earlier(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
+    let view = ctx.views.get_mut_active_view();
+    let buffer = ctx
+        .buffers
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer");
+
+    if let Some(cursors) = buffer.earlier(count) {
+        restore_cursors(view, buffer, cursors, mode);
+    }
+}
+
+fn later(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
+    let view = ctx.views.get_mut_active_view();
+    let buffer = ctx
+        .buffers
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer");
+
+    if let Some(cursors) = buffer.later(count) {
+        restore_cursors(view, buffer, cursors, mode);
+    }
+}
+
+fn restore_cursors(view: &mut View, buffer: &Buffer, cursors: Vec<Cursor>, mode: VimMode) {
+    if cursors.is_empty() {
+        return;
+    }
+
+    view.cursors = cursors;
+
+    // the cursors were recorded against a different version of the content, so they have to be
+    // clamped back into bounds
+    let content = buffer.content();
+    for cursor in view.cursors.iter_mut() {
+        cursor.y = usize::min(cursor.y, content.len_lines().saturating_sub(1));
+
+        let last_char = content.line(cursor.y).chars().last().unwrap_or_default();
+        let has_newline = matches!(last_char, '\n');
+        let offset_from_eol = get_offset_from_eol(mode, has_newline);
+        let max_x = content.line_len(cursor.y).saturating_sub(offset_from_eol);
+
+        cursor.x = usize::min(cursor.x, max_x);
+        cursor.snap_to_grapheme(buffer);
+        cursor.update_virtual_x(buffer);
+    }
+}
+
+fn move_cursor_left(ctx: &mut CommandContext<'_>, count: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
-    cursor.move_left_by(1);
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+
+    cursor.move_left_by(buffer, count);
 }
 
-fn move_cursor_down(ctx: &mut CommandContext<'_>, mode: VimMode) {
+fn move_cursor_down(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
-    cursor.move_down_by(buffer, 1);
+    cursor.move_down_by(buffer, count);
     adjust_cursor_after_vertical_move(cursor, buffer, mode);
 }
 
-fn move_cursor_up(ctx: &mut CommandContext<'_>, mode: VimMode) {
+fn move_cursor_up(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer");
+
+    cursor.move_up_by(count);
+    adjust_cursor_after_vertical_move(cursor, buffer, mode);
+}
+
+fn move_cursor_display_down(ctx: &mut CommandContext<'_>, mode: VimMode) {
+    move_cursor_by_display_row(ctx, mode, true);
+}
+
+fn move_cursor_display_up(ctx: &mut CommandContext<'_>, mode: VimMode) {
+    move_cursor_by_display_row(ctx, mode, false);
+}
+
+/// Moves the cursor to the row of the view below or above it, keeping its place on screen. Rows
+/// are whole lines unless lines wrap.
+fn move_cursor_by_display_row(ctx: &mut CommandContext<'_>, mode: VimMode, down: bool) {
+    let view_id = ctx.views.get_active_view_id();
+    let width = ctx.views.get_layout_for_view(view_id).usable_rect.width as usize;
+    let view = ctx.views.get_mut_active_view();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
         .expect("view references non-existing buffer");
+    let content = buffer.content();
 
-    cursor.move_up_by(1);
+    let cursor = *view.cursors.first().unwrap();
+    let rows = view.display_rows(buffer, cursor.y, width);
+    let row_idx = find_display_row(&rows, cursor.x);
+    let last_line = content.len_lines().saturating_sub(1);
+
+    let (line_idx, row, is_last_row) = match down {
+        true if row_idx + 1 < rows.len() => {
+            (cursor.y, rows[row_idx + 1], row_idx + 2 == rows.len())
+        }
+        true if cursor.y < last_line => {
+            let next_rows = view.display_rows(buffer, cursor.y + 1, width);
+            (cursor.y + 1, next_rows[0], next_rows.len() == 1)
+        }
+        false if row_idx > 0 => (cursor.y, rows[row_idx - 1], false),
+        false if cursor.y > 0 => {
+            let prev_rows = view.display_rows(buffer, cursor.y - 1, width);
+            (cursor.y - 1, *prev_rows.last().unwrap(), true)
+        }
+        _ => return,
+    };
+
+    let cursor = view.cursors.first_mut().unwrap();
+    if cursor.virtual_x != usize::MAX {
+        let screen_x = rows[row_idx].screen_x(cursor.virtual_x);
+        cursor.virtual_x = row.line_column(screen_x);
+    }
+    cursor.y = line_idx;
     adjust_cursor_after_vertical_move(cursor, buffer, mode);
+
+    // the column can be past the end of a row that isn't the last of its line
+    if !is_last_row && cursor.x >= row.end {
+        let row_end = content.point_to_char(Point::new(row.end, line_idx));
+        cursor.x = content
+            .char_to_point(content.prev_grapheme_boundary(row_end))
+            .x;
+    }
 }
 
-fn move_cursor_right(ctx: &mut CommandContext<'_>, mode: VimMode) {
+fn move_cursor_right(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
     let last_char = buffer
@@ -99,15 +427,15 @@ fn move_cursor_right(ctx: &mut CommandContext<'_>, mode: VimMode) {
     let has_newline = matches!(last_char, '\n');
     let offset_from_eol = get_offset_from_eol(mode, has_newline);
 
-    cursor.move_right_by_with_offset(buffer, 1, offset_from_eol);
+    cursor.move_right_by_with_offset(buffer, count, offset_from_eol);
 }
 
-fn move_cursor_right_over_lines(ctx: &mut CommandContext<'_>, mode: VimMode) {
+fn move_cursor_right_over_lines(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
     let last_char = buffer
@@ -119,7 +447,7 @@ fn move_cursor_right_over_lines(ctx: &mut CommandContext<'_>, mode: VimMode) {
     let has_newline = matches!(last_char, '\n');
     let offset_from_eol = get_offset_from_eol(mode, has_newline);
 
-    cursor.move_right_by_with_offset(buffer, 1, offset_from_eol);
+    cursor.move_right_by_with_offset(buffer, count, offset_from_eol);
 }
 
 fn get_offset_from_eol(mode: VimMode, has_newline: bool) -> usize {
@@ -129,6 +457,7 @@ fn get_offset_from_eol(mode: VimMode, has_newline: bool) -> usize {
         VimMode::Insert => 1,
         VimMode::Visual => 0,
         VimMode::Command => 0,
+        VimMode::Search => 0,
     }
 }
 
@@ -138,14 +467,16 @@ fn move_cursor_to_line_start(ctx: &mut CommandContext<'_>) {
     cursor.move_to_line_start();
 }
 
-fn move_cursor_to_line_end(ctx: &mut CommandContext<'_>, mode: VimMode) {
+/// Moves to the end of the line, or of the one `count - 1` lines below.
+fn move_cursor_to_line_end(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
+    cursor.move_down_by(buffer, count - 1);
     let last_char = buffer
         .content()
         .line(cursor.y)
@@ -159,78 +490,81 @@ fn move_cursor_to_line_end(ctx: &mut CommandContext<'_>, mode: VimMode) {
     cursor.move_to_line_end_with_offset(buffer, offset_from_eol);
 }
 
-fn delete_whole_line(ctx: &mut CommandContext<'_>, mode: VimMode) {
-    let view = ctx.views.get_mut_active_view();
-    let cursor = view.cursors.first_mut().unwrap();
-    let buffer = ctx
-        .buffers
-        .get_mut(&view.buffer_id)
-        .expect("view references non-existing buffer");
-
-    let content = buffer.content_mut();
-
-    let len_lines = content.len_lines();
-    let is_last_line = cursor.y == len_lines.saturating_sub(1);
-    content.delete_whole_line(cursor.y);
-
-    // When deleting the last line of text, the cursor should move to the line above to not be
-    // out-of-bounds from the text buffer
-    if is_last_line {
-        move_cursor_up(ctx, mode);
+/// The last line of `content`, not counting the empty one after a final line break.
+fn last_text_line(content: &TextObject) -> usize {
+    let last_line = content.len_lines().saturating_sub(1);
+    match last_line > 0 && content.line_len(last_line) == 0 {
+        true => last_line - 1,
+        false => last_line,
     }
 }
 
-fn move_to_top(ctx: &mut CommandContext<'_>, mode: VimMode) {
+/// Moves to line `line_number`, counting from 1, or to the last line when there are fewer.
+fn move_to_line(ctx: &mut CommandContext<'_>, mode: VimMode, line_number: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
+    // the empty line after a final line break isn't a line of its own
+    let line_idx = line_number
+        .saturating_sub(1)
+        .min(last_text_line(buffer.content()));
+
     cursor.move_up_by(usize::MAX);
+    cursor.move_down_by(buffer, line_idx);
     adjust_cursor_after_vertical_move(cursor, buffer, mode);
 }
 
-fn move_to_bottom(ctx: &mut CommandContext<'_>, mode: VimMode) {
-    let view = ctx.views.get_mut_active_view();
-    let cursor = view.cursors.first_mut().unwrap();
-    let buffer = ctx
+/// `N%`, moves to the first non-blank of the line `percent` percent of the way into the buffer.
+fn move_to_percentage(ctx: &mut CommandContext<'_>, mode: VimMode, percent: usize) {
+    if percent > 100 {
+        return;
+    }
+
+    let view = ctx.views.get_active_view();
+    let content = ctx
         .buffers
-        .get_mut(&view.buffer_id)
-        .expect("view references non-existing buffer");
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
 
-    cursor.move_down_by(buffer, usize::MAX);
-    adjust_cursor_after_vertical_move(cursor, buffer, mode);
+    let lines = last_text_line(content) + 1;
+    move_to_line(ctx, mode, (percent * lines).div_ceil(100));
+    move_to_first_non_space(ctx);
 }
 
-fn page_up(ctx: &mut CommandContext<'_>, mode: VimMode) {
+/// Scrolls the cursor up by `count` lines, or by half the view.
+fn page_up(ctx: &mut CommandContext<'_>, mode: VimMode, count: Option<usize>) {
     let view_id = ctx.views.get_active_view_id();
     let layout = ctx.views.get_layout_for_view(view_id);
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
-    let half_page = layout.usable_rect.height / 2;
-    cursor.move_up_by(half_page as usize);
+    let half_page = layout.usable_rect.height as usize / 2;
+    cursor.move_up_by(count.unwrap_or(half_page));
     adjust_cursor_after_vertical_move(cursor, buffer, mode);
 }
 
-fn page_down(ctx: &mut CommandContext<'_>, mode: VimMode) {
+/// Scrolls the cursor down by `count` lines, or by half the view.
+fn page_down(ctx: &mut CommandContext<'_>, mode: VimMode, count: Option<usize>) {
     let view_id = ctx.views.get_active_view_id();
     let layout = ctx.views.get_layout_for_view(view_id);
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
-    let half_page = layout.usable_rect.height / 2;
-    cursor.move_down_by(buffer, half_page as usize);
+    let half_page = layout.usable_rect.height as usize / 2;
+    cursor.move_down_by(buffer, count.unwrap_or(half_page));
     adjust_cursor_after_vertical_move(cursor, buffer, mode);
 }
 
@@ -239,23 +573,27 @@ fn move_to_first_non_space(ctx: &mut CommandContext<'_>) {
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
+    // the line can be empty, with no character to move onto
     let position = buffer.content().find_first_non_space_character(cursor.y);
-    cursor.move_to(buffer, position.x, position.y);
+    cursor.move_to_with_offset(buffer, position.x, position.y, 0);
 }
 
-fn move_to_last_non_space(ctx: &mut CommandContext<'_>) {
+/// Moves to the last non-blank of the line, or of the one `count - 1` lines below.
+fn move_to_last_non_space(ctx: &mut CommandContext<'_>, count: usize) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
+    cursor.move_down_by(buffer, count - 1);
+    // the line can be empty, with no character to move onto
     let position = buffer.content().find_last_non_space_character(cursor.y);
-    cursor.move_to(buffer, position.x, position.y);
+    cursor.move_to_with_offset(buffer, position.x, position.y, 0);
 }
 
 fn move_to_next_paragraph(ctx: &mut CommandContext<'_>) {
@@ -263,7 +601,7 @@ fn move_to_next_paragraph(ctx: &mut CommandContext<'_>) {
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
     let position = buffer.content().find_next_paragraph(cursor.y);
@@ -275,66 +613,83 @@ fn move_to_prev_paragraph(ctx: &mut CommandContext<'_>) {
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
     let position = buffer.content().find_prev_paragraph(cursor.y);
     cursor.move_to(buffer, position.x, position.y);
 }
 
 fn delete_prev_char(ctx: &mut CommandContext<'_>, mode: VimMode) {
-    let view = ctx.views.get_mut_active_view();
-    let buffer_id = view.buffer_id;
-    let cursor = view.cursors.first_mut().unwrap();
-    let position = Point::new(cursor.x, cursor.y);
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+
+    let char_idx = content.point_to_char(Point::new(cursor.x, cursor.y));
     let is_cursor_on_line_start = cursor.x == 0;
 
-    match mode {
-        VimMode::Insert if !is_cursor_on_line_start => {
-            ctx.buffers
-                .get_mut(&buffer_id)
-                .expect("view references non-existing buffer")
-                .content_mut()
-                .delete_prev_char(Point::new(cursor.x, cursor.y));
-            move_cursor_left(ctx);
-        }
-        VimMode::Insert => {
-            move_cursor_up(ctx, mode);
-            move_cursor_to_line_end(ctx, mode);
-            move_cursor_left(ctx);
-            ctx.buffers
-                .get_mut(&buffer_id)
-                .expect("view references non-existing buffer")
-                .content_mut()
-                .delete_prev_char(position);
-        }
-        VimMode::Normal if !is_cursor_on_line_start => {
-            ctx.buffers
-                .get_mut(&buffer_id)
-                .expect("view references non-existing buffer")
-                .content_mut()
-                .delete_prev_char(Point::new(cursor.x, cursor.y));
-            move_cursor_left(ctx);
-        }
-        VimMode::Normal => (),
-        VimMode::Visual => (),
-        VimMode::Command => (),
+    // in insert mode, deleting at the start of a line joins it with the line above
+    let can_delete = match mode {
+        VimMode::Insert => char_idx > 0,
+        VimMode::Normal => !is_cursor_on_line_start,
+        VimMode::Visual => false,
+        VimMode::Command => false,
+        VimMode::Search => false,
+    };
+
+    if !can_delete {
+        return;
     }
+
+    // with `softtabstop`, spaces are deleted back to the previous soft tab stop at once
+    let soft_tab_width = ctx
+        .options
+        .soft_tab_width()
+        .filter(|_| mode == VimMode::Insert);
+    let spaces = count_before_cursor(content, cursor, |ch| ch == ' ');
+
+    let prev_char_idx = match soft_tab_width {
+        Some(width) if spaces > 0 => {
+            let buffer = ctx
+                .buffers
+                .get(view.buffer_id)
+                .expect("view references non-existing buffer");
+            let column = buffer.display_column(Point::new(cursor.x, cursor.y));
+            let prev_stop = (column - 1) / width * width;
+            char_idx - spaces.min(column - prev_stop)
+        }
+        _ => content.prev_grapheme_boundary(char_idx),
+    };
+
+    apply_transaction(ctx, Transaction::new().delete(prev_char_idx..char_idx));
 }
 
-fn delete_curr_char(ctx: &mut CommandContext<'_>, mode: VimMode) {
-    let view = ctx.views.get_mut_active_view();
-    let cursor = view.cursors.first_mut().unwrap();
-    let buffer = ctx
+/// Deletes `count` grapheme clusters from the cursor on. In normal mode they never go past the end
+/// of the line.
+fn delete_curr_char(ctx: &mut CommandContext<'_>, mode: VimMode, count: usize) {
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let content = ctx
         .buffers
-        .get_mut(&view.buffer_id)
-        .expect("view references non-existing buffer");
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
 
-    let content = buffer.content_mut();
     let line_len = content.line_len(cursor.y);
     let last_char = content.line(cursor.y).chars().last().unwrap_or_default();
     let has_newline = matches!(last_char, '\n');
     let is_empty_line = line_len == 1 && has_newline;
-    let offset_from_eol = get_offset_from_eol(mode, has_newline);
+
+    let char_idx = content.point_to_char(Point::new(cursor.x, cursor.y));
+    if char_idx >= content.len_chars() {
+        return;
+    }
+
+    // the whole grapheme cluster under the cursor is deleted, not only its first char
+    let next_char_idx = content.next_grapheme_boundary(char_idx);
 
     match mode {
         VimMode::Normal => {
@@ -342,58 +697,163 @@ fn delete_curr_char(ctx: &mut CommandContext<'_>, mode: VimMode) {
                 return;
             }
 
-            let is_on_last_char = cursor.x == line_len.saturating_sub(offset_from_eol);
-            content.delete_curr_char(Point::new(cursor.x, cursor.y));
+            let line_end =
+                content.point_to_char(Point::new(line_len, cursor.y)) - usize::from(has_newline);
+            let mut end = next_char_idx;
+            for _ in 1..count {
+                if end >= line_end {
+                    break;
+                }
+                end = content.next_grapheme_boundary(end);
+            }
+
+            apply_transaction(ctx, Transaction::new().delete(char_idx..end));
 
-            if is_on_last_char {
-                move_cursor_left(ctx);
+            if end >= line_end {
+                move_cursor_left(ctx, 1);
             }
         }
-        VimMode::Insert => content.delete_curr_char(Point::new(cursor.x, cursor.y)),
+        VimMode::Insert => {
+            apply_transaction(ctx, Transaction::new().delete(char_idx..next_char_idx))
+        }
         VimMode::Visual => {}
         VimMode::Command => {}
+        VimMode::Search => {}
     }
 }
 
-fn insert_character(ctx: &mut CommandContext<'_>, mode: VimMode, ch: char) {
-    let view = ctx.views.get_mut_active_view();
-    let cursor = view.cursors.first_mut().unwrap();
-    let position = Point::new(cursor.x, cursor.y);
+fn insert_character(ctx: &mut CommandContext<'_>, ch: char) {
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+
+    let char_idx = content.point_to_char(Point::new(cursor.x, cursor.y));
+    apply_transaction(ctx, Transaction::new().insert(char_idx, ch));
+}
+
+/// Inserts a tab character, or moves to the next soft tab stop when `softtabstop` is set, using
+/// spaces when `expandtab` is set and as many tabs as fit otherwise.
+fn insert_tab(ctx: &mut CommandContext<'_>) {
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get(view.buffer_id)
         .expect("view references non-existing buffer");
+    let content = buffer.content();
+    let tabstop = buffer.tabstop();
+
+    let char_idx = content.point_to_char(Point::new(cursor.x, cursor.y));
+    let column = buffer.display_column(Point::new(cursor.x, cursor.y));
+    let width = ctx.options.soft_tab_width().unwrap_or(tabstop);
+    let target = (column / width + 1) * width;
+
+    if ctx.options.expandtab {
+        let spaces = " ".repeat(target - column);
+        apply_transaction(ctx, Transaction::new().insert(char_idx, spaces));
+        return;
+    }
+
+    if ctx.options.soft_tab_width().is_none() {
+        apply_transaction(ctx, Transaction::new().insert(char_idx, '\t'));
+        return;
+    }
 
-    buffer.content_mut().insert_char_at(position, ch);
-    move_cursor_right(ctx, mode);
+    // the blanks before the cursor are redone with as many tabs as fit, like vim does
+    let blanks = count_before_cursor(content, cursor, |ch| ch == ' ' || ch == '\t');
+    let start_column = buffer.display_column(Point::new(cursor.x - blanks, cursor.y));
+
+    let mut whitespace = String::new();
+    let mut column = start_column;
+    while (column / tabstop + 1) * tabstop <= target {
+        whitespace.push('\t');
+        column = (column / tabstop + 1) * tabstop;
+    }
+    whitespace.push_str(&" ".repeat(target - column));
+
+    apply_transaction(
+        ctx,
+        Transaction::new().replace(char_idx - blanks..char_idx, whitespace),
+    );
 }
 
-fn move_to_next_word(ctx: &mut CommandContext<'_>, mode: VimMode) {
+/// How many chars right before the cursor, on its line, match `predicate`.
+fn count_before_cursor(
+    content: &TextObject,
+    cursor: &Cursor,
+    predicate: fn(char) -> bool,
+) -> usize {
+    let line = content.line(cursor.y);
+    (0..cursor.x)
+        .rev()
+        .take_while(|&x| predicate(line.char(x)))
+        .count()
+}
+
+/// Moves the cursor with one of the word motions of [`TextObject`], over `count` words or
+/// `WORD`s.
+fn move_by_word(
+    ctx: &mut CommandContext<'_>,
+    mode: VimMode,
+    motion: fn(&TextObject, Point<usize>, bool) -> Point<usize>,
+    big_word: bool,
+    count: usize,
+) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
-    let position = Point::new(cursor.x, cursor.y);
+    let mut position = Point::new(cursor.x, cursor.y);
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
     let content = buffer.content();
-    let position = content.find_next_word_boundary(position);
+    for _ in 0..count {
+        position = motion(content, position, big_word);
+    }
 
     let last_char = content.line(position.y).chars().last().unwrap_or_default();
-    // TODO: this should also work when EOL is \r or \r\n
     let has_newline = matches!(last_char, '\n');
     let offset_from_eol = get_offset_from_eol(mode, has_newline);
 
     cursor.move_to_with_offset(buffer, position.x, position.y, offset_from_eol);
 }
 
+/// Moves the cursor to the `count`th match of a character on its line, staying where it is when
+/// there are fewer. A repeated `t` or `T` skips the character right next to the cursor, as it
+/// would otherwise never move.
+fn find_char(ctx: &mut CommandContext<'_>, search: CharSearch, repeat: bool, count: usize) {
+    let view = ctx.views.get_mut_active_view();
+    let cursor = view.cursors.first_mut().unwrap();
+    let position = Point::new(cursor.x, cursor.y);
+    let buffer = ctx
+        .buffers
+        .get_mut(view.buffer_id)
+        .expect("view references non-existing buffer");
+
+    let content = buffer.content();
+    let target = match content.find_char(position, search, count) {
+        Some(target) if repeat && search.till && target == position => {
+            content.find_char(position, search, count + 1)
+        }
+        target => target,
+    };
+
+    if let Some(target) = target {
+        cursor.move_to(buffer, target.x, target.y);
+    }
+}
+
 fn move_to_matching_pair(ctx: &mut CommandContext<'_>, mode: VimMode) {
     let view = ctx.views.get_mut_active_view();
     let cursor = view.cursors.first_mut().unwrap();
     let buffer = ctx
         .buffers
-        .get_mut(&view.buffer_id)
+        .get_mut(view.buffer_id)
         .expect("view references non-existing buffer");
 
     let matching_pair = buffer
@@ -404,6 +864,226 @@ fn move_to_matching_pair(ctx: &mut CommandContext<'_>, mode: VimMode) {
     adjust_cursor_after_vertical_move(cursor, buffer, mode);
 }
 
+fn report_error(ctx: &mut CommandContext<'_>, result: Result<()>) {
+    if let Err(e) = result {
+        *ctx.message = Some(Message::error(e.to_string()));
+    }
+}
+
+fn search(
+    ctx: &mut CommandContext<'_>,
+    pattern: &str,
+    direction: SearchDirection,
+    mode: VimMode,
+) -> Result<()> {
+    // the search starts over from where the cursor was before the pattern was previewed
+    cancel_search(ctx);
+
+    // an empty pattern searches for the last one again
+    if !pattern.is_empty() {
+        ctx.search.pattern = Some(pattern.to_string());
+        ctx.search.smartcase = true;
+    }
+    ctx.search.direction = direction;
+
+    let pattern = ctx.search.compile(ctx.options)?;
+    ctx.search.highlight = true;
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let char_idx = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content()
+        .point_to_char(Point::new(cursor.x, cursor.y));
+
+    jump_to_match(ctx, &pattern, char_idx, direction, mode)
+}
+
+/// Jumps to the first match of the pattern being typed, as if searching from where the cursor was
+/// when the search started. Patterns that don't match or don't compile yet leave the cursor there.
+fn preview_search(
+    ctx: &mut CommandContext<'_>,
+    pattern: &str,
+    direction: SearchDirection,
+    mode: VimMode,
+) {
+    let view = ctx.views.get_mut_active_view();
+    let preview = ctx.search.preview.get_or_insert_with(|| SearchPreview {
+        pattern: String::new(),
+        origin: *view.cursors.first().unwrap(),
+        scroll_offset: view.scroll_offset,
+        skipped_rows: view.skipped_rows,
+    });
+    preview.pattern = pattern.to_string();
+
+    let origin = preview.origin;
+    *view.cursors.first_mut().unwrap() = origin;
+    view.scroll_offset = preview.scroll_offset;
+    view.skipped_rows = preview.skipped_rows;
+
+    if pattern.is_empty() {
+        return;
+    }
+
+    let ignore_case = ctx.options.search_ignores_case(pattern);
+    let Ok(pattern) = SearchPattern::new(pattern, ignore_case) else {
+        return;
+    };
+    let char_idx = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content()
+        .point_to_char(Point::new(origin.x, origin.y));
+
+    // the message of the jump would replace the pattern being typed in the dock
+    let message = ctx.message.take();
+    let _ = jump_to_match(ctx, &pattern, char_idx, direction, mode);
+    *ctx.message = message;
+}
+
+/// Moves the cursor and the view back to where they were before the pattern was previewed.
+fn cancel_search(ctx: &mut CommandContext<'_>) {
+    let Some(preview) = ctx.search.preview.take() else {
+        return;
+    };
+
+    let view = ctx.views.get_mut_active_view();
+    *view.cursors.first_mut().unwrap() = preview.origin;
+    view.scroll_offset = preview.scroll_offset;
+    view.skipped_rows = preview.skipped_rows;
+}
+
+fn search_next(ctx: &mut CommandContext<'_>, reverse: bool, mode: VimMode) -> Result<()> {
+    let direction = match (ctx.search.direction, reverse) {
+        (direction, false) => direction,
+        (SearchDirection::Forward, true) => SearchDirection::Backward,
+        (SearchDirection::Backward, true) => SearchDirection::Forward,
+    };
+
+    let pattern = ctx.search.compile(ctx.options)?;
+    ctx.search.highlight = true;
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let char_idx = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content()
+        .point_to_char(Point::new(cursor.x, cursor.y));
+
+    jump_to_match(ctx, &pattern, char_idx, direction, mode)
+}
+
+/// Repeats the last search `count` times, the other way around when `reverse` is set.
+fn repeat_search(
+    ctx: &mut CommandContext<'_>,
+    reverse: bool,
+    mode: VimMode,
+    count: usize,
+) -> Result<()> {
+    for _ in 0..count {
+        search_next(ctx, reverse, mode)?;
+    }
+
+    Ok(())
+}
+
+/// Searches for the keyword under the cursor, or the first one after it on the same line.
+fn search_word_under_cursor(
+    ctx: &mut CommandContext<'_>,
+    direction: SearchDirection,
+    mode: VimMode,
+) -> Result<()> {
+    let is_keyword = |ch: char| ch.is_alphanumeric() || ch == '_';
+
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+
+    let line: Vec<char> = content.line(cursor.y).chars().collect();
+    let cursor_x = cursor.x.min(line.len());
+    let mut start = match line[cursor_x..].iter().position(|&ch| is_keyword(ch)) {
+        Some(offset) => cursor_x + offset,
+        None => return Err(GlyphError::NoStringUnderCursor),
+    };
+    while start > 0 && is_keyword(line[start - 1]) {
+        start -= 1;
+    }
+    let end = start
+        + line[start..]
+            .iter()
+            .take_while(|&&ch| is_keyword(ch))
+            .count();
+
+    let word: String = line[start..end].iter().collect();
+    let char_idx = content.point_to_char(Point::new(start, cursor.y));
+
+    ctx.search.set_word(&word, direction);
+    let pattern = ctx.search.compile(ctx.options)?;
+    ctx.search.highlight = true;
+
+    jump_to_match(ctx, &pattern, char_idx, direction, mode)
+}
+
+/// Moves the cursor to the next match after `char_idx`, or the previous one before it, wrapping
+/// around the ends of the buffer.
+fn jump_to_match(
+    ctx: &mut CommandContext<'_>,
+    pattern: &SearchPattern,
+    char_idx: usize,
+    direction: SearchDirection,
+    mode: VimMode,
+) -> Result<()> {
+    let view = ctx.views.get_mut_active_view();
+    let cursor = view.cursors.first_mut().unwrap();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+    let content = buffer.content();
+
+    let (from, prefix) = match direction {
+        SearchDirection::Forward => (char_idx + 1, '/'),
+        SearchDirection::Backward => (char_idx, '?'),
+    };
+
+    let (range, message) = match content.search(pattern, from, direction) {
+        Some(range) => (
+            range,
+            Message::info(format!("{prefix}{}", pattern.as_str())),
+        ),
+        None => {
+            let (restart, message) = match direction {
+                SearchDirection::Forward => (0, "search hit BOTTOM, continuing at TOP"),
+                SearchDirection::Backward => {
+                    (content.len_chars(), "search hit TOP, continuing at BOTTOM")
+                }
+            };
+
+            let range = content
+                .search(pattern, restart, direction)
+                .ok_or_else(|| GlyphError::PatternNotFound(pattern.as_str().to_string()))?;
+            (range, Message::error(message))
+        }
+    };
+
+    let position = content.char_to_point(range.start);
+    let last_char = content.line(position.y).chars().last().unwrap_or_default();
+    let has_newline = matches!(last_char, '\n');
+    let offset_from_eol = get_offset_from_eol(mode, has_newline);
+
+    cursor.move_to_with_offset(buffer, position.x, position.y, offset_from_eol);
+    *ctx.message = Some(message);
+
+    Ok(())
+}
+
 fn adjust_cursor_after_vertical_move(cursor: &mut Cursor, buffer: &Buffer, mode: VimMode) {
     let content = buffer.content();
     let line_len = content.line_len(cursor.y);
@@ -412,41 +1092,97 @@ fn adjust_cursor_after_vertical_move(cursor: &mut Cursor, buffer: &Buffer, mode:
 
     let offset_from_eol = get_offset_from_eol(mode, has_newline);
     let max_x = line_len.saturating_sub(offset_from_eol);
-    if cursor.x >= max_x && cursor.x >= cursor.virtual_x {
-        cursor.virtual_x = cursor.x;
-        cursor.x = max_x;
-        return;
-    }
 
-    if cursor.x < cursor.virtual_x {
-        cursor.x = usize::min(cursor.virtual_x, max_x);
-    }
+    // `virtual_x` is a display column, so the cursor stays in the same place on screen even when
+    // the lines have tabs or wide characters at different places
+    let x = content.column_to_x(cursor.y, cursor.virtual_x, buffer.tabstop());
+    cursor.x = usize::min(x, max_x);
+    cursor.snap_to_grapheme(buffer);
 }
 
-fn scroll_view_to_cursor(ctx: &mut CommandContext<'_>) {
-    let active_view_id = ctx.views.get_active_view_id();
-    let layout = ctx.views.get_layout_for_view(active_view_id);
-    let view = ctx.views.get_mut_active_view();
-    let cursor = view.cursors.first_mut().unwrap();
+#[cfg(test)]
+mod tests {
+    use crate::testing::TestEditor;
+
+    #[test]
+    fn test_move_to_line() {
+        let mut editor = TestEditor::new("one\n  two\nthree\nfour\n");
+
+        editor.keys("G");
+        assert_eq!(editor.cursor(), (0, 3));
+
+        editor.keys("2G");
+        assert_eq!(editor.cursor(), (0, 1));
 
-    let view_height = layout.usable_rect.height as usize;
-    let view_width = layout.usable_rect.width as usize;
+        // past the end it stops on the last line, not the empty one after the final line break
+        editor.keys("gg10G");
+        assert_eq!(editor.cursor(), (0, 3));
 
-    if cursor.y.saturating_sub(view.scroll_offset.y) > view_height - 1 {
-        view.scroll_offset.y = cursor.y - (view_height - 1)
+        editor.keys("50%");
+        assert_eq!(editor.cursor(), (2, 1));
+
+        editor.keys("100%");
+        assert_eq!(editor.cursor(), (0, 3));
     }
 
-    if cursor.y.saturating_sub(view.scroll_offset.y) == 0 {
-        let vertical_offset = view.scroll_offset.y.saturating_sub(cursor.y);
-        view.scroll_offset.y = view.scroll_offset.y.saturating_sub(vertical_offset);
+    #[test]
+    fn test_move_by_display_row() {
+        let long_line = "a".repeat(100);
+        let mut editor = TestEditor::new(&format!("{long_line}\nb\n{long_line}\n"));
+        editor.views.get_mut_active_view().options.wrap = true;
+        editor.keys("5l");
+
+        editor.keys("gj");
+        assert_eq!(editor.cursor(), (85, 0));
+
+        editor.keys("gj");
+        assert_eq!(editor.cursor(), (0, 1));
+
+        // the screen column is kept across the short line in between
+        editor.keys("gj");
+        assert_eq!(editor.cursor(), (5, 2));
+
+        editor.keys("gkgk");
+        assert_eq!(editor.cursor(), (85, 0));
+
+        editor.keys("3gk");
+        assert_eq!(editor.cursor(), (5, 0));
+
+        // without wrapping, rows are whole lines
+        editor.views.get_mut_active_view().options.wrap = false;
+        editor.keys("gj");
+        assert_eq!(editor.cursor(), (0, 1));
     }
 
-    if cursor.x.saturating_sub(view.scroll_offset.x) == 0 {
-        let horizontal_offset = view.scroll_offset.x.saturating_sub(cursor.x);
-        view.scroll_offset.x = view.scroll_offset.x.saturating_sub(horizontal_offset);
+    #[test]
+    fn test_repeat_char_search() {
+        let find = |keys| {
+            let mut editor = TestEditor::new("abxcxdxe\n");
+            editor.keys(keys);
+            editor.cursor()
+        };
+
+        assert_eq!(find("fx"), (2, 0));
+        assert_eq!(find("fx;"), (4, 0));
+        assert_eq!(find("fx;,"), (2, 0));
+        assert_eq!(find("fx2;"), (6, 0));
+
+        // a repeated `t` doesn't stay stuck right before the match it stopped at
+        assert_eq!(find("tx"), (1, 0));
+        assert_eq!(find("tx;"), (3, 0));
+        assert_eq!(find("tx;,"), (3, 0));
+        assert_eq!(find("$Tx;"), (5, 0));
     }
 
-    if cursor.x.saturating_sub(view.scroll_offset.x) > view_width - 1 {
-        view.scroll_offset.x = cursor.x - (view_width - 1);
+    #[test]
+    fn test_delete_till_char() {
+        let mut editor = TestEditor::new("foo(bar) baz\n");
+        editor.keys("fbdt)");
+        assert_eq!(editor.text(), "foo() baz\n");
+        assert_eq!(editor.cursor(), (4, 0));
+
+        // the search is remembered for `;` like any other
+        editor.keys("0;");
+        assert_eq!(editor.cursor(), (3, 0));
     }
 }
@@ -0,0 +1,921 @@
+use std::ops::Range;
+
+use glyph_core::command_handler::{CommandContext, apply_transaction};
+use glyph_core::error::GlyphError;
+use glyph_core::geometry::Point;
+use glyph_core::key_mapper::{Command, MotionType, Operator, VimMode};
+use glyph_core::message::Message;
+use glyph_core::register::Register;
+use glyph_core::text_object::{
+    CharSearch, SearchDirection, TextObject, Transaction, line_graphemes,
+};
+
+use super::{
+    VimBufferCommandHandler, cancel_search, get_offset_from_eol, last_text_line,
+    move_to_first_non_space, report_error,
+};
+
+/// How the text between the two ends of a motion is covered, see `:help exclusive`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MotionKind {
+    /// Up to the character at the end, without it.
+    Exclusive,
+    /// Up to the character at the end, with it.
+    Inclusive,
+    /// Every line from the start to the end.
+    Linewise,
+}
+
+/// Where a motion or text object starts and ends, before it's turned into an [`OperatorRange`].
+#[derive(Debug, Clone, Copy)]
+struct MotionSpan {
+    from: Point<usize>,
+    to: Point<usize>,
+    kind: MotionKind,
+    /// Whether an exclusive end at the start of a line moves back to the line above, which vim
+    /// does for motions but not for text objects.
+    adjusts_end: bool,
+}
+
+/// The text an operator applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OperatorRange {
+    Chars(Range<usize>),
+    /// Lines `top` to `bottom`, both included.
+    Lines {
+        top: usize,
+        bottom: usize,
+    },
+    /// The display columns `columns` of lines `top` to `bottom`.
+    Block {
+        top: usize,
+        bottom: usize,
+        columns: Range<usize>,
+    },
+}
+
+impl OperatorRange {
+    /// The first and last line the range touches.
+    fn lines(&self, content: &TextObject) -> (usize, usize) {
+        match self {
+            Self::Chars(range) => {
+                let last = range.end.saturating_sub(1).max(range.start);
+                (
+                    content.char_to_point(range.start).y,
+                    content.char_to_point(last).y,
+                )
+            }
+            Self::Lines { top, bottom } => (*top, *bottom),
+            Self::Block { top, bottom, .. } => (*top, *bottom),
+        }
+    }
+}
+
+impl VimBufferCommandHandler {
+    /// Applies `operator` to the text from the cursor to where `motion` takes it, or to the text
+    /// object `motion` selects. Nothing happens when the motion fails, like `dfx` on a line
+    /// without an `x`.
+    pub(super) fn operate(
+        &mut self,
+        ctx: &mut CommandContext<'_>,
+        operator: Operator,
+        motion: &Command,
+        force: Option<MotionType>,
+        mode: VimMode,
+        count: Option<usize>,
+    ) {
+        // the search of `d/foo` starts from where the cursor was before the pattern was previewed
+        cancel_search(ctx);
+
+        let cursor = *ctx.views.get_active_view().cursors.first().unwrap();
+        let start = Point::new(cursor.x, cursor.y);
+        let span = self.resolve_motion(ctx, operator, motion, start, count);
+
+        // the motion moved the cursor, the operator decides where it ends up
+        *ctx.views.get_mut_active_view().cursors.first_mut().unwrap() = cursor;
+
+        let Some(span) = span else {
+            return;
+        };
+
+        let view = ctx.views.get_active_view();
+        let buffer = ctx
+            .buffers
+            .get(view.buffer_id)
+            .expect("view references non-existing buffer");
+        let range = to_operator_range(buffer.content(), span, force, buffer.tabstop());
+        let register = register_content(buffer.content(), &range, buffer.tabstop());
+
+        let name = ctx.resolved_keymap.register;
+        let written = match operator {
+            Operator::Delete | Operator::Change => ctx.registers.delete(name, register),
+            Operator::Yank => ctx.registers.yank(name, register),
+            Operator::Indent | Operator::Outdent | Operator::Reindent => Ok(()),
+        };
+        // the text is in the registers even when it couldn't reach the system clipboard, so the
+        // operator still applies and the failure is only reported
+        if let Err(e) = &written
+            && !matches!(e, GlyphError::ClipboardFailed { .. })
+        {
+            report_error(ctx, written);
+            return;
+        }
+
+        match operator {
+            Operator::Delete => delete(ctx, range, mode),
+            Operator::Change => change(ctx, range),
+            Operator::Yank => yank(ctx, range, start),
+            Operator::Indent => shift(ctx, range, true),
+            Operator::Outdent => shift(ctx, range, false),
+            Operator::Reindent => reindent(ctx, range),
+        }
+        report_error(ctx, written);
+    }
+
+    fn resolve_motion(
+        &mut self,
+        ctx: &mut CommandContext<'_>,
+        operator: Operator,
+        motion: &Command,
+        start: Point<usize>,
+        count: Option<usize>,
+    ) -> Option<MotionSpan> {
+        let times = count.unwrap_or(1);
+        let view = ctx.views.get_active_view();
+        let content = ctx
+            .buffers
+            .get(view.buffer_id)
+            .expect("view references non-existing buffer")
+            .content();
+
+        let span = |to, kind| MotionSpan {
+            from: start,
+            to,
+            kind,
+            adjusts_end: true,
+        };
+
+        match motion {
+            Command::SelectTextObject { kind, scope } => {
+                let range = content.select_text_object(*kind, *scope, start, times)?;
+                let (to, motion_kind) = match kind.is_linewise() {
+                    true => (
+                        content.char_to_point(range.end.saturating_sub(1)),
+                        MotionKind::Linewise,
+                    ),
+                    false => (content.char_to_point(range.end), MotionKind::Exclusive),
+                };
+
+                Some(MotionSpan {
+                    from: content.char_to_point(range.start),
+                    to,
+                    kind: motion_kind,
+                    adjusts_end: false,
+                })
+            }
+            // `dd`, `cc` and the like, from the cursor line down
+            Command::SelectLines => {
+                let bottom = start
+                    .y
+                    .saturating_add(times - 1)
+                    .min(last_text_line(content))
+                    .max(start.y);
+                Some(span(Point::new(0, bottom), MotionKind::Linewise))
+            }
+            Command::MoveToNextWord | Command::MoveToNextBigWord => {
+                let big_word = matches!(motion, Command::MoveToNextBigWord);
+                let on_blank = content
+                    .line(start.y)
+                    .get_char(start.x)
+                    .is_none_or(char::is_whitespace);
+
+                // `cw` stops at the end of the word like `ce`, unless it starts on a blank
+                match operator == Operator::Change && !on_blank {
+                    true => Some(span(
+                        content.find_change_word_end(start, big_word, times),
+                        MotionKind::Inclusive,
+                    )),
+                    false => Some(span(
+                        content.find_word_operator_end(start, big_word, times),
+                        MotionKind::Exclusive,
+                    )),
+                }
+            }
+            // unlike the cursor, the end of `dl` can be right after the last character of a line
+            Command::MoveCursorRight | Command::MoveCursorRightOverLines => {
+                let line_end = line_content_end(content, start.y);
+                let mut end = content.point_to_char(start);
+                for _ in 0..times {
+                    if end >= line_end {
+                        break;
+                    }
+                    end = content.next_grapheme_boundary(end);
+                }
+
+                let to = content.char_to_point(end);
+                (to != start).then(|| span(to, MotionKind::Exclusive))
+            }
+            motion => {
+                self.handle_command(ctx, motion, VimMode::Normal, count);
+
+                let cursor = ctx.views.get_active_view().cursors.first().unwrap();
+                let to = Point::new(cursor.x, cursor.y);
+                // these succeed where the cursor already is, like `d$` on the last character
+                let always_succeeds = matches!(
+                    motion,
+                    Command::MoveCursorLineStart
+                        | Command::MoveCursorLineEnd
+                        | Command::MoveToFirstNonSpace
+                        | Command::MoveToLastNonSpace
+                        | Command::MoveToTop
+                        | Command::MoveToBottom
+                );
+
+                (to != start || always_succeeds).then(|| span(to, self.motion_kind(motion, count)))
+            }
+        }
+    }
+
+    fn motion_kind(&self, motion: &Command, count: Option<usize>) -> MotionKind {
+        let forward = |search: CharSearch| search.direction == SearchDirection::Forward;
+
+        match motion {
+            Command::MoveCursorDown
+            | Command::MoveCursorUp
+            | Command::MoveToTop
+            | Command::MoveToBottom
+            | Command::PageUp
+            | Command::PageDown => MotionKind::Linewise,
+            // `N%` goes to a line rather than to the matching pair
+            Command::MoveToMatchingPair if count.is_some() => MotionKind::Linewise,
+            Command::MoveCursorLineEnd
+            | Command::MoveToLastNonSpace
+            | Command::MoveToMatchingPair
+            | Command::MoveToWordEnd
+            | Command::MoveToBigWordEnd
+            | Command::MoveToPrevWordEnd
+            | Command::MoveToPrevBigWordEnd => MotionKind::Inclusive,
+            Command::FindChar(search) if forward(*search) => MotionKind::Inclusive,
+            Command::RepeatCharSearch if self.last_char_search.is_some_and(forward) => {
+                MotionKind::Inclusive
+            }
+            Command::RepeatCharSearchReversed
+                if self.last_char_search.is_some_and(|search| !forward(search)) =>
+            {
+                MotionKind::Inclusive
+            }
+            _ => MotionKind::Exclusive,
+        }
+    }
+}
+
+/// Turns the ends of a motion into the text it covers, forced to another kind by `v`, `V` or
+/// `<c-v>` typed after the operator.
+fn to_operator_range(
+    content: &TextObject,
+    span: MotionSpan,
+    force: Option<MotionType>,
+    tabstop: usize,
+) -> OperatorRange {
+    let (from, to) = match (span.from.y, span.from.x) <= (span.to.y, span.to.x) {
+        true => (span.from, span.to),
+        false => (span.to, span.from),
+    };
+
+    // `v` flips between exclusive and inclusive, and makes a linewise motion exclusive
+    let kind = match (force, span.kind) {
+        (None, kind) => kind,
+        (Some(MotionType::Charwise), MotionKind::Exclusive) => MotionKind::Inclusive,
+        (Some(MotionType::Charwise), _) => MotionKind::Exclusive,
+        (Some(MotionType::Linewise), _) => MotionKind::Linewise,
+        (Some(MotionType::Blockwise), _) => {
+            let from_columns = grapheme_columns(content, from, tabstop);
+            let to_columns = grapheme_columns(content, to, tabstop);
+            return OperatorRange::Block {
+                top: from.y,
+                bottom: to.y,
+                columns: from_columns.start.min(to_columns.start)
+                    ..from_columns.end.max(to_columns.end),
+            };
+        }
+    };
+
+    // an exclusive motion ending at the start of a line stops at the end of the line above, and
+    // covers whole lines when it also starts before the first non-blank, see `:help exclusive`
+    if kind == MotionKind::Exclusive
+        && force.is_none()
+        && span.adjusts_end
+        && to.x == 0
+        && to.y > from.y
+    {
+        if from.x <= content.find_first_non_space_character(from.y).x {
+            return OperatorRange::Lines {
+                top: from.y,
+                bottom: to.y - 1,
+            };
+        }
+
+        return OperatorRange::Chars(
+            content.point_to_char(from)..line_content_end(content, to.y - 1),
+        );
+    }
+
+    let start = content.point_to_char(from);
+    let end = content.point_to_char(to);
+    match kind {
+        MotionKind::Exclusive => OperatorRange::Chars(start..end),
+        // the end never takes the line break along
+        MotionKind::Inclusive => {
+            let end = content
+                .next_grapheme_boundary(end)
+                .min(line_content_end(content, to.y))
+                .max(end);
+            OperatorRange::Chars(start..end)
+        }
+        MotionKind::Linewise => {
+            let bottom = to.y.min(last_text_line(content));
+            OperatorRange::Lines {
+                top: from.y.min(bottom),
+                bottom,
+            }
+        }
+    }
+}
+
+/// The text of the range, as it's stored in a register.
+fn register_content(content: &TextObject, range: &OperatorRange, tabstop: usize) -> Register {
+    match range {
+        OperatorRange::Chars(range) => Register::new(
+            content.slice(range.clone()).to_string(),
+            MotionType::Charwise,
+        ),
+        OperatorRange::Lines { top, bottom } => {
+            let start = content.point_to_char(Point::new(0, *top));
+            let end = content.point_to_char(Point::new(0, *bottom)) + content.line_len(*bottom);
+            let mut text = content.slice(start..end).to_string();
+            // the last line has no line break of its own
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            Register::new(text, MotionType::Linewise)
+        }
+        OperatorRange::Block {
+            top,
+            bottom,
+            columns,
+        } => {
+            let rows = block_ranges(content, *top, *bottom, columns, tabstop)
+                .into_iter()
+                .map(|range| content.slice(range).to_string())
+                .collect::<Vec<_>>();
+            Register::new(rows.join("\n"), MotionType::Blockwise)
+        }
+    }
+}
+
+/// The char index of the end of line `line_idx`, before its line break.
+pub(super) fn line_content_end(content: &TextObject, line_idx: usize) -> usize {
+    let has_newline = content.line(line_idx).chars().last() == Some('\n');
+    content.point_to_char(Point::new(0, line_idx)) + content.line_len(line_idx)
+        - usize::from(has_newline)
+}
+
+/// The display columns taken by the grapheme cluster at `point`.
+fn grapheme_columns(content: &TextObject, point: Point<usize>, tabstop: usize) -> Range<usize> {
+    let line = content.line(point.y).to_string();
+    line_graphemes(&line, tabstop)
+        .find(|grapheme| grapheme.x >= point.x)
+        .map(|grapheme| grapheme.column..grapheme.column + grapheme.width)
+        .unwrap_or_else(|| {
+            let column = content.display_column(point, tabstop);
+            column..column + 1
+        })
+}
+
+/// The char ranges of each line of a block, the graphemes that start within `columns`.
+fn block_ranges(
+    content: &TextObject,
+    top: usize,
+    bottom: usize,
+    columns: &Range<usize>,
+    tabstop: usize,
+) -> Vec<Range<usize>> {
+    (top..=bottom)
+        .map(|line_idx| {
+            let line = content.line(line_idx).to_string();
+            let line_start = content.point_to_char(Point::new(0, line_idx));
+            let graphemes = line_graphemes(&line, tabstop)
+                .filter(|grapheme| grapheme.text != "\n" && columns.contains(&grapheme.column))
+                .collect::<Vec<_>>();
+
+            match (graphemes.first(), graphemes.last()) {
+                (Some(first), Some(last)) => {
+                    line_start + first.x..line_start + last.x + last.text.chars().count()
+                }
+                _ => line_start..line_start,
+            }
+        })
+        .collect()
+}
+
+/// Puts the cursor on `char_idx`, or as close as `mode` lets it be.
+pub(super) fn place_cursor(ctx: &mut CommandContext<'_>, char_idx: usize, mode: VimMode) {
+    let view = ctx.views.get_mut_active_view();
+    let cursor = view.cursors.first_mut().unwrap();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+    let content = buffer.content();
+
+    let position = content.char_to_point(char_idx.min(content.len_chars()));
+    let last_char = content.line(position.y).chars().last().unwrap_or_default();
+    let has_newline = matches!(last_char, '\n');
+    let offset_from_eol = get_offset_from_eol(mode, has_newline);
+
+    cursor.move_to_with_offset(buffer, position.x, position.y, offset_from_eol);
+}
+
+/// Deletes the blocks of each line, returning where the first one started.
+fn delete_block(
+    ctx: &mut CommandContext<'_>,
+    top: usize,
+    bottom: usize,
+    columns: &Range<usize>,
+) -> usize {
+    let view = ctx.views.get_active_view();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+    let ranges = block_ranges(buffer.content(), top, bottom, columns, buffer.tabstop());
+
+    let mut transaction = Transaction::new();
+    for range in ranges.iter().filter(|range| !range.is_empty()) {
+        transaction.delete(range.clone());
+    }
+    apply_transaction(ctx, &transaction);
+
+    // the lines above the block are untouched, so its first range still starts there
+    ranges[0].start
+}
+
+fn delete(ctx: &mut CommandContext<'_>, range: OperatorRange, mode: VimMode) {
+    match range {
+        OperatorRange::Chars(range) => {
+            apply_transaction(ctx, Transaction::new().delete(range.clone()));
+            place_cursor(ctx, range.start, mode);
+        }
+        OperatorRange::Lines { top, bottom } => delete_lines(ctx, top, bottom),
+        OperatorRange::Block {
+            top,
+            bottom,
+            columns,
+        } => {
+            let start = delete_block(ctx, top, bottom, &columns);
+            place_cursor(ctx, start, mode);
+        }
+    }
+}
+
+/// Deletes lines `top` to `bottom`, leaving the cursor on the first non-blank of the line that
+/// takes their place.
+fn delete_lines(ctx: &mut CommandContext<'_>, top: usize, bottom: usize) {
+    let view = ctx.views.get_active_view();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+
+    let last_line = content.len_lines().saturating_sub(1);
+    let line_start = content.point_to_char(Point::new(0, top));
+    let line_end = content.point_to_char(Point::new(0, bottom)) + content.line_len(bottom);
+
+    // the last line has no line break of its own, so the one that ends the line above goes with it
+    let range = match bottom == last_line {
+        true => line_start.saturating_sub(1)..line_end,
+        false => line_start..line_end,
+    };
+
+    apply_transaction(ctx, Transaction::new().delete(range));
+
+    // deleting the lines at the end leaves the cursor on the line above them
+    let view = ctx.views.get_mut_active_view();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+    let cursor = view.cursors.first_mut().unwrap();
+    cursor.y = top.min(last_text_line(content));
+
+    move_to_first_non_space(ctx);
+
+    let lines = bottom - top + 1;
+    if lines > 2 {
+        *ctx.message = Some(Message::info(format!("{lines} fewer lines")));
+    }
+}
+
+/// Deletes the range and leaves the cursor where the text is typed again. Whole lines are
+/// emptied rather than deleted, so the text goes on a line of its own.
+fn change(ctx: &mut CommandContext<'_>, range: OperatorRange) {
+    match range {
+        OperatorRange::Chars(range) => {
+            apply_transaction(ctx, Transaction::new().delete(range.clone()));
+            place_cursor(ctx, range.start, VimMode::Insert);
+        }
+        OperatorRange::Lines { top, bottom } => {
+            let view = ctx.views.get_active_view();
+            let content = ctx
+                .buffers
+                .get(view.buffer_id)
+                .expect("view references non-existing buffer")
+                .content();
+
+            let start = content.point_to_char(Point::new(0, top));
+            let end = line_content_end(content, bottom);
+            apply_transaction(ctx, Transaction::new().delete(start..end));
+            place_cursor(ctx, start, VimMode::Insert);
+        }
+        OperatorRange::Block {
+            top,
+            bottom,
+            columns,
+        } => {
+            let start = delete_block(ctx, top, bottom, &columns);
+            place_cursor(ctx, start, VimMode::Insert);
+        }
+    }
+}
+
+/// Moves the cursor to the start of the range, the text was already stored in a register.
+fn yank(ctx: &mut CommandContext<'_>, range: OperatorRange, start: Point<usize>) {
+    let view = ctx.views.get_active_view();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+    let content = buffer.content();
+    let (top, bottom) = range.lines(content);
+
+    let char_idx = match &range {
+        OperatorRange::Chars(range) => range.start,
+        OperatorRange::Lines { top, .. } => {
+            content.point_to_char(Point::new(0, *top)) + start.x.min(content.line_len(*top))
+        }
+        OperatorRange::Block { top, columns, .. } => {
+            let x = content.column_to_x(*top, columns.start, buffer.tabstop());
+            content.point_to_char(Point::new(x, *top))
+        }
+    };
+    place_cursor(ctx, char_idx, VimMode::Normal);
+
+    let lines = bottom - top + 1;
+    let message = match range {
+        OperatorRange::Block { .. } => format!("block of {lines} lines yanked"),
+        _ => format!("{lines} lines yanked"),
+    };
+    if lines > 2 {
+        *ctx.message = Some(Message::info(message));
+    }
+}
+
+/// `>` and `<`, shifts the lines of the range by `shiftwidth` columns, leaving empty lines alone.
+fn shift(ctx: &mut CommandContext<'_>, range: OperatorRange, indent: bool) {
+    let view = ctx.views.get_active_view();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+    let content = buffer.content();
+    let shift_width = ctx.options.shift_width();
+    let (top, bottom) = range.lines(content);
+
+    let mut transaction = Transaction::new();
+    for line_idx in top..=bottom {
+        let line_start = content.point_to_char(Point::new(0, line_idx));
+        if line_content_end(content, line_idx) == line_start {
+            continue;
+        }
+
+        let (indent_len, width) = line_indent(content, line_idx, buffer.tabstop());
+        let width = match indent {
+            true => width + shift_width,
+            false => width.saturating_sub(shift_width),
+        };
+        transaction.replace(
+            line_start..line_start + indent_len,
+            indent_string(width, ctx.options.expandtab, buffer.tabstop()),
+        );
+    }
+
+    apply_transaction(ctx, &transaction);
+    ctx.views
+        .get_mut_active_view()
+        .cursors
+        .first_mut()
+        .unwrap()
+        .y = top;
+    move_to_first_non_space(ctx);
+
+    let lines = bottom - top + 1;
+    if lines > 2 {
+        let direction = if indent { '>' } else { '<' };
+        *ctx.message = Some(Message::info(format!("{lines} lines {direction}ed 1 time")));
+    }
+}
+
+/// `=`, indents each line of the range one `shiftwidth` deeper than the line above for every
+/// bracket that line leaves open, and one less for every bracket it starts by closing. Blank
+/// lines are emptied.
+fn reindent(ctx: &mut CommandContext<'_>, range: OperatorRange) {
+    let view = ctx.views.get_active_view();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+    let content = buffer.content();
+    let tabstop = buffer.tabstop();
+    let shift_width = ctx.options.shift_width() as isize;
+    let (top, bottom) = range.lines(content);
+
+    // the first line is indented against the last non-blank line above the range
+    let (mut width, mut open) = (0..top)
+        .rev()
+        .map(|line_idx| (line_idx, content.line(line_idx).to_string()))
+        .find(|(_, line)| !line.trim().is_empty())
+        .map(|(line_idx, line)| {
+            let (_, width) = line_indent(content, line_idx, tabstop);
+            (width as isize, bracket_balance(line.trim()).1)
+        })
+        .unwrap_or_default();
+
+    let mut transaction = Transaction::new();
+    for line_idx in top..=bottom {
+        let line = content.line(line_idx).to_string();
+        let text = line.trim();
+        let (indent_len, _) = line_indent(content, line_idx, tabstop);
+        let line_start = content.point_to_char(Point::new(0, line_idx));
+
+        if text.is_empty() {
+            let end = line_content_end(content, line_idx);
+            transaction.delete(line_start..end);
+            continue;
+        }
+
+        let (closing, balance) = bracket_balance(text);
+        width = (width + shift_width * (open - closing)).max(0);
+        open = balance;
+
+        transaction.replace(
+            line_start..line_start + indent_len,
+            indent_string(width as usize, ctx.options.expandtab, tabstop),
+        );
+    }
+
+    apply_transaction(ctx, &transaction);
+    ctx.views
+        .get_mut_active_view()
+        .cursors
+        .first_mut()
+        .unwrap()
+        .y = top;
+    move_to_first_non_space(ctx);
+
+    let lines = bottom - top + 1;
+    if lines > 2 {
+        *ctx.message = Some(Message::info(format!("{lines} lines indented")));
+    }
+}
+
+/// How many brackets `text` starts by closing, and how many of the rest are left open, negative
+/// when more are closed.
+fn bracket_balance(text: &str) -> (isize, isize) {
+    let is_closing = |ch: char| matches!(ch, ')' | ']' | '}');
+    let closing = text.chars().take_while(|&ch| is_closing(ch)).count();
+    let balance = text
+        .chars()
+        .skip(closing)
+        .map(|ch| match ch {
+            '(' | '[' | '{' => 1,
+            ')' | ']' | '}' => -1,
+            _ => 0,
+        })
+        .sum();
+
+    (closing as isize, balance)
+}
+
+/// How many chars the indent of a line takes, and how many display columns.
+fn line_indent(content: &TextObject, line_idx: usize, tabstop: usize) -> (usize, usize) {
+    let indent_len = content
+        .line(line_idx)
+        .chars()
+        .take_while(|&ch| ch == ' ' || ch == '\t')
+        .count();
+
+    (
+        indent_len,
+        content.display_column(Point::new(indent_len, line_idx), tabstop),
+    )
+}
+
+/// An indent `width` columns wide, of spaces with `expandtab` and of as many tabs as fit otherwise.
+fn indent_string(width: usize, expandtab: bool, tabstop: usize) -> String {
+    match expandtab {
+        true => " ".repeat(width),
+        false => "\t".repeat(width / tabstop) + &" ".repeat(width % tabstop),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestEditor;
+
+    fn span(from: (usize, usize), to: (usize, usize), kind: MotionKind) -> MotionSpan {
+        MotionSpan {
+            from: Point::new(from.0, from.1),
+            to: Point::new(to.0, to.1),
+            kind,
+            adjusts_end: true,
+        }
+    }
+
+    #[test]
+    fn test_exclusive_motion_ending_at_line_start() {
+        let content = TextObject::new("  foo\n  bar\nbaz\n".into());
+        let range = |from, adjusts_end| {
+            let span = MotionSpan {
+                adjusts_end,
+                ..span(from, (0, 1), MotionKind::Exclusive)
+            };
+            to_operator_range(&content, span, None, 8)
+        };
+
+        // from the first non-blank it becomes linewise, without the line it ends on
+        assert_eq!(
+            range((2, 0), true),
+            OperatorRange::Lines { top: 0, bottom: 0 }
+        );
+        assert_eq!(
+            range((0, 0), true),
+            OperatorRange::Lines { top: 0, bottom: 0 }
+        );
+        // from further in the line it stops before the line break
+        assert_eq!(range((3, 0), true), OperatorRange::Chars(3..5));
+        // text objects keep their end
+        assert_eq!(range((3, 0), false), OperatorRange::Chars(3..6));
+    }
+
+    #[test]
+    fn test_forced_motions() {
+        let content = TextObject::new("abc def\nghi jkl\n".into());
+        let range = |kind, force| to_operator_range(&content, span((0, 0), (2, 0), kind), force, 8);
+
+        assert_eq!(
+            range(MotionKind::Exclusive, None),
+            OperatorRange::Chars(0..2)
+        );
+        assert_eq!(
+            range(MotionKind::Exclusive, Some(MotionType::Charwise)),
+            OperatorRange::Chars(0..3)
+        );
+        assert_eq!(
+            range(MotionKind::Inclusive, Some(MotionType::Charwise)),
+            OperatorRange::Chars(0..2)
+        );
+        assert_eq!(
+            range(MotionKind::Linewise, Some(MotionType::Charwise)),
+            OperatorRange::Chars(0..2)
+        );
+        assert_eq!(
+            range(MotionKind::Exclusive, Some(MotionType::Linewise)),
+            OperatorRange::Lines { top: 0, bottom: 0 }
+        );
+
+        // a forced exclusive motion isn't adjusted when it ends at the start of a line
+        let span = span((1, 0), (0, 1), MotionKind::Linewise);
+        assert_eq!(
+            to_operator_range(&content, span, Some(MotionType::Charwise), 8),
+            OperatorRange::Chars(1..8)
+        );
+    }
+
+    #[test]
+    fn test_blockwise_range() {
+        let content = TextObject::new("abcd\nefgh\n\tij\n".into());
+        let block = |from, to| {
+            let span = span(from, to, MotionKind::Linewise);
+            to_operator_range(&content, span, Some(MotionType::Blockwise), 8)
+        };
+
+        let expected = OperatorRange::Block {
+            top: 0,
+            bottom: 1,
+            columns: 1..3,
+        };
+        assert_eq!(block((1, 0), (2, 1)), expected);
+        // the corners can be given either way around
+        assert_eq!(block((2, 0), (1, 1)), expected);
+
+        // a tab takes every column it covers
+        let OperatorRange::Block { columns, .. } = block((0, 2), (1, 0)) else {
+            panic!("expected a block");
+        };
+        assert_eq!(columns, 0..8);
+
+        let register = register_content(&content, &block((1, 0), (2, 1)), 8);
+        assert_eq!(register, Register::new("bc\nfg", MotionType::Blockwise));
+    }
+
+    #[test]
+    fn test_change_word_acts_like_change_end() {
+        let mut editor = TestEditor::new("foo bar baz\n");
+        editor.keys("cwx<esc>");
+        assert_eq!(editor.text(), "x bar baz\n");
+
+        let mut editor = TestEditor::new("foo bar baz\n");
+        editor.keys("c2wx<esc>");
+        assert_eq!(editor.text(), "x baz\n");
+
+        // on a blank it changes the blanks only
+        let mut editor = TestEditor::new("foo   bar\n");
+        editor.keys("lllcwx<esc>");
+        assert_eq!(editor.text(), "fooxbar\n");
+    }
+
+    #[test]
+    fn test_delete_with_motions() {
+        let mut editor = TestEditor::new("call(a, b) end\n");
+        editor.keys("f(ldt)");
+        assert_eq!(editor.text(), "call() end\n");
+        assert_eq!(editor.cursor(), (5, 0));
+        assert_eq!(
+            editor.registers.get('"'),
+            Some(Register::new("a, b", MotionType::Charwise))
+        );
+
+        let mut editor = TestEditor::new("one two foo three\n");
+        editor.keys("d/foo<cr>");
+        assert_eq!(editor.text(), "foo three\n");
+        assert_eq!(editor.cursor(), (0, 0));
+
+        // `dw` on the last word of a line doesn't join the next one
+        let mut editor = TestEditor::new("one two\nthree\n");
+        editor.keys("wdw");
+        assert_eq!(editor.text(), "one \nthree\n");
+
+        let mut editor = TestEditor::new("a\nb\nc\nd\n");
+        editor.keys("j2dd");
+        assert_eq!(editor.text(), "a\nd\n");
+        assert_eq!(editor.cursor(), (0, 1));
+    }
+
+    #[test]
+    fn test_blockwise_delete() {
+        let mut editor = TestEditor::new("abcd\nefgh\nijkl\n");
+        editor.keys("ld<c-v>j");
+        assert_eq!(editor.text(), "acd\negh\nijkl\n");
+
+        // the block spans from the cursor to where the search lands
+        let mut editor = TestEditor::new("abcd\nefgh\nijkl\n");
+        editor.keys("ld<c-v>/g<cr>");
+        assert_eq!(editor.text(), "ad\neh\nijkl\n");
+        assert_eq!(editor.cursor(), (1, 0));
+        assert_eq!(
+            editor.registers.get('"'),
+            Some(Register::new("bc\nfg", MotionType::Blockwise))
+        );
+    }
+
+    #[test]
+    fn test_shift_and_reindent_with_counts() {
+        let mut editor = TestEditor::new("a\nb\n\nc\nd\n");
+        editor.options.shiftwidth = 4;
+        editor.options.expandtab = true;
+
+        editor.keys("4>>");
+        assert_eq!(editor.text(), "    a\n    b\n\n    c\nd\n");
+        assert_eq!(editor.message, Some(Message::info("4 lines >ed 1 time")));
+        assert_eq!(editor.cursor(), (4, 0));
+
+        editor.keys(">j");
+        assert_eq!(editor.text(), "        a\n        b\n\n    c\nd\n");
+
+        editor.keys("j2<lt>j");
+        assert_eq!(editor.text(), "        a\n    b\n\nc\nd\n");
+
+        let mut editor = TestEditor::new("fn main() {\nfoo(\nbar)\n    }\nend\n");
+        editor.options.shiftwidth = 4;
+        editor.options.expandtab = true;
+        editor.keys("4==");
+        assert_eq!(
+            editor.text(),
+            "fn main() {\n    foo(\n        bar)\n}\nend\n"
+        );
+        assert_eq!(editor.message, Some(Message::info("4 lines indented")));
+    }
+}
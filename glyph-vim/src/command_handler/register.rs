@@ -0,0 +1,374 @@
+use glyph_core::command_handler::{CommandContext, apply_transaction};
+use glyph_core::error::{GlyphError, Result};
+use glyph_core::geometry::Point;
+use glyph_core::key_mapper::{MotionType, VimMode};
+use glyph_core::message::Message;
+use glyph_core::register::Register;
+use glyph_core::text_object::{Transaction, grapheme_width, line_graphemes};
+
+use super::operator::{line_content_end, place_cursor};
+use super::{last_text_line, move_to_first_non_space};
+
+/// `p` and `P`, puts the text of the register typed before them `count` times. Text taken as whole
+/// lines goes below or above the cursor line, and a block goes into the same columns of the lines
+/// from the cursor down.
+pub(super) fn put(
+    ctx: &mut CommandContext<'_>,
+    before: bool,
+    cursor_after: bool,
+    count: usize,
+) -> Result<()> {
+    let name = ctx.resolved_keymap.register.unwrap_or('"');
+    if name == '_' {
+        return Ok(());
+    }
+
+    let register = ctx
+        .registers
+        .get(name)
+        .ok_or(GlyphError::EmptyRegister(name))?;
+
+    match register.motion_type {
+        MotionType::Charwise => put_chars(ctx, &register.text.repeat(count), before, cursor_after),
+        MotionType::Linewise => put_lines(ctx, &register.text.repeat(count), before, cursor_after),
+        MotionType::Blockwise => put_block(ctx, &register, count, before, cursor_after),
+    }
+
+    Ok(())
+}
+
+fn put_chars(ctx: &mut CommandContext<'_>, text: &str, before: bool, cursor_after: bool) {
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+
+    let char_idx = content.point_to_char(Point::new(cursor.x, cursor.y));
+    let at = match before {
+        true => char_idx,
+        false => content
+            .next_grapheme_boundary(char_idx)
+            .min(line_content_end(content, cursor.y))
+            .max(char_idx),
+    };
+    apply_transaction(ctx, Transaction::new().insert(at, text));
+
+    // the cursor goes on the last character put, or on the first when it spans lines
+    let end = at + text.chars().count();
+    let view = ctx.views.get_active_view();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+    let target = match (cursor_after, text.contains('\n')) {
+        (true, _) => end,
+        (false, true) => at,
+        (false, false) => content.prev_grapheme_boundary(end).max(at),
+    };
+
+    place_cursor(ctx, target, VimMode::Normal);
+}
+
+fn put_lines(ctx: &mut CommandContext<'_>, text: &str, before: bool, cursor_after: bool) {
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+
+    let line_idx = match before {
+        true => cursor.y,
+        false => cursor.y + 1,
+    };
+
+    // below a last line without a line break, the text needs one to start on a line of its own
+    let mut transaction = Transaction::new();
+    match line_idx < content.len_lines() {
+        true => transaction.insert(content.point_to_char(Point::new(0, line_idx)), text),
+        false => {
+            let text = format!("\n{}", text.strip_suffix('\n').unwrap_or(text));
+            transaction.insert(content.len_chars(), text)
+        }
+    };
+    apply_transaction(ctx, &transaction);
+
+    let lines = text.matches('\n').count().max(1);
+    let view = ctx.views.get_mut_active_view();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+    let cursor = view.cursors.first_mut().unwrap();
+
+    match cursor_after {
+        true => {
+            cursor.y = (line_idx + lines).min(last_text_line(content));
+            cursor.x = 0;
+            let char_idx = content.point_to_char(Point::new(0, cursor.y));
+            place_cursor(ctx, char_idx, VimMode::Normal);
+        }
+        false => {
+            cursor.y = line_idx;
+            move_to_first_non_space(ctx);
+        }
+    }
+}
+
+/// Puts each row of a block on a line of its own, from the cursor line down, at the display
+/// column after the cursor or at the cursor. Short lines are padded with spaces to reach that
+/// column, and lines are added past the end of the buffer.
+fn put_block(
+    ctx: &mut CommandContext<'_>,
+    register: &Register,
+    count: usize,
+    before: bool,
+    cursor_after: bool,
+) {
+    let view = ctx.views.get_active_view();
+    let cursor = *view.cursors.first().unwrap();
+    let buffer = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer");
+    let content = buffer.content();
+    let tabstop = buffer.tabstop();
+
+    let rows = register.text.split('\n').collect::<Vec<_>>();
+    let width = rows
+        .iter()
+        .map(|row| row_width(row))
+        .max()
+        .unwrap_or_default();
+
+    let cursor_line = content.line(cursor.y).to_string();
+    let column = line_graphemes(&cursor_line, tabstop)
+        .find(|grapheme| grapheme.x == cursor.x && grapheme.text != "\n")
+        .map(|grapheme| match before {
+            true => grapheme.column,
+            false => grapheme.column + grapheme.width,
+        })
+        .unwrap_or_default();
+
+    // where the text of the first row starts and the last one ends, for the cursor
+    let mut first = None;
+    let mut last = None;
+    let mut inserts = vec![];
+    let mut appended = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let text = format!("{row}{}", " ".repeat(width - row_width(row))).repeat(count);
+        let line_idx = cursor.y + i;
+
+        if line_idx > last_text_line(content) {
+            let text = format!("{}{}", " ".repeat(column), text.trim_end());
+            appended.push('\n');
+            appended.push_str(&text);
+            first.get_or_insert(Point::new(column, line_idx));
+            last = Some(Point::new(text.chars().count(), line_idx));
+            continue;
+        }
+
+        let line = content.line(line_idx).to_string();
+        let line_start = content.point_to_char(Point::new(0, line_idx));
+        let next = line_graphemes(&line, tabstop)
+            .find(|grapheme| grapheme.column >= column && grapheme.text != "\n");
+
+        // a short line is padded to reach the column, but nothing needs to follow the row there
+        let (x, text) = match next {
+            Some(grapheme) => (grapheme.x, text),
+            None => {
+                let x = line_content_end(content, line_idx) - line_start;
+                let line_width = content.display_column(Point::new(x, line_idx), tabstop);
+                let padding = " ".repeat(column.saturating_sub(line_width));
+                (x, format!("{padding}{}", text.trim_end()))
+            }
+        };
+
+        let start = x + text.chars().count() - text.trim_start_matches(' ').chars().count();
+        first.get_or_insert(Point::new(start, line_idx));
+        last = Some(Point::new(x + text.chars().count(), line_idx));
+        inserts.push((line_start + x, text));
+    }
+
+    // the rows past the end follow the one put at the end of the last line, if any
+    if !appended.is_empty() {
+        let end = line_content_end(content, last_text_line(content));
+        match inserts.last_mut() {
+            Some((char_idx, text)) if *char_idx == end => text.push_str(&appended),
+            _ => inserts.push((end, appended)),
+        }
+    }
+
+    let mut transaction = Transaction::new();
+    for (char_idx, text) in inserts {
+        transaction.insert(char_idx, text);
+    }
+    apply_transaction(ctx, &transaction);
+
+    let view = ctx.views.get_active_view();
+    let content = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content();
+    let target = match cursor_after {
+        true => last,
+        false => first,
+    };
+    if let Some(target) = target {
+        place_cursor(ctx, content.point_to_char(target), VimMode::Normal);
+    }
+}
+
+fn row_width(row: &str) -> usize {
+    line_graphemes(row, 1)
+        .map(|grapheme| grapheme_width(grapheme.text))
+        .sum()
+}
+
+/// Types the text of register `name` at the cursor, for `<c-r>` in insert mode, returning it.
+pub(super) fn insert_register(ctx: &mut CommandContext<'_>, name: char) -> Result<String> {
+    let register = ctx
+        .registers
+        .get(name)
+        .ok_or(GlyphError::EmptyRegister(name))?;
+
+    let view = ctx.views.get_active_view();
+    let cursor = view.cursors.first().unwrap();
+    let char_idx = ctx
+        .buffers
+        .get(view.buffer_id)
+        .expect("view references non-existing buffer")
+        .content()
+        .point_to_char(Point::new(cursor.x, cursor.y));
+
+    apply_transaction(ctx, Transaction::new().insert(char_idx, &register.text));
+
+    Ok(register.text)
+}
+
+/// `:registers`, shows the content of every register that holds something, with control
+/// characters drawn visibly, see [`escape_controls`].
+pub(super) fn list_registers(ctx: &mut CommandContext<'_>) {
+    let mut lines = vec![String::from("Type Name Content")];
+
+    for (name, register) in ctx.registers.list() {
+        let kind = match register.motion_type {
+            MotionType::Charwise => 'c',
+            MotionType::Linewise => 'l',
+            MotionType::Blockwise => 'b',
+        };
+        let content = escape_controls(&register.text);
+
+        lines.push(format!("  {kind}  \"{name}   {content}"));
+    }
+
+    *ctx.message = Some(Message::info(lines.join("\n")));
+}
+
+/// Draws ASCII control characters in caret notation, like a line break as `^J`, and other control
+/// characters by their code point, like `<85>`.
+fn escape_controls(text: &str) -> String {
+    text.chars()
+        .map(|ch| match ch {
+            ch if ch.is_ascii_control() => format!("^{}", ((ch as u8) ^ 0x40) as char),
+            ch if ch.is_control() => format!("<{:x}>", ch as u32),
+            ch => ch.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use glyph_core::editing_plugin::EditingPlugin;
+
+    use super::*;
+    use crate::testing::TestEditor;
+
+    #[test]
+    fn test_put_chars() {
+        let put = |keys| {
+            let mut editor = TestEditor::new("foo bar\n");
+            editor.keys("yiww").keys(keys);
+            (editor.text(), editor.cursor())
+        };
+
+        // the cursor ends on the last character put, or right after it with `gp` and `gP`
+        assert_eq!(put("p"), ("foo bfooar\n".into(), (7, 0)));
+        assert_eq!(put("P"), ("foo foobar\n".into(), (6, 0)));
+        assert_eq!(put("gp"), ("foo bfooar\n".into(), (8, 0)));
+        assert_eq!(put("gP"), ("foo foobar\n".into(), (7, 0)));
+        assert_eq!(put("3p"), ("foo bfoofoofooar\n".into(), (13, 0)));
+    }
+
+    #[test]
+    fn test_put_lines() {
+        let put = |keys| {
+            let mut editor = TestEditor::new("one\n  two\n");
+            editor.keys("jyyk").keys(keys);
+            (editor.text(), editor.cursor())
+        };
+
+        // the cursor ends on the first non-blank of the first line put, or on the line after the
+        // last one with `gp` and `gP`
+        assert_eq!(put("p"), ("one\n  two\n  two\n".into(), (2, 1)));
+        assert_eq!(put("P"), ("  two\none\n  two\n".into(), (2, 0)));
+        assert_eq!(put("gp"), ("one\n  two\n  two\n".into(), (0, 2)));
+        assert_eq!(put("gP"), ("  two\none\n  two\n".into(), (0, 1)));
+        assert_eq!(put("j2p"), ("one\n  two\n  two\n  two\n".into(), (2, 2)));
+    }
+
+    #[test]
+    fn test_named_and_black_hole_registers() {
+        let mut editor = TestEditor::new("one\ntwo\nthree\n");
+
+        // an uppercase name appends to the register
+        editor.keys("\"ayyj\"AyyG\"ap");
+        assert_eq!(editor.text(), "one\ntwo\nthree\none\ntwo\n");
+
+        // the black hole register leaves the unnamed one as it was, and has nothing to put
+        editor.keys("ggyyj\"_ddp\"_p");
+        assert_eq!(editor.text(), "one\nthree\none\none\ntwo\n");
+        assert_eq!(editor.registers.get('"').unwrap().text, "one\n");
+    }
+
+    #[test]
+    fn test_put_block() {
+        let put = |keys| {
+            let mut editor = TestEditor::new("ab\ncd\nxyz\nq\n");
+            editor.keys("y<c-v>/d<cr>2jll").keys(keys);
+            (editor.text(), editor.cursor())
+        };
+
+        // short lines are padded to reach the column the block goes to
+        assert_eq!(put("p"), ("ab\ncd\nxyzab\nq  cd\n".into(), (3, 2)));
+        assert_eq!(put("P"), ("ab\ncd\nxyabz\nq cd\n".into(), (2, 2)));
+        assert_eq!(put("2p"), ("ab\ncd\nxyzabab\nq  cdcd\n".into(), (3, 2)));
+
+        // rows past the end of the buffer go on lines of their own
+        assert_eq!(put("jp"), ("ab\ncd\nxyz\nqab\n cd\n".into(), (1, 3)));
+    }
+
+    #[test]
+    fn test_insert_register() {
+        let mut editor = TestEditor::new("one.\n");
+        editor.keys("\"ayiw$i <c-r>a<esc>");
+        assert_eq!(editor.text(), "one one.\n");
+
+        editor.keys(":<c-r>a");
+        assert_eq!(editor.plugin.render_dock().as_deref(), Some(":one"));
+    }
+
+    #[test]
+    fn test_escape_controls() {
+        assert_eq!(escape_controls("a\tb\n"), "a^Ib^J");
+        assert_eq!(escape_controls("\x1b\x7f"), "^[^?");
+        assert_eq!(escape_controls("\u{85}\u{9b}é"), "<85><9b>é");
+    }
+}
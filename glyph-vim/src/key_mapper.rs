@@ -1,61 +1,134 @@
 mod command_mode_key_mapper;
 mod insert_mode_key_mapper;
 mod normal_mode_key_mapper;
+mod search_mode_key_mapper;
 
-use glyph_core::key_mapper::{Command, VimMode};
+use glyph_core::event_loop::event::{KeyCode, KeyEvent, KeyModifiers};
+use glyph_core::key_mapper::{Command, MotionType, Operator};
+use glyph_core::text_object::{CharSearch, SearchDirection, TextObjectKind, TextObjectScope};
 use glyph_trie::Trie;
 
 pub use self::command_mode_key_mapper::CommandModeKeymapper;
 pub use self::insert_mode_key_mapper::InsertModeKeymapper;
-pub use self::normal_mode_key_mapper::NormalModeKeymapper;
+pub use self::normal_mode_key_mapper::{NormalModeKeymapper, PendingOperator};
+pub use self::search_mode_key_mapper::SearchModeKeymapper;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub enum CommandWrapper {
     General(Command),
     Vim(VimCommand),
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 pub enum VimCommand {
     InsertMode,
     NormalMode,
     CommandMode,
+    SearchMode(SearchDirection),
+}
+
+/// Builds the commands of a keymap that takes the character typed after it, like `f`.
+pub type KeymapArgument = fn(char) -> Vec<CommandWrapper>;
+
+/// What typing the keys of a keymap does.
+#[derive(Debug)]
+enum KeymapAction {
+    /// Runs the commands right away.
+    Commands(Vec<CommandWrapper>),
+    /// Waits for one more character, which can't be expressed by keys of the trie.
+    Argument(KeymapArgument),
+    /// Waits for the motion or text object the operator applies to.
+    Operator(Operator),
+    /// Forces the motion typed after an operator to be charwise, linewise or blockwise.
+    ForceMotion(MotionType),
 }
 
 #[derive(Debug)]
 pub struct Keymap {
-    mode: VimMode,
-    commands: Vec<CommandWrapper>,
+    action: KeymapAction,
 }
 
 impl Keymap {
-    fn new(mode: VimMode, commands: Vec<CommandWrapper>) -> Self {
-        Self { mode, commands }
+    fn new(commands: Vec<CommandWrapper>) -> Self {
+        Self {
+            action: KeymapAction::Commands(commands),
+        }
+    }
+
+    fn with_argument(argument: KeymapArgument) -> Self {
+        Self {
+            action: KeymapAction::Argument(argument),
+        }
+    }
+
+    fn operator(operator: Operator) -> Self {
+        Self {
+            action: KeymapAction::Operator(operator),
+        }
+    }
+
+    fn force_motion(motion_type: MotionType) -> Self {
+        Self {
+            action: KeymapAction::ForceMotion(motion_type),
+        }
+    }
+}
+
+fn find_char(ch: char, direction: SearchDirection, till: bool) -> Vec<CommandWrapper> {
+    let search = CharSearch {
+        ch,
+        direction,
+        till,
+    };
+    vec![CommandWrapper::General(Command::FindChar(search))]
+}
+
+fn select_text_object(ch: char, scope: TextObjectScope) -> Vec<CommandWrapper> {
+    TextObjectKind::from_key(ch)
+        .map(|kind| {
+            vec![CommandWrapper::General(Command::SelectTextObject {
+                kind,
+                scope,
+            })]
+        })
+        .unwrap_or_default()
+}
+
+/// The character a key types, if any.
+fn key_char(key: &KeyEvent) -> Option<char> {
+    if !(key.modifiers & !KeyModifiers::SHIFT).is_empty() {
+        return None;
+    }
+
+    match key.code {
+        KeyCode::Char(ch) => Some(ch),
+        KeyCode::Tab => Some('\t'),
+        _ => None,
     }
 }
 
 pub struct LoadedKeymaps {
     pub normal: Trie<Keymap>,
+    /// The motions and text objects an operator waits for.
+    pub operator_pending: Trie<Keymap>,
     pub insert: Trie<Keymap>,
 }
 
-// TODO: this will probably not cut it when it comes to repetitions of keymaps. Such as d10j or
-// 10dj, will we need a parser for commands?
 pub fn load_vim_keymaps() -> LoadedKeymaps {
     let mut normal_keymaps = Trie::new();
+    let mut operator_pending_keymaps = Trie::new();
     let mut insert_keymaps = Trie::new();
 
-    let normal = VimMode::Normal;
-    let insert = VimMode::Insert;
-
     let move_cursor_left = CommandWrapper::General(Command::MoveCursorLeft);
     let move_cursor_down = CommandWrapper::General(Command::MoveCursorDown);
     let move_cursor_up = CommandWrapper::General(Command::MoveCursorUp);
     let move_cursor_right = CommandWrapper::General(Command::MoveCursorRight);
+    let move_cursor_display_down = CommandWrapper::General(Command::MoveCursorDisplayDown);
+    let move_cursor_display_up = CommandWrapper::General(Command::MoveCursorDisplayUp);
     let move_cursor_right_over_lines = CommandWrapper::General(Command::MoveCursorRightOverLines);
     let move_cursor_to_line_start = CommandWrapper::General(Command::MoveCursorLineStart);
     let move_cursor_to_line_end = CommandWrapper::General(Command::MoveCursorLineEnd);
-    let delete_whole_line = CommandWrapper::General(Command::DeleteWholeLine);
     let move_to_top = CommandWrapper::General(Command::MoveToTop);
     let move_to_bottom = CommandWrapper::General(Command::MoveToBottom);
     let page_up = CommandWrapper::General(Command::PageUp);
@@ -65,61 +138,237 @@ pub fn load_vim_keymaps() -> LoadedKeymaps {
     let move_to_last_non_space = CommandWrapper::General(Command::MoveToLastNonSpace);
     let move_to_next_paragraph = CommandWrapper::General(Command::MoveToNextParagraph);
     let move_to_prev_paragraph = CommandWrapper::General(Command::MoveToPrevParagraph);
-    let delete_prev_char = CommandWrapper::General(Command::DeletePrevChar);
-    let delete_curr_char = CommandWrapper::General(Command::DeleteCurrChar);
+    let delete_prev_char = CommandWrapper::General(Command::Operate {
+        operator: Operator::Delete,
+        motion: Box::new(Command::MoveCursorLeft),
+        force: None,
+    });
+    let delete_curr_char = CommandWrapper::General(Command::Operate {
+        operator: Operator::Delete,
+        motion: Box::new(Command::MoveCursorRight),
+        force: None,
+    });
+    let put = |before, cursor_after| {
+        CommandWrapper::General(Command::Put {
+            before,
+            cursor_after,
+        })
+    };
     let move_to_next_word = CommandWrapper::General(Command::MoveToNextWord);
+    let move_to_next_big_word = CommandWrapper::General(Command::MoveToNextBigWord);
+    let move_to_prev_word = CommandWrapper::General(Command::MoveToPrevWord);
+    let move_to_prev_big_word = CommandWrapper::General(Command::MoveToPrevBigWord);
+    let move_to_word_end = CommandWrapper::General(Command::MoveToWordEnd);
+    let move_to_big_word_end = CommandWrapper::General(Command::MoveToBigWordEnd);
+    let move_to_prev_word_end = CommandWrapper::General(Command::MoveToPrevWordEnd);
+    let move_to_prev_big_word_end = CommandWrapper::General(Command::MoveToPrevBigWordEnd);
+    let repeat_char_search = CommandWrapper::General(Command::RepeatCharSearch);
+    let repeat_char_search_reversed = CommandWrapper::General(Command::RepeatCharSearchReversed);
+    let undo = CommandWrapper::General(Command::Undo);
+    let redo = CommandWrapper::General(Command::Redo);
+    let earlier = CommandWrapper::General(Command::Earlier);
+    let later = CommandWrapper::General(Command::Later);
     let quit = CommandWrapper::General(Command::Quit);
+    let alternate_buffer = CommandWrapper::General(Command::AlternateBuffer);
+    let search_next = CommandWrapper::General(Command::SearchNext);
+    let search_prev = CommandWrapper::General(Command::SearchPrev);
+    let search_word_forward =
+        CommandWrapper::General(Command::SearchWordUnderCursor(SearchDirection::Forward));
+    let search_word_backward =
+        CommandWrapper::General(Command::SearchWordUnderCursor(SearchDirection::Backward));
 
     let enter_insert_mode = CommandWrapper::Vim(VimCommand::InsertMode);
     let enter_normal_mode = CommandWrapper::Vim(VimCommand::NormalMode);
     let enter_command_mode = CommandWrapper::Vim(VimCommand::CommandMode);
+    let search_forward = CommandWrapper::Vim(VimCommand::SearchMode(SearchDirection::Forward));
+    let search_backward = CommandWrapper::Vim(VimCommand::SearchMode(SearchDirection::Backward));
 
     // cursor movement motions
-    normal_keymaps.insert("h", Keymap::new(normal, vec![move_cursor_left]));
-    normal_keymaps.insert("j", Keymap::new(normal, vec![move_cursor_down]));
-    normal_keymaps.insert("k", Keymap::new(normal, vec![move_cursor_up]));
-    normal_keymaps.insert("l", Keymap::new(normal, vec![move_cursor_right]));
-    normal_keymaps.insert("0", Keymap::new(normal, vec![move_cursor_to_line_start]));
-    normal_keymaps.insert("$", Keymap::new(normal, vec![move_cursor_to_line_end]));
-    normal_keymaps.insert("gg", Keymap::new(normal, vec![move_to_top]));
-    normal_keymaps.insert("G", Keymap::new(normal, vec![move_to_bottom]));
-    normal_keymaps.insert("%", Keymap::new(normal, vec![move_to_matching_pair]));
-    normal_keymaps.insert("^", Keymap::new(normal, vec![move_to_first_non_space]));
-    normal_keymaps.insert("g_", Keymap::new(normal, vec![move_to_last_non_space]));
-    normal_keymaps.insert("}", Keymap::new(normal, vec![move_to_next_paragraph]));
-    normal_keymaps.insert("{", Keymap::new(normal, vec![move_to_prev_paragraph]));
-    normal_keymaps.insert("w", Keymap::new(normal, vec![move_to_next_word]));
+    normal_keymaps.insert("h", Keymap::new(vec![move_cursor_left.clone()]));
+    normal_keymaps.insert("j", Keymap::new(vec![move_cursor_down.clone()]));
+    normal_keymaps.insert("k", Keymap::new(vec![move_cursor_up.clone()]));
+    normal_keymaps.insert("l", Keymap::new(vec![move_cursor_right.clone()]));
+    normal_keymaps.insert("gj", Keymap::new(vec![move_cursor_display_down]));
+    normal_keymaps.insert("gk", Keymap::new(vec![move_cursor_display_up]));
+    normal_keymaps.insert("0", Keymap::new(vec![move_cursor_to_line_start]));
+    normal_keymaps.insert("$", Keymap::new(vec![move_cursor_to_line_end]));
+    normal_keymaps.insert("gg", Keymap::new(vec![move_to_top]));
+    normal_keymaps.insert("G", Keymap::new(vec![move_to_bottom]));
+    normal_keymaps.insert("%", Keymap::new(vec![move_to_matching_pair]));
+    normal_keymaps.insert("^", Keymap::new(vec![move_to_first_non_space.clone()]));
+    normal_keymaps.insert("g_", Keymap::new(vec![move_to_last_non_space]));
+    normal_keymaps.insert("}", Keymap::new(vec![move_to_next_paragraph]));
+    normal_keymaps.insert("{", Keymap::new(vec![move_to_prev_paragraph]));
+    normal_keymaps.insert("w", Keymap::new(vec![move_to_next_word.clone()]));
+    normal_keymaps.insert("W", Keymap::new(vec![move_to_next_big_word]));
+    normal_keymaps.insert("b", Keymap::new(vec![move_to_prev_word.clone()]));
+    normal_keymaps.insert("B", Keymap::new(vec![move_to_prev_big_word]));
+    normal_keymaps.insert("e", Keymap::new(vec![move_to_word_end]));
+    normal_keymaps.insert("E", Keymap::new(vec![move_to_big_word_end]));
+    normal_keymaps.insert("ge", Keymap::new(vec![move_to_prev_word_end]));
+    normal_keymaps.insert("gE", Keymap::new(vec![move_to_prev_big_word_end]));
+
+    normal_keymaps.insert(
+        "f",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Forward, false)),
+    );
+    normal_keymaps.insert(
+        "t",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Forward, true)),
+    );
+    normal_keymaps.insert(
+        "F",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Backward, false)),
+    );
+    normal_keymaps.insert(
+        "T",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Backward, true)),
+    );
+    normal_keymaps.insert(";", Keymap::new(vec![repeat_char_search]));
+    normal_keymaps.insert(",", Keymap::new(vec![repeat_char_search_reversed]));
 
     normal_keymaps.insert(
         "<cr>",
-        Keymap::new(normal, vec![move_cursor_down, move_to_first_non_space]),
+        Keymap::new(vec![move_cursor_down.clone(), move_to_first_non_space]),
     );
 
-    normal_keymaps.insert(" ", Keymap::new(normal, vec![move_cursor_right_over_lines]));
+    normal_keymaps.insert(" ", Keymap::new(vec![move_cursor_right_over_lines]));
+
+    // `x` and `X` are `dl` and `dh`, so what they delete goes to a register
+    normal_keymaps.insert("X", Keymap::new(vec![delete_prev_char]));
+    normal_keymaps.insert("x", Keymap::new(vec![delete_curr_char]));
+
+    normal_keymaps.insert("i", Keymap::new(vec![enter_insert_mode]));
+
+    // operators, which wait for a motion or text object
+    normal_keymaps.insert("d", Keymap::operator(Operator::Delete));
+    normal_keymaps.insert("c", Keymap::operator(Operator::Change));
+    normal_keymaps.insert("y", Keymap::operator(Operator::Yank));
+    normal_keymaps.insert(">", Keymap::operator(Operator::Indent));
+    normal_keymaps.insert("<lt>", Keymap::operator(Operator::Outdent));
+    normal_keymaps.insert("=", Keymap::operator(Operator::Reindent));
+
+    normal_keymaps.insert("p", Keymap::new(vec![put(false, false)]));
+    normal_keymaps.insert("P", Keymap::new(vec![put(true, false)]));
+    normal_keymaps.insert("gp", Keymap::new(vec![put(false, true)]));
+    normal_keymaps.insert("gP", Keymap::new(vec![put(true, true)]));
+
+    normal_keymaps.insert("u", Keymap::new(vec![undo]));
+    normal_keymaps.insert("<c-r>", Keymap::new(vec![redo]));
+    normal_keymaps.insert("g-", Keymap::new(vec![earlier]));
+    normal_keymaps.insert("g+", Keymap::new(vec![later]));
 
-    normal_keymaps.insert("X", Keymap::new(normal, vec![delete_prev_char]));
-    normal_keymaps.insert("x", Keymap::new(normal, vec![delete_curr_char]));
+    normal_keymaps.insert("<c-u>", Keymap::new(vec![page_up]));
+    normal_keymaps.insert("<c-d>", Keymap::new(vec![page_down]));
+    normal_keymaps.insert(":", Keymap::new(vec![enter_command_mode]));
 
-    normal_keymaps.insert("i", Keymap::new(normal, vec![enter_insert_mode]));
-    normal_keymaps.insert("dd", Keymap::new(normal, vec![delete_whole_line]));
-    normal_keymaps.insert("<c-u>", Keymap::new(normal, vec![page_up]));
-    normal_keymaps.insert("<c-d>", Keymap::new(normal, vec![page_down]));
-    normal_keymaps.insert(":", Keymap::new(normal, vec![enter_command_mode]));
+    // search
+    normal_keymaps.insert("/", Keymap::new(vec![search_forward.clone()]));
+    normal_keymaps.insert("?", Keymap::new(vec![search_backward.clone()]));
+    normal_keymaps.insert("n", Keymap::new(vec![search_next]));
+    normal_keymaps.insert("N", Keymap::new(vec![search_prev]));
+    normal_keymaps.insert("*", Keymap::new(vec![search_word_forward]));
+    normal_keymaps.insert("#", Keymap::new(vec![search_word_backward]));
 
-    normal_keymaps.insert("q", Keymap::new(normal, vec![quit]));
+    // terminals send ctrl-^ as ctrl-6
+    normal_keymaps.insert("<c-^>", Keymap::new(vec![alternate_buffer.clone()]));
+    normal_keymaps.insert("<c-6>", Keymap::new(vec![alternate_buffer]));
+
+    normal_keymaps.insert("q", Keymap::new(vec![quit]));
+
+    // operator pending keymaps
+    let motions = [
+        ("h", Command::MoveCursorLeft),
+        ("j", Command::MoveCursorDown),
+        ("k", Command::MoveCursorUp),
+        ("l", Command::MoveCursorRight),
+        (" ", Command::MoveCursorRightOverLines),
+        ("<cr>", Command::MoveCursorDown),
+        ("gj", Command::MoveCursorDisplayDown),
+        ("gk", Command::MoveCursorDisplayUp),
+        ("0", Command::MoveCursorLineStart),
+        ("$", Command::MoveCursorLineEnd),
+        ("^", Command::MoveToFirstNonSpace),
+        ("g_", Command::MoveToLastNonSpace),
+        ("gg", Command::MoveToTop),
+        ("G", Command::MoveToBottom),
+        ("%", Command::MoveToMatchingPair),
+        ("}", Command::MoveToNextParagraph),
+        ("{", Command::MoveToPrevParagraph),
+        ("w", Command::MoveToNextWord),
+        ("W", Command::MoveToNextBigWord),
+        ("b", Command::MoveToPrevWord),
+        ("B", Command::MoveToPrevBigWord),
+        ("e", Command::MoveToWordEnd),
+        ("E", Command::MoveToBigWordEnd),
+        ("ge", Command::MoveToPrevWordEnd),
+        ("gE", Command::MoveToPrevBigWordEnd),
+        (";", Command::RepeatCharSearch),
+        (",", Command::RepeatCharSearchReversed),
+        ("n", Command::SearchNext),
+        ("N", Command::SearchPrev),
+        (
+            "*",
+            Command::SearchWordUnderCursor(SearchDirection::Forward),
+        ),
+        (
+            "#",
+            Command::SearchWordUnderCursor(SearchDirection::Backward),
+        ),
+    ];
+    for (keys, motion) in motions {
+        let motion = CommandWrapper::General(motion);
+        operator_pending_keymaps.insert(keys, Keymap::new(vec![motion]));
+    }
+
+    operator_pending_keymaps.insert(
+        "f",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Forward, false)),
+    );
+    operator_pending_keymaps.insert(
+        "t",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Forward, true)),
+    );
+    operator_pending_keymaps.insert(
+        "F",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Backward, false)),
+    );
+    operator_pending_keymaps.insert(
+        "T",
+        Keymap::with_argument(|ch| find_char(ch, SearchDirection::Backward, true)),
+    );
+    operator_pending_keymaps.insert(
+        "i",
+        Keymap::with_argument(|ch| select_text_object(ch, TextObjectScope::Inner)),
+    );
+    operator_pending_keymaps.insert(
+        "a",
+        Keymap::with_argument(|ch| select_text_object(ch, TextObjectScope::Around)),
+    );
+    operator_pending_keymaps.insert("/", Keymap::new(vec![search_forward]));
+    operator_pending_keymaps.insert("?", Keymap::new(vec![search_backward]));
+    operator_pending_keymaps.insert("v", Keymap::force_motion(MotionType::Charwise));
+    operator_pending_keymaps.insert("V", Keymap::force_motion(MotionType::Linewise));
+    operator_pending_keymaps.insert("<c-v>", Keymap::force_motion(MotionType::Blockwise));
 
     // insert mode keymaps
-    insert_keymaps.insert("<esc>", Keymap::new(insert, vec![enter_normal_mode]));
+    insert_keymaps.insert("<esc>", Keymap::new(vec![enter_normal_mode.clone()]));
 
-    insert_keymaps.insert("<c-right>", Keymap::new(insert, vec![move_to_next_word]));
-    insert_keymaps.insert("<left>", Keymap::new(insert, vec![move_cursor_left]));
-    insert_keymaps.insert("<down>", Keymap::new(insert, vec![move_cursor_down]));
-    insert_keymaps.insert("<up>", Keymap::new(insert, vec![move_cursor_up]));
-    insert_keymaps.insert("<right>", Keymap::new(insert, vec![move_cursor_right]));
-    insert_keymaps.insert("<c-c>", Keymap::new(insert, vec![enter_normal_mode]));
+    insert_keymaps.insert("<c-right>", Keymap::new(vec![move_to_next_word]));
+    insert_keymaps.insert("<c-left>", Keymap::new(vec![move_to_prev_word]));
+    insert_keymaps.insert("<left>", Keymap::new(vec![move_cursor_left]));
+    insert_keymaps.insert("<down>", Keymap::new(vec![move_cursor_down]));
+    insert_keymaps.insert("<up>", Keymap::new(vec![move_cursor_up]));
+    insert_keymaps.insert("<right>", Keymap::new(vec![move_cursor_right]));
+    insert_keymaps.insert("<c-c>", Keymap::new(vec![enter_normal_mode]));
+    insert_keymaps.insert(
+        "<c-r>",
+        Keymap::with_argument(|ch| vec![CommandWrapper::General(Command::InsertRegister(ch))]),
+    );
 
     LoadedKeymaps {
         normal: normal_keymaps,
+        operator_pending: operator_pending_keymaps,
         insert: insert_keymaps,
     }
 }
@@ -1,25 +1,53 @@
-use glyph_core::event_loop::event::{KeyCode, KeyEvent};
+use glyph_core::event_loop::event::{KeyCode, KeyEvent, KeyModifiers};
 use glyph_core::key_mapper::Command;
+use glyph_core::register::Registers;
 
-use crate::key_mapper::{CommandWrapper, VimCommand};
+use crate::key_mapper::{CommandWrapper, VimCommand, key_char};
 
 #[derive(Debug)]
 pub struct CommandModeKeymapper {
     command: String,
+    /// Whether `<c-r>` was typed and the name of the register to type comes next.
+    awaiting_register: bool,
 }
 
 impl CommandModeKeymapper {
     pub fn new() -> Self {
         Self {
             command: String::new(),
+            awaiting_register: false,
         }
     }
 
-    pub fn dock_height(&self) -> u16 {
-        if self.command.is_empty() { 0 } else { 1 }
+    /// Starts the command line with the range of `count` lines from the cursor, as vim does when
+    /// `:` is typed after a count.
+    pub fn start_with_count(&mut self, count: usize) {
+        self.command = match count {
+            1 => ".".to_string(),
+            count => format!(".,.+{}", count - 1),
+        };
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) -> Vec<CommandWrapper> {
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, registers: &Registers) -> Vec<CommandWrapper> {
+        // the line break of whole lines would end the command, so it's left out
+        if self.awaiting_register {
+            self.awaiting_register = false;
+            if let Some(register) = key_char(&key).and_then(|name| registers.get(name)) {
+                self.command
+                    .push_str(register.text.strip_suffix('\n').unwrap_or(&register.text));
+            }
+            return vec![];
+        }
+
+        if key.code == KeyCode::Char('r') && key.modifiers == KeyModifiers::CONTROL {
+            self.awaiting_register = true;
+            return vec![];
+        }
+
         match key.code {
             KeyCode::Char(c) => {
                 self.command.push(c);
@@ -48,7 +76,10 @@ impl CommandModeKeymapper {
             KeyCode::Insert => vec![],
             KeyCode::F(_) => vec![],
             KeyCode::Null => vec![],
-            KeyCode::Esc => vec![],
+            KeyCode::Esc => {
+                self.command.clear();
+                vec![CommandWrapper::Vim(VimCommand::NormalMode)]
+            }
             KeyCode::CapsLock => vec![],
             KeyCode::ScrollLock => vec![],
             KeyCode::NumLock => vec![],
@@ -62,24 +93,226 @@ impl CommandModeKeymapper {
     }
 
     fn handle_command(&mut self) -> Vec<CommandWrapper> {
-        if self.command.is_empty() {
-            return vec![];
+        let command = std::mem::take(&mut self.command);
+        let mut commands = vec![CommandWrapper::Vim(VimCommand::NormalMode)];
+        commands.extend(parse_command(&command));
+        commands
+    }
+}
+
+fn parse_command(command: &str) -> Vec<CommandWrapper> {
+    if let Some(commands) = parse_substitute(command) {
+        return commands;
+    }
+
+    let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+    if let "se" | "set" = name {
+        return parse_set(args);
+    }
+
+    if let Some(commands) = parse_buffer_command(name, args.trim()) {
+        return commands;
+    }
+
+    match command {
+        "q" => vec![CommandWrapper::General(Command::Quit)],
+        "quit" => vec![CommandWrapper::General(Command::Quit)],
+        "qa" => vec![CommandWrapper::General(Command::Quit)],
+        "qall" => vec![CommandWrapper::General(Command::Quit)],
+        "q!" => vec![CommandWrapper::General(Command::ForceQuit)],
+        "quit!" => vec![CommandWrapper::General(Command::ForceQuit)],
+        "qa!" => vec![CommandWrapper::General(Command::ForceQuit)],
+        "qall!" => vec![CommandWrapper::General(Command::ForceQuit)],
+        "w" => vec![CommandWrapper::General(Command::Save)],
+        "write" => vec![CommandWrapper::General(Command::Save)],
+        "w!" => vec![CommandWrapper::General(Command::ForceSave)],
+        "write!" => vec![CommandWrapper::General(Command::ForceSave)],
+        "wa" => vec![CommandWrapper::General(Command::SaveAll)],
+        "wall" => vec![CommandWrapper::General(Command::SaveAll)],
+        "up" => vec![CommandWrapper::General(Command::Update)],
+        "update" => vec![CommandWrapper::General(Command::Update)],
+        "wq" => vec![
+            CommandWrapper::General(Command::Save),
+            CommandWrapper::General(Command::Quit),
+        ],
+        "wq!" => vec![
+            CommandWrapper::General(Command::ForceSave),
+            CommandWrapper::General(Command::ForceQuit),
+        ],
+        "e" => vec![CommandWrapper::General(Command::Reload)],
+        "edit" => vec![CommandWrapper::General(Command::Reload)],
+        "e!" => vec![CommandWrapper::General(Command::ForceReload)],
+        "edit!" => vec![CommandWrapper::General(Command::ForceReload)],
+        "checkt" => vec![CommandWrapper::General(Command::CheckTime)],
+        "checktime" => vec![CommandWrapper::General(Command::CheckTime)],
+        "x" => vec![
+            CommandWrapper::General(Command::Update),
+            CommandWrapper::General(Command::Quit),
+        ],
+        "xit" => vec![
+            CommandWrapper::General(Command::Update),
+            CommandWrapper::General(Command::Quit),
+        ],
+        "xa" => vec![
+            CommandWrapper::General(Command::SaveAll),
+            CommandWrapper::General(Command::Quit),
+        ],
+        "xall" => vec![
+            CommandWrapper::General(Command::SaveAll),
+            CommandWrapper::General(Command::Quit),
+        ],
+        "u" => vec![CommandWrapper::General(Command::Undo)],
+        "undo" => vec![CommandWrapper::General(Command::Undo)],
+        "red" => vec![CommandWrapper::General(Command::Redo)],
+        "redo" => vec![CommandWrapper::General(Command::Redo)],
+        "ea" => vec![CommandWrapper::General(Command::Earlier)],
+        "earlier" => vec![CommandWrapper::General(Command::Earlier)],
+        "lat" => vec![CommandWrapper::General(Command::Later)],
+        "later" => vec![CommandWrapper::General(Command::Later)],
+        "reg" => vec![CommandWrapper::General(Command::ListRegisters)],
+        "registers" => vec![CommandWrapper::General(Command::ListRegisters)],
+        "di" => vec![CommandWrapper::General(Command::ListRegisters)],
+        "display" => vec![CommandWrapper::General(Command::ListRegisters)],
+        "wqa" => vec![
+            CommandWrapper::General(Command::SaveAll),
+            CommandWrapper::General(Command::Quit),
+        ],
+        "waq" => vec![
+            CommandWrapper::General(Command::SaveAll),
+            CommandWrapper::General(Command::Quit),
+        ],
+        _ => vec![],
+    }
+}
+
+/// Parses the commands that open, name, switch between and close buffers, `:b2` is the same as `:b 2`.
+fn parse_buffer_command(name: &str, args: &str) -> Option<Vec<CommandWrapper>> {
+    let (name, args) = match name.strip_prefix('b') {
+        Some(number) if !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) => {
+            ("b", number)
+        }
+        _ => (name, args),
+    };
+
+    let command = match name {
+        "e" | "edit" if !args.is_empty() => Command::Edit(args.to_string()),
+        "f" | "file" if !args.is_empty() => Command::Rename(args.to_string()),
+        "sav" | "saveas" | "sav!" | "saveas!" if !args.is_empty() => Command::SaveAs {
+            path: args.to_string(),
+            force: name.ends_with('!'),
+        },
+        "b" | "buffer" if !args.is_empty() => Command::SwitchBuffer(args.to_string()),
+        "bn" | "bnext" => Command::NextBuffer,
+        "bp" | "bprev" | "bprevious" | "bN" | "bNext" => Command::PrevBuffer,
+        "bd" | "bdelete" | "bd!" | "bdelete!" => Command::DeleteBuffer {
+            buffer: (!args.is_empty()).then(|| args.to_string()),
+            force: name.ends_with('!'),
+        },
+        "ls" | "buffers" | "files" => Command::ListBuffers,
+        "noh" | "nohl" | "nohlsearch" => Command::ClearSearchHighlight,
+        _ => return None,
+    };
+
+    Some(vec![CommandWrapper::General(command)])
+}
+
+/// Parses `:[range]s/pattern/replacement/flags`. Any punctuation can be used instead of `/`, and
+/// is escaped with a backslash inside the pattern and the replacement.
+fn parse_substitute(command: &str) -> Option<Vec<CommandWrapper>> {
+    let (range, rest) = command.split_at(range_len(command));
+    let rest = rest
+        .strip_prefix("substitute")
+        .or_else(|| rest.strip_prefix('s'))?;
+
+    let mut chars = rest.chars();
+    let delimiter = chars.next()?;
+    if delimiter.is_alphanumeric() || delimiter.is_whitespace() || "\\\"|".contains(delimiter) {
+        return None;
+    }
+
+    let mut parts = vec![String::new()];
+    let mut chars = chars.peekable();
+    while let Some(ch) = chars.next() {
+        // the flags are everything after the replacement
+        let is_flags = parts.len() == 3;
+        let part = parts.last_mut().expect("there is always a part");
+        match ch {
+            _ if is_flags => part.push(ch),
+            '\\' if chars.next_if_eq(&delimiter).is_some() => part.push(delimiter),
+            '\\' => {
+                part.push(ch);
+                part.extend(chars.next());
+            }
+            _ if ch == delimiter => parts.push(String::new()),
+            _ => part.push(ch),
+        }
+    }
+
+    let mut parts = parts.into_iter();
+    let command = Command::Substitute {
+        range: range.to_string(),
+        pattern: parts.next().unwrap_or_default(),
+        replacement: parts.next().unwrap_or_default(),
+        flags: parts.next().unwrap_or_default().trim().to_string(),
+    };
+
+    Some(vec![CommandWrapper::General(command)])
+}
+
+/// Length of the line range at the start of a command, like `%`, `.,$` or `'<,'>`.
+fn range_len(command: &str) -> usize {
+    let mut chars = command.char_indices();
+
+    while let Some((idx, ch)) = chars.next() {
+        match ch {
+            '\'' => {
+                chars.next();
+            }
+            '%' | '.' | '$' | ',' | ';' | '+' | '-' => {}
+            ch if ch.is_ascii_digit() => {}
+            _ => return idx,
         }
+    }
+
+    command.len()
+}
+
+/// Parses the arguments of `:set`, which are either `name=value` or just `name` for boolean
+/// options. A value can have spaces in it when they are escaped with a backslash.
+fn parse_set(args: &str) -> Vec<CommandWrapper> {
+    split_set_args(args)
+        .into_iter()
+        .map(|arg| {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            CommandWrapper::General(Command::SetOption { name, value })
+        })
+        .collect()
+}
 
-        match self.command.as_str() {
-            "q" => vec![CommandWrapper::General(Command::Quit)],
-            "quit" => vec![CommandWrapper::General(Command::Quit)],
-            "w" => vec![CommandWrapper::General(Command::Save)],
-            "write" => vec![CommandWrapper::General(Command::Save)],
-            "wq" => vec![
-                CommandWrapper::General(Command::Save),
-                CommandWrapper::General(Command::Quit),
-            ],
-            "waq" => vec![
-                CommandWrapper::General(Command::SaveAll),
-                CommandWrapper::General(Command::Quit),
-            ],
-            _ => vec![],
+fn split_set_args(args: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut chars = args.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => part.extend(chars.next()),
+            ch if ch.is_whitespace() => {
+                if !part.is_empty() {
+                    parts.push(std::mem::take(&mut part));
+                }
+            }
+            ch => part.push(ch),
         }
     }
+
+    if !part.is_empty() {
+        parts.push(part);
+    }
+
+    parts
 }
@@ -2,12 +2,16 @@ use glyph_core::event_loop::event::{KeyCode, KeyEvent};
 use glyph_core::key_mapper::Command;
 use glyph_trie::Trie;
 
-use crate::key_mapper::{CommandWrapper, Keymap, VimCommand};
+use crate::key_mapper::{
+    CommandWrapper, Keymap, KeymapAction, KeymapArgument, VimCommand, key_char,
+};
 
 #[derive(Debug)]
 pub struct InsertModeKeymapper {
     buffered_key: String,
     insert_keymaps: Trie<Keymap>,
+    /// The keymap waiting for its character argument, such as `<c-r>` before the register.
+    pending_argument: Option<KeymapArgument>,
 }
 
 impl InsertModeKeymapper {
@@ -15,10 +19,15 @@ impl InsertModeKeymapper {
         Self {
             buffered_key: String::new(),
             insert_keymaps,
+            pending_argument: None,
         }
     }
 
     pub fn handle_key(&mut self, key: KeyEvent) -> Vec<CommandWrapper> {
+        if let Some(argument) = self.pending_argument.take() {
+            return key_char(&key).map(argument).unwrap_or_default();
+        }
+
         let key_str = key.to_string();
         let full_key = format!("{}{}", self.buffered_key, key_str);
         let query = self.insert_keymaps.get(&full_key);
@@ -42,7 +51,15 @@ impl InsertModeKeymapper {
         }
 
         match query.value {
-            Some(keymap) => keymap.commands.clone(),
+            Some(keymap) => match &keymap.action {
+                KeymapAction::Commands(commands) => commands.clone(),
+                KeymapAction::Argument(argument) => {
+                    self.pending_argument = Some(*argument);
+                    vec![]
+                }
+                // operators only wait for a motion in normal mode
+                KeymapAction::Operator(_) | KeymapAction::ForceMotion(_) => vec![],
+            },
             None => match key.code {
                 KeyCode::Backspace => vec![CommandWrapper::General(Command::DeletePrevChar)],
                 KeyCode::Left => vec![CommandWrapper::General(Command::MoveCursorLeft)],
@@ -57,7 +74,7 @@ impl InsertModeKeymapper {
                 KeyCode::Char(c) => vec![CommandWrapper::General(Command::TypeChar(c))],
                 KeyCode::Esc => vec![CommandWrapper::Vim(VimCommand::InsertMode)],
                 KeyCode::Enter => vec![],
-                KeyCode::Tab => vec![],
+                KeyCode::Tab => vec![CommandWrapper::General(Command::InsertTab)],
                 KeyCode::BackTab => vec![],
                 KeyCode::Insert => vec![],
                 KeyCode::F(_) => vec![],
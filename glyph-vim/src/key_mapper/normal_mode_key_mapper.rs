@@ -1,40 +1,286 @@
 use glyph_core::event_loop::event::KeyEvent;
+use glyph_core::key_mapper::{Command, MotionType, Operator};
+use glyph_core::register::Registers;
 use glyph_trie::Trie;
 
-use super::Keymap;
-use crate::key_mapper::CommandWrapper;
+use super::{Keymap, KeymapAction, KeymapArgument, key_char};
+use crate::key_mapper::{CommandWrapper, VimCommand};
+
+/// The commands of a complete keymap, with the count and register typed for it.
+#[derive(Debug, Default)]
+pub struct CountedCommands {
+    pub commands: Vec<CommandWrapper>,
+    pub count: Option<usize>,
+    pub register: Option<char>,
+}
+
+/// An operator waiting for the motion or text object it applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingOperator {
+    pub operator: Operator,
+    /// The keys of the operator, typing them again applies it to whole lines, like `dd`.
+    keys: String,
+    /// Set by `v`, `V` or `<c-v>` typed before the motion.
+    pub force: Option<MotionType>,
+}
+
+impl PendingOperator {
+    /// Applies the operator to the range covered by `motion`.
+    pub fn apply(&self, motion: Command) -> Command {
+        Command::Operate {
+            operator: self.operator,
+            motion: Box::new(motion),
+            force: self.force,
+        }
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct NormalModeKeymapper {
     buffered_key: String,
     normal_keymaps: Trie<Keymap>,
+    operator_pending_keymaps: Trie<Keymap>,
+    pending_operator: Option<PendingOperator>,
+    /// The keymap waiting for its character argument, such as `f` before the character to find.
+    pending_argument: Option<KeymapArgument>,
+    /// The counts typed so far, multiplied together, like the `2` and `3` of `2d3w`.
+    count: Option<usize>,
+    /// The count whose digits are still being typed.
+    typed_count: Option<usize>,
+    /// The register typed after `"`, like the `a` of `"ayy`.
+    register: Option<char>,
+    /// Whether `"` was typed and the name of the register comes next.
+    awaiting_register: bool,
 }
 
 impl NormalModeKeymapper {
-    pub fn new(normal_keymaps: Trie<Keymap>) -> Self {
+    pub fn new(normal_keymaps: Trie<Keymap>, operator_pending_keymaps: Trie<Keymap>) -> Self {
         Self {
             normal_keymaps,
+            operator_pending_keymaps,
             buffered_key: String::new(),
+            pending_operator: None,
+            pending_argument: None,
+            count: None,
+            typed_count: None,
+            register: None,
+            awaiting_register: false,
         }
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) -> Vec<CommandWrapper> {
+    pub fn handle_key(&mut self, key: KeyEvent) -> CountedCommands {
+        // any key that isn't a character, such as escape, cancels the pending keymap
+        if let Some(argument) = self.pending_argument.take() {
+            let commands = key_char(&key).map(argument).unwrap_or_default();
+            return self.resolve(commands);
+        }
+
+        if self.awaiting_register {
+            self.awaiting_register = false;
+            return match key_char(&key).filter(|&name| Registers::is_valid(name)) {
+                Some(name) => {
+                    self.register = Some(name);
+                    CountedCommands::default()
+                }
+                None => self.resolve(vec![]),
+            };
+        }
+
         let key_str = key.to_string();
         let full_key = format!("{}{}", self.buffered_key, key_str);
-        let query = self.normal_keymaps.get(&full_key);
+        let keymaps = match self.pending_operator {
+            Some(_) => &self.operator_pending_keymaps,
+            None => &self.normal_keymaps,
+        };
+        let query = keymaps.get(&full_key);
+
+        // digits are a count unless they continue a keymap, and `0` only continues a count as it
+        // moves to the start of the line on its own
+        let digit = key_char(&key).and_then(|ch| ch.to_digit(10));
+        let continues_keymap =
+            !self.buffered_key.is_empty() && (query.continues || query.value.is_some());
+        if let Some(digit) = digit
+            && !continues_keymap
+            && (digit != 0 || self.typed_count.is_some())
+        {
+            let typed = self.typed_count.unwrap_or_default();
+            self.typed_count = Some(typed.saturating_mul(10).saturating_add(digit as usize));
+            return CountedCommands::default();
+        }
+
+        if let Some(typed) = self.typed_count.take() {
+            self.count = Some(self.count.unwrap_or(1).saturating_mul(typed));
+        }
+
+        // the register comes before the operator, like the count
+        if full_key == "\"" && self.pending_operator.is_none() {
+            self.awaiting_register = true;
+            return CountedCommands::default();
+        }
+
+        if self
+            .pending_operator
+            .as_ref()
+            .is_some_and(|pending| pending.keys == full_key)
+        {
+            self.buffered_key.clear();
+            return self.resolve(vec![CommandWrapper::General(Command::SelectLines)]);
+        }
 
         // if the key is just a part of a bigger keymap, buffer the key and do nothing
         if query.continues {
             self.buffered_key.push_str(&key_str);
-            return vec![];
+            return CountedCommands::default();
         }
 
         let Some(keymap) = query.value else {
             self.buffered_key.clear();
-            return vec![];
+            return self.resolve(vec![]);
         };
 
         self.buffered_key.clear();
-        keymap.commands.clone()
+        match &keymap.action {
+            KeymapAction::Commands(commands) => self.resolve(commands.clone()),
+            KeymapAction::Argument(argument) => {
+                self.pending_argument = Some(*argument);
+                CountedCommands::default()
+            }
+            KeymapAction::Operator(operator) => {
+                self.pending_operator = Some(PendingOperator {
+                    operator: *operator,
+                    keys: full_key,
+                    force: None,
+                });
+                CountedCommands::default()
+            }
+            KeymapAction::ForceMotion(motion_type) => {
+                if let Some(pending) = &mut self.pending_operator {
+                    pending.force = Some(*motion_type);
+                }
+                CountedCommands::default()
+            }
+        }
+    }
+
+    /// Whether an operator is waiting for its motion, so the cursor can show it.
+    pub fn is_operator_pending(&self) -> bool {
+        self.pending_operator.is_some()
+    }
+
+    /// Hands the pending operator over to a motion typed outside of normal mode, such as the
+    /// pattern of `d/foo`.
+    pub fn take_pending_operator(&mut self) -> Option<PendingOperator> {
+        self.pending_operator.take()
+    }
+
+    /// Ends the keymap being typed, handing its count over to its commands. A pending operator is
+    /// applied to the motion, or cancelled when there is none.
+    fn resolve(&mut self, commands: Vec<CommandWrapper>) -> CountedCommands {
+        let count = self.count.take();
+        let register = self.register.take();
+        let Some(pending) = self.pending_operator.take() else {
+            return CountedCommands {
+                commands,
+                count,
+                register,
+            };
+        };
+
+        // the motion is typed in search mode, which takes the operator over
+        if commands
+            .iter()
+            .any(|command| matches!(command, CommandWrapper::Vim(VimCommand::SearchMode(_))))
+        {
+            self.pending_operator = Some(pending);
+            return CountedCommands {
+                commands,
+                count,
+                register,
+            };
+        }
+
+        let Some(CommandWrapper::General(motion)) = commands.into_iter().next() else {
+            return CountedCommands::default();
+        };
+
+        let mut commands = vec![CommandWrapper::General(pending.apply(motion))];
+        if pending.operator == Operator::Change {
+            commands.push(CommandWrapper::Vim(VimCommand::InsertMode));
+        }
+
+        CountedCommands {
+            commands,
+            count,
+            register,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_mapper::load_vim_keymaps;
+    use crate::testing::parse_keys;
+
+    /// Types `keys`, returning what the last one resolved to.
+    fn type_keys(keys: &str) -> CountedCommands {
+        let keymaps = load_vim_keymaps();
+        let mut keymapper = NormalModeKeymapper::new(keymaps.normal, keymaps.operator_pending);
+
+        parse_keys(keys)
+            .into_iter()
+            .map(|key| keymapper.handle_key(key))
+            .last()
+            .unwrap()
+    }
+
+    fn general(command: Command) -> Vec<CommandWrapper> {
+        vec![CommandWrapper::General(command)]
+    }
+
+    #[test]
+    fn test_counts() {
+        let resolved = type_keys("10G");
+        assert_eq!(resolved.commands, general(Command::MoveToBottom));
+        assert_eq!(resolved.count, Some(10));
+
+        let resolved = type_keys("50%");
+        assert_eq!(resolved.commands, general(Command::MoveToMatchingPair));
+        assert_eq!(resolved.count, Some(50));
+
+        // the counts before the operator and before the motion multiply
+        let resolved = type_keys("2d3w");
+        let operate = Command::Operate {
+            operator: Operator::Delete,
+            motion: Box::new(Command::MoveToNextWord),
+            force: None,
+        };
+        assert_eq!(resolved.commands, general(operate));
+        assert_eq!(resolved.count, Some(6));
+
+        // a count is typed without resolving anything
+        let resolved = type_keys("10");
+        assert!(resolved.commands.is_empty());
+        assert_eq!(resolved.count, None);
+    }
+
+    #[test]
+    fn test_zero_is_a_motion_unless_it_continues_a_count() {
+        let resolved = type_keys("0");
+        assert_eq!(resolved.commands, general(Command::MoveCursorLineStart));
+        assert_eq!(resolved.count, None);
+
+        let resolved = type_keys("d0");
+        let operate = Command::Operate {
+            operator: Operator::Delete,
+            motion: Box::new(Command::MoveCursorLineStart),
+            force: None,
+        };
+        assert_eq!(resolved.commands, general(operate));
+        assert_eq!(resolved.count, None);
+
+        let resolved = type_keys("20j");
+        assert_eq!(resolved.commands, general(Command::MoveCursorDown));
+        assert_eq!(resolved.count, Some(20));
     }
 }
\ No newline at end of file
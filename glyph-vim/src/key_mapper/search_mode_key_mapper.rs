@@ -0,0 +1,116 @@
+use glyph_core::event_loop::event::{KeyCode, KeyEvent};
+use glyph_core::key_mapper::Command;
+use glyph_core::text_object::SearchDirection;
+
+use crate::key_mapper::{CommandWrapper, VimCommand};
+
+/// Reads the pattern typed after `/` or `?`.
+#[derive(Debug)]
+pub struct SearchModeKeymapper {
+    pattern: String,
+    direction: SearchDirection,
+    /// Patterns that were searched for, oldest first.
+    history: Vec<String>,
+    /// The entry of the history being shown, if the user is browsing it.
+    history_idx: Option<usize>,
+}
+
+impl SearchModeKeymapper {
+    pub fn new() -> Self {
+        Self {
+            pattern: String::new(),
+            direction: SearchDirection::Forward,
+            history: vec![],
+            history_idx: None,
+        }
+    }
+
+    /// Starts reading a new pattern.
+    pub fn start(&mut self, direction: SearchDirection) {
+        self.pattern.clear();
+        self.direction = direction;
+        self.history_idx = None;
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn direction(&self) -> SearchDirection {
+        self.direction
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Vec<CommandWrapper> {
+        match key.code {
+            KeyCode::Char(c) => {
+                self.pattern.push(c);
+                self.preview()
+            }
+            KeyCode::Backspace => {
+                if self.pattern.is_empty() {
+                    return self.cancel();
+                }
+
+                self.pattern.pop();
+                self.preview()
+            }
+            KeyCode::Enter => self.handle_search(),
+            KeyCode::Up => {
+                self.browse_history(true);
+                self.preview()
+            }
+            KeyCode::Down => {
+                self.browse_history(false);
+                self.preview()
+            }
+            KeyCode::Esc => self.cancel(),
+            _ => vec![],
+        }
+    }
+
+    fn preview(&self) -> Vec<CommandWrapper> {
+        vec![CommandWrapper::General(Command::PreviewSearch {
+            pattern: self.pattern.clone(),
+            direction: self.direction,
+        })]
+    }
+
+    fn cancel(&mut self) -> Vec<CommandWrapper> {
+        self.pattern.clear();
+        vec![
+            CommandWrapper::Vim(VimCommand::NormalMode),
+            CommandWrapper::General(Command::CancelSearch),
+        ]
+    }
+
+    fn handle_search(&mut self) -> Vec<CommandWrapper> {
+        let pattern = std::mem::take(&mut self.pattern);
+
+        if !pattern.is_empty() {
+            self.history.retain(|entry| *entry != pattern);
+            self.history.push(pattern.clone());
+        }
+
+        vec![
+            CommandWrapper::Vim(VimCommand::NormalMode),
+            CommandWrapper::General(Command::Search {
+                pattern,
+                direction: self.direction,
+            }),
+        ]
+    }
+
+    /// Shows an older entry of the history, or a newer one, going back to an empty pattern past
+    /// the newest entry.
+    fn browse_history(&mut self, older: bool) {
+        let idx = match (self.history_idx, older) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => None,
+            (Some(idx), true) => Some(idx.saturating_sub(1)),
+            (Some(idx), false) => Some(idx + 1).filter(|&idx| idx < self.history.len()),
+        };
+
+        self.history_idx = idx;
+        self.pattern = idx.map(|idx| self.history[idx].clone()).unwrap_or_default();
+    }
+}
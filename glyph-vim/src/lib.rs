@@ -1,12 +1,17 @@
 mod command_handler;
 mod key_mapper;
 mod statusline;
+#[cfg(test)]
+mod testing;
 
 use glyph_core::command_handler::CommandHandler;
+use glyph_core::cursor::CursorShape;
 use glyph_core::editing_plugin::EditingPlugin;
 use glyph_core::event_loop::Event;
-use glyph_core::key_mapper::{EditorMode, Keymapper, ResolvedKeymap, VimMode};
+use glyph_core::key_mapper::{Command, EditorMode, Keymapper, Operator, ResolvedKeymap, VimMode};
+use glyph_core::register::Registers;
 use glyph_core::status_provider::{StatuslineContext, StatuslineProvider};
+use glyph_core::text_object::SearchDirection;
 
 use crate::command_handler::VimBufferCommandHandler;
 use crate::key_mapper::*;
@@ -18,7 +23,14 @@ pub struct VimEditingPlugin {
     normal_mode_keymapper: NormalModeKeymapper,
     insert_mode_keymapper: InsertModeKeymapper,
     command_mode_keymapper: CommandModeKeymapper,
+    search_mode_keymapper: SearchModeKeymapper,
     mode: VimMode,
+    /// The count typed before `/` or `?`, which applies once the pattern is typed.
+    search_count: Option<usize>,
+    /// The register typed before `/` or `?`, which an operator applying to the search writes to.
+    search_register: Option<char>,
+    /// The operator typed before `/` or `?`, which applies to the search once the pattern is typed.
+    search_operator: Option<PendingOperator>,
 }
 
 impl Default for VimEditingPlugin {
@@ -34,39 +46,96 @@ impl VimEditingPlugin {
         Self {
             statusline: VimStatusline,
             mode: VimMode::Normal,
-            normal_mode_keymapper: NormalModeKeymapper::new(loaded_keymaps.normal),
+            normal_mode_keymapper: NormalModeKeymapper::new(
+                loaded_keymaps.normal,
+                loaded_keymaps.operator_pending,
+            ),
             insert_mode_keymapper: InsertModeKeymapper::new(loaded_keymaps.insert),
             command_mode_keymapper: CommandModeKeymapper::new(),
+            search_mode_keymapper: SearchModeKeymapper::new(),
+            search_count: None,
+            search_register: None,
+            search_operator: None,
         }
     }
 }
 
 impl Keymapper for VimEditingPlugin {
-    fn parse_event(&mut self, event: Option<Event>) -> Option<ResolvedKeymap> {
-        let Event::Key(key) = event?;
+    fn parse_event(
+        &mut self,
+        event: Option<Event>,
+        registers: &Registers,
+    ) -> Option<ResolvedKeymap> {
+        let Some(Event::Key(key)) = event else {
+            return None;
+        };
 
-        let commands = match self.mode {
-            VimMode::Normal => self.normal_mode_keymapper.handle_key(key),
-            VimMode::Insert => self.insert_mode_keymapper.handle_key(key),
-            VimMode::Command => self.command_mode_keymapper.handle_key(key),
+        let (commands, count, register) = match self.mode {
+            VimMode::Normal => {
+                let keymap = self.normal_mode_keymapper.handle_key(key);
+                (keymap.commands, keymap.count, keymap.register)
+            }
+            VimMode::Insert => (self.insert_mode_keymapper.handle_key(key), None, None),
+            VimMode::Command => (
+                self.command_mode_keymapper.handle_key(key, registers),
+                None,
+                None,
+            ),
+            VimMode::Search => (
+                self.search_mode_keymapper.handle_key(key),
+                self.search_count,
+                self.search_register,
+            ),
             VimMode::Visual => todo!(),
         };
 
         let mut general_commands = vec![];
         for cmd in commands {
             match cmd {
+                CommandWrapper::General(cmd @ Command::Search { .. }) => {
+                    match self.search_operator.take() {
+                        Some(pending) => {
+                            if pending.operator == Operator::Change {
+                                self.mode = VimMode::Insert;
+                            }
+                            general_commands.push(pending.apply(cmd));
+                        }
+                        None => general_commands.push(cmd),
+                    }
+                }
                 CommandWrapper::General(cmd) => general_commands.push(cmd),
                 CommandWrapper::Vim(cmd) => match cmd {
                     VimCommand::InsertMode => self.mode = VimMode::Insert,
                     VimCommand::NormalMode => self.mode = VimMode::Normal,
-                    VimCommand::CommandMode => self.mode = VimMode::Command,
+                    VimCommand::CommandMode => {
+                        // a count turns into a range of lines from the cursor, like `:.,.+2`
+                        if let Some(count) = count {
+                            self.command_mode_keymapper.start_with_count(count);
+                        }
+                        self.mode = VimMode::Command;
+                    }
+                    VimCommand::SearchMode(direction) => {
+                        self.search_mode_keymapper.start(direction);
+                        self.search_count = count;
+                        self.search_register = register;
+                        self.search_operator = self.normal_mode_keymapper.take_pending_operator();
+                        self.mode = VimMode::Search;
+                    }
                 },
             }
         }
 
+        if self.mode != VimMode::Search {
+            self.search_count = None;
+            self.search_register = None;
+            self.search_operator = None;
+        }
+
         Some(ResolvedKeymap {
             commands: general_commands,
             mode: Some(self.mode()),
+            count,
+            register,
         })
     }
 
@@ -83,10 +152,37 @@ impl StatuslineProvider for VimEditingPlugin {
 
 impl EditingPlugin for VimEditingPlugin {
     fn create_command_handler(&self) -> Box<dyn CommandHandler> {
-        Box::new(VimBufferCommandHandler)
+        Box::new(VimBufferCommandHandler::default())
     }
 
     fn dock_height(&self) -> u16 {
-        self.command_mode_keymapper.dock_height()
+        match self.mode {
+            VimMode::Command | VimMode::Search => 1,
+            _ => 0,
+        }
+    }
+
+    fn render_dock(&self) -> Option<String> {
+        match self.mode {
+            VimMode::Command => Some(format!(":{}", self.command_mode_keymapper.command())),
+            VimMode::Search => {
+                let prefix = match self.search_mode_keymapper.direction() {
+                    SearchDirection::Forward => '/',
+                    SearchDirection::Backward => '?',
+                };
+                Some(format!("{prefix}{}", self.search_mode_keymapper.pattern()))
+            }
+            _ => None,
+        }
+    }
+
+    fn cursor_shape(&self) -> CursorShape {
+        match self.mode {
+            VimMode::Insert => CursorShape::Bar,
+            VimMode::Normal if self.normal_mode_keymapper.is_operator_pending() => {
+                CursorShape::Underline
+            }
+            _ => CursorShape::Block,
+        }
     }
 }
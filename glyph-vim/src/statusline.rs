@@ -13,6 +13,13 @@ impl StatuslineProvider for VimStatusline {
             .map(|s| s.to_string_lossy().into_owned())
             .unwrap_or_default();
 
+        let modified = if ctx.buffer_info.is_dirty() { " [+]" } else { "" };
+        let read_only = if ctx.buffer_info.is_read_only() { " [RO]" } else { "" };
+        let loading = match ctx.buffer_info.loading_progress() {
+            Some(percent) => format!(" [loading {percent}%]"),
+            None => String::new(),
+        };
+
         let mode_str = format!("[ {mode} ]");
         let cursor_pos_str = format!(
             "{}:{}",
@@ -20,9 +27,20 @@ impl StatuslineProvider for VimStatusline {
             ctx.cursor_position.x + 1
         );
 
-        let left_side = format!(" {mode_str} {file_name}");
-        let right_side = format!("{cursor_pos_str} ");
-        let padding = ctx.width - left_side.len() - right_side.len();
+        let left_side = format!(" {mode_str} {file_name}{modified}{read_only}{loading}");
+        let file_format = ctx.buffer_info.line_ending();
+        let file_encoding = ctx.buffer_info.encoding();
+        let bom = if ctx.buffer_info.has_bom() { ",bom" } else { "" };
+        let search_count = match ctx.search_count {
+            Some(count) => format!("{count}  "),
+            None => String::new(),
+        };
+        let right_side =
+            format!("{search_count}{file_encoding}{bom}[{file_format}]  {cursor_pos_str} ");
+        let padding = ctx
+            .width
+            .saturating_sub(left_side.len())
+            .saturating_sub(right_side.len());
         let padding = " ".repeat(padding).to_string();
 
         format!("{left_side}{padding}{right_side}")
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use glyph_core::buffer_manager::{BufferId, BufferManager};
+use glyph_core::command_handler::{CommandContext, CommandHandler};
+use glyph_core::config::{ClipboardConfig, Config, KeyMapPreset, StatuslineConfig};
+use glyph_core::event_loop::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use glyph_core::geometry::Size;
+use glyph_core::key_mapper::Keymapper;
+use glyph_core::message::Message;
+use glyph_core::options::{DEFAULT_TABSTOP, EditorOptions};
+use glyph_core::prompt::Prompt;
+use glyph_core::register::Registers;
+use glyph_core::search::SearchState;
+use glyph_core::text_object::Transaction;
+use glyph_core::view_manager::ViewManager;
+
+use crate::VimEditingPlugin;
+use crate::command_handler::VimBufferCommandHandler;
+
+/// An editor with a single buffer, driven by typing keys through the vim keymaps the way the
+/// event loop does.
+pub struct TestEditor {
+    pub plugin: VimEditingPlugin,
+    pub handler: VimBufferCommandHandler,
+    pub buffers: BufferManager,
+    pub views: ViewManager,
+    pub options: EditorOptions,
+    pub search: SearchState,
+    pub registers: Registers,
+    pub message: Option<Message>,
+    prompt: Option<Prompt>,
+    should_quit: bool,
+}
+
+impl TestEditor {
+    pub fn new(text: &str) -> Self {
+        let config = Arc::new(Config {
+            keymap_preset: KeyMapPreset::Vim,
+            statusline: StatuslineConfig::default(),
+            autoread: false,
+            ignorecase: false,
+            smartcase: false,
+            tabstop: DEFAULT_TABSTOP,
+            expandtab: false,
+            shiftwidth: 0,
+            softtabstop: 0,
+            wrap: false,
+            linebreak: false,
+            breakindent: false,
+            showbreak: String::new(),
+            clipboard: ClipboardConfig::None,
+        });
+
+        let mut buffers = BufferManager::with_swap_dir(None);
+        let buffer_id = buffers.create_empty_buffer();
+        let buffer = buffers.get_mut(buffer_id).unwrap();
+        buffer
+            .content_mut()
+            .apply(Transaction::new().insert(0, text));
+        buffer.commit_changes(&[]);
+
+        Self {
+            plugin: VimEditingPlugin::new(),
+            handler: VimBufferCommandHandler::default(),
+            buffers,
+            views: ViewManager::new(config.clone(), BufferId::new(0), Size::new(80, 24)),
+            options: EditorOptions::from(config.as_ref()),
+            search: SearchState::default(),
+            registers: Registers::default(),
+            message: None,
+            prompt: None,
+            should_quit: false,
+        }
+    }
+
+    /// Types `keys`, written like vim keymaps with special keys such as `<esc>`, `<cr>` and
+    /// `<c-v>` between angle brackets.
+    pub fn keys(&mut self, keys: &str) -> &mut Self {
+        for key in parse_keys(keys) {
+            let Some(resolved_keymap) = self
+                .plugin
+                .parse_event(Some(Event::Key(key)), &self.registers)
+            else {
+                continue;
+            };
+
+            self.handler.handle_commands(&mut CommandContext {
+                resolved_keymap: &resolved_keymap,
+                buffers: &mut self.buffers,
+                views: &mut self.views,
+                should_quit: &mut self.should_quit,
+                message: &mut self.message,
+                options: &mut self.options,
+                search: &mut self.search,
+                registers: &mut self.registers,
+                prompt: &mut self.prompt,
+            });
+        }
+
+        self
+    }
+
+    pub fn text(&self) -> String {
+        let view = self.views.get_active_view();
+        let content = self.buffers.get(view.buffer_id).unwrap().content();
+        content.slice(0..content.len_chars()).to_string()
+    }
+
+    /// The cursor as `(x, y)`.
+    pub fn cursor(&self) -> (usize, usize) {
+        let cursor = self.views.get_active_view().cursors.first().unwrap();
+        (cursor.x, cursor.y)
+    }
+}
+
+/// The key events of `keys`, see [`TestEditor::keys`].
+pub fn parse_keys(keys: &str) -> Vec<KeyEvent> {
+    let key = |code, modifiers| KeyEvent {
+        code,
+        modifiers,
+        kind: KeyEventKind::Press,
+    };
+
+    let mut events = vec![];
+    let mut rest = keys;
+    while let Some(ch) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|tail| tail.split_once('>'))
+            .filter(|(name, _)| !name.is_empty());
+
+        let Some((name, tail)) = special else {
+            events.push(key(KeyCode::Char(ch), KeyModifiers::NONE));
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        };
+
+        events.push(match name {
+            "esc" => key(KeyCode::Esc, KeyModifiers::NONE),
+            "cr" => key(KeyCode::Enter, KeyModifiers::NONE),
+            "bs" => key(KeyCode::Backspace, KeyModifiers::NONE),
+            "lt" => key(KeyCode::Char('<'), KeyModifiers::NONE),
+            name => {
+                let ch = name.strip_prefix("c-").and_then(|ch| ch.chars().next());
+                key(
+                    KeyCode::Char(ch.expect("unknown key")),
+                    KeyModifiers::CONTROL,
+                )
+            }
+        });
+        rest = tail;
+    }
+
+    events
+}
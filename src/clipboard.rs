@@ -0,0 +1,48 @@
+use std::io::stdout;
+
+use crossterm::execute;
+use crossterm::style::Print;
+use glyph_core::clipboard::{
+    ClipboardKind, ClipboardProvider, CommandClipboardProvider, is_over_ssh, osc52_sequence,
+};
+use glyph_core::config::{ClipboardConfig, Config};
+use glyph_core::error::{GlyphError, Result};
+
+/// Copies through the terminal with OSC 52, which works over ssh but can't read the clipboard
+/// back.
+#[derive(Debug)]
+pub struct Osc52ClipboardProvider;
+
+impl ClipboardProvider for Osc52ClipboardProvider {
+    fn name(&self) -> &str {
+        "osc52"
+    }
+
+    fn set_contents(&self, kind: ClipboardKind, text: &str) -> Result<()> {
+        execute!(stdout(), Print(osc52_sequence(kind, text))).map_err(|e| {
+            GlyphError::ClipboardFailed {
+                provider: self.name().to_string(),
+                reason: e.to_string(),
+            }
+        })
+    }
+
+    fn get_contents(&self, _: ClipboardKind) -> Option<String> {
+        None
+    }
+}
+
+pub fn provider_from_config(config: &Config) -> Option<Box<dyn ClipboardProvider>> {
+    match config.clipboard {
+        ClipboardConfig::Auto => {
+            match CommandClipboardProvider::detect().filter(|_| !is_over_ssh()) {
+                Some(provider) => Some(Box::new(provider)),
+                None => Some(Box::new(Osc52ClipboardProvider)),
+            }
+        }
+        ClipboardConfig::Osc52 => Some(Box::new(Osc52ClipboardProvider)),
+        ClipboardConfig::WlClipboard => Some(Box::new(CommandClipboardProvider::wl_clipboard())),
+        ClipboardConfig::Xclip => Some(Box::new(CommandClipboardProvider::xclip())),
+        ClipboardConfig::None => None,
+    }
+}
@@ -28,19 +28,21 @@ impl EventLoop for CrosstermEventLoop {
         }
 
         match crossterm::event::read() {
-            Ok(event) => Ok(Some(event.into_event())),
+            Ok(event) => Ok(event.into_event()),
             Err(e) => Err(EventLoopError::FailedToReadEvent(e)),
         }
     }
 }
 
 impl IntoEvent for CTEvent {
-    type Target = Event;
+    type Target = Option<Event>;
 
     fn into_event(self) -> Self::Target {
         match self {
-            CTEvent::Key(event) => Event::Key(event.into_event()),
-            _ => unimplemented!(),
+            CTEvent::Key(event) => Some(Event::Key(event.into_event())),
+            CTEvent::FocusGained => Some(Event::FocusGained),
+            CTEvent::FocusLost => Some(Event::FocusLost),
+            _ => None,
         }
     }
 }
@@ -1,12 +1,13 @@
+mod clipboard;
 mod event_loop;
 mod renderer;
 
 use std::sync::Arc;
 
-use glyph_core::Glyph;
 use glyph_core::config::{Config, KeyMapPreset};
 use glyph_core::editing_plugin::EditingPlugin;
 use glyph_core::startup_options::StartupOptions;
+use glyph_core::{Glyph, buffer_manager};
 use glyph_vim::VimEditingPlugin;
 use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriterExt};
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
@@ -44,11 +45,37 @@ fn editing_plugin_from_config(config: &Config) -> Box<dyn EditingPlugin> {
     }
 }
 
+fn list_recoverable_files() -> eyre::Result<()> {
+    let swap_files = buffer_manager::recoverable_files()?;
+
+    if swap_files.is_empty() {
+        println!("No swap files found");
+        return Ok(());
+    }
+
+    println!("Swap files found:");
+    for info in swap_files {
+        let running = if info.is_running() { ", still running" } else { "" };
+        println!(
+            "    {} (pid {}{running})",
+            info.file_path.display(),
+            info.pid
+        );
+    }
+    println!("Open a file with -r to recover it");
+
+    Ok(())
+}
+
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
 
     let startup_options = StartupOptions::from_args();
 
+    if startup_options.recover && startup_options.files.is_empty() {
+        return list_recoverable_files();
+    }
+
     setup_tracing(startup_options.verbose)?;
 
     let config = Arc::new(glyph_config::load()?);
@@ -56,14 +83,21 @@ fn main() -> eyre::Result<()> {
     let renderer = renderer::CrosstermRenderer::new(config.clone())?;
     let editing_plugin = editing_plugin_from_config(&config);
 
-    Glyph::new(
+    let clipboard = clipboard::provider_from_config(&config);
+
+    let mut glyph = Glyph::new(
         config,
         event_loop,
         renderer,
         editing_plugin,
         startup_options,
-    )?
-    .run()?;
+    )?;
+
+    if let Some(clipboard) = clipboard {
+        glyph.set_clipboard_provider(clipboard);
+    }
+
+    glyph.run()?;
 
     Ok(())
 }
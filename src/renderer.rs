@@ -1,15 +1,19 @@
 #![allow(dead_code)]
 
+use std::borrow::Cow;
 use std::io::{Write, stdout};
 use std::sync::Arc;
 
 use crossterm::style::{Attribute, Color, Print, SetAttribute};
 use crossterm::{cursor, queue};
 use glyph_core::config::{Config, StatuslineMode};
+use glyph_core::cursor::CursorShape;
 use glyph_core::geometry::{Point, Rect, Size};
+use glyph_core::message::MessageKind;
 use glyph_core::renderer::error::{RendererError, Result};
 use glyph_core::renderer::{RenderContext, Renderer};
 use glyph_core::status_provider::StatuslineContext;
+use glyph_core::text_object::line_graphemes;
 use glyph_core::view_manager::{LayoutTreeNode, LeafView};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -89,15 +93,28 @@ impl Style {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Cell {
-    pub char: char,
+    /// The grapheme cluster printed in the cell. It's empty for the cells covered by a wide
+    /// character printed to their left.
+    pub symbol: String,
     pub style: Style,
 }
 
 impl Cell {
-    pub fn new(char: char, style: Style) -> Self {
-        Self { char, style }
+    pub fn new(symbol: &str, style: Style) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            style,
+        }
+    }
+
+    /// A cell covered by the wide character to its left, which prints nothing.
+    pub fn continuation(style: Style) -> Self {
+        Self {
+            symbol: String::new(),
+            style,
+        }
     }
 }
 
@@ -130,7 +147,7 @@ impl CellBuffer {
             let y = i / size.width as usize;
 
             if c != &other.0[i] {
-                changes.push(Change::new(*c, Point::new(x as u16, y as u16)));
+                changes.push(Change::new(c.clone(), Point::new(x as u16, y as u16)));
             }
         }
 
@@ -148,6 +165,9 @@ pub struct CrosstermRenderer {
     size: Size,
     config: Arc<Config>,
     buffers: [CellBuffer; 2],
+    dock_height: u16,
+    /// The shape last sent to the terminal, which keeps it until told otherwise.
+    cursor_shape: Option<CursorShape>,
 }
 
 impl CrosstermRenderer {
@@ -156,6 +176,8 @@ impl CrosstermRenderer {
             config,
             size: Size::default(),
             buffers: [CellBuffer::default(), CellBuffer::default()],
+            dock_height: 0,
+            cursor_shape: None,
         };
 
         renderer.resize(renderer.get_size(0)?)?;
@@ -188,23 +210,107 @@ impl CrosstermRenderer {
         let view = visible_views.iter().find(|v| v.id == leaf.view_id).unwrap();
         let buffer = ctx.buffers.iter().find(|b| b.id == view.buffer_id).unwrap();
         let content = buffer.content();
+        let height = leaf.usable_rect.height as usize;
 
-        for y in 0..leaf.usable_rect.height as usize {
-            let line = content.get_line(y + view.scroll_offset.y);
-
-            for x in 0..leaf.usable_rect.width as usize {
-                let char = line
-                    .and_then(|line| line.get_char(x + view.scroll_offset.x))
-                    .unwrap_or(' ');
+        // large files aren't highlighted, searching them on every frame would be too slow
+        let matches = match ctx.search_highlight.filter(|_| !buffer.is_large()) {
+            Some(pattern) => {
+                let first_line = view.scroll_offset.y;
+                content.find_in_lines(pattern, first_line..first_line + height)
+            }
+            None => vec![],
+        };
+        // the match the cursor is on stands out from the others, but only in the active view
+        let current_match = (view.id == ctx.views.get_active_view_id()).then(|| {
+            let cursor = view.cursors.first().unwrap();
+            content.point_to_char(Point::new(cursor.x, cursor.y))
+        });
 
-                let char = match char {
-                    '\n' => ' ',
-                    '\t' => ' ',
-                    '\r' => ' ',
-                    _ => char,
-                };
+        let width = leaf.usable_rect.width as usize;
+        let size = Size::new(leaf.usable_rect.width, leaf.usable_rect.height);
+        let rows = view.visible_rows(buffer, size);
+        let scroll_x = match view.options.wrap {
+            true => 0,
+            false => view.scroll_offset.x,
+        };
+        let showbreak =
+            line_graphemes(&view.options.showbreak, buffer.tabstop()).collect::<Vec<_>>();
+        let showbreak_width = showbreak.iter().map(|g| g.width).sum::<usize>();
+
+        let mut screen_rows = vec![vec![Cell::new(" ", Style::default()); width]; height];
+        let mut y = 0;
+
+        for line_rows in rows.chunk_by(|(a, _), (b, _)| a == b) {
+            let line_idx = line_rows[0].0;
+            let line_start = content.point_to_char(Point::new(0, line_idx));
+            let line = Cow::<str>::from(content.line(line_idx));
+            // the rows of a line come in order, so its graphemes are walked once for all of them
+            let mut graphemes = line_graphemes(&line, buffer.tabstop()).peekable();
+
+            for &(_, display_row) in line_rows {
+                let row = &mut screen_rows[y];
+                y += 1;
+
+                if display_row.is_wrapped() {
+                    let showbreak_x = display_row.indent.saturating_sub(showbreak_width);
+                    let style = Style::new().with_fg(Color::DarkGrey);
+                    for grapheme in showbreak.iter() {
+                        if let Some(cell) = row.get_mut(showbreak_x + grapheme.column) {
+                            *cell = Cell::new(grapheme.symbol(), style);
+                        }
+                    }
+                }
+
+                while graphemes
+                    .next_if(|grapheme| grapheme.x < display_row.start)
+                    .is_some()
+                {}
+
+                while let Some(grapheme) =
+                    graphemes.next_if(|grapheme| grapheme.x < display_row.end)
+                {
+                    let start = display_row.screen_x(grapheme.column);
+                    if start + grapheme.width <= scroll_x {
+                        continue;
+                    }
+                    if start >= scroll_x + width {
+                        break;
+                    }
+
+                    let char_idx = line_start + grapheme.x;
+                    let search_match = matches.iter().find(|m| m.contains(&char_idx));
+                    let style = match search_match {
+                        Some(m) if Some(m.start) == current_match => {
+                            Style::new().with_fg(Color::Black).with_bg(Color::Magenta)
+                        }
+                        Some(_) => Style::new().with_fg(Color::Black).with_bg(Color::Yellow),
+                        None => Style::default(),
+                    };
+
+                    // a wide character cut by the edges of the view is drawn as blanks, as the
+                    // terminal can't draw half of it, and so is every column of a tab
+                    let columns = start..start + grapheme.width;
+                    let is_clipped = columns.start < scroll_x || columns.end > scroll_x + width;
+                    let symbol = grapheme.symbol();
+                    let is_blank = is_clipped || symbol == " ";
+                    for column in columns.clone() {
+                        let Some(cell) = column.checked_sub(scroll_x).and_then(|x| row.get_mut(x))
+                        else {
+                            continue;
+                        };
+
+                        *cell = match (is_blank, column == columns.start) {
+                            (true, _) => Cell::new(" ", style),
+                            (false, true) => Cell::new(symbol, style),
+                            (false, false) => Cell::continuation(style),
+                        };
+                    }
+                }
+            }
+        }
 
-                let cell = Cell::new(char, Style::default());
+        for (y, row) in screen_rows.into_iter().enumerate() {
+            for (x, cell) in row.into_iter().enumerate() {
                 let screen_x = rect.x + leaf.usable_rect.x + x as u16;
                 let screen_y = rect.y + leaf.usable_rect.y + y as u16;
                 cell_buffer.set_cell(screen_x, screen_y, cell, self.size);
@@ -242,6 +348,7 @@ impl CrosstermRenderer {
             cursor_position,
             current_mode: ctx.mode,
             width: rect.width as usize,
+            search_count: ctx.search_count,
         });
 
         let y = rect.bottom();
@@ -253,7 +360,57 @@ impl CrosstermRenderer {
         );
     }
 
+    fn render_dock(&self, ctx: &RenderContext<'_>, editor_rect: Rect) {
+        let (content, color) = match (ctx.editing_plugin.render_dock(), ctx.message) {
+            // the user has to answer a prompt before doing anything else
+            _ if ctx.prompt.is_some() => {
+                let prompt = ctx.prompt.expect("checked above");
+                (prompt.to_string(), Color::Yellow)
+            }
+            (Some(content), _) => (content, Color::Reset),
+            (None, Some(message)) => {
+                let color = match message.kind {
+                    MessageKind::Info => Color::Reset,
+                    MessageKind::Error => Color::Red,
+                };
+                (message.text.clone(), color)
+            }
+            (None, None) => return,
+        };
+
+        let width = self.size.width as usize;
+
+        // the statusline sits right below the editor area, and the dock right below it
+        let y = editor_rect.bottom() + 1;
+
+        for (offset, line) in content.lines().take(self.dock_height as usize).enumerate() {
+            let line = line.chars().take(width).collect::<String>();
+            let padding = " ".repeat(width.saturating_sub(line.chars().count()));
+
+            _ = crossterm::queue!(
+                stdout(),
+                crossterm::cursor::MoveTo(0, y + offset as u16),
+                crossterm::style::SetForegroundColor(color),
+                Print(line),
+                Print(padding),
+                crossterm::style::SetForegroundColor(Color::Reset),
+            );
+        }
+    }
+
+    /// Makes the next frame redraw every cell, for when something drawn outside of the cell
+    /// buffers, like the statusline, moved and left stale content behind.
+    fn invalidate(&mut self) {
+        let cells = vec![Cell::default(); self.size.width as usize * self.size.height as usize];
+        self.buffers[1] = CellBuffer(cells);
+    }
+
     fn queue_change(&mut self, x: u16, y: u16, change: Change) -> Result<()> {
+        // the wide character to the left already covers continuation cells
+        if change.cell.symbol.is_empty() {
+            return Ok(());
+        }
+
         let mut stdout = stdout();
 
         if change.cell.style.bold {
@@ -273,7 +430,7 @@ impl CrosstermRenderer {
             crossterm::cursor::MoveTo(x, y),
             crossterm::style::SetForegroundColor(change.cell.style.fg),
             crossterm::style::SetBackgroundColor(change.cell.style.bg),
-            Print(change.cell.char)
+            Print(change.cell.symbol)
         );
 
         Ok(())
@@ -282,18 +439,62 @@ impl CrosstermRenderer {
     fn position_cursor(&self, ctx: &mut RenderContext<'_>) {
         let view = ctx.views.get_active_view();
         let cursor = view.cursors.first().unwrap();
-        let cursor_x = cursor.x - view.scroll_offset.x;
-        let cursor_y = cursor.y - view.scroll_offset.y;
+        let buffer = ctx.buffers.iter().find(|b| b.id == view.buffer_id).unwrap();
+        let leaf = ctx.views.get_layout_for_view(view.id);
+        let size = Size::new(leaf.usable_rect.width, leaf.usable_rect.height);
+        let column = buffer.display_column(Point::new(cursor.x, cursor.y));
+
+        let rows = view.visible_rows(buffer, size);
+        let Some(cursor_y) = rows
+            .iter()
+            .rposition(|(line_idx, row)| *line_idx == cursor.y && row.start <= cursor.x)
+        else {
+            return;
+        };
+
+        let scroll_x = match view.options.wrap {
+            true => 0,
+            false => view.scroll_offset.x,
+        };
+        let cursor_x = rows[cursor_y].1.screen_x(column).saturating_sub(scroll_x);
+        let cursor_x = cursor_x.min(size.width.saturating_sub(1) as usize);
         _ = queue!(stdout(), cursor::MoveTo(cursor_x as u16, cursor_y as u16));
     }
+
+    fn set_cursor_shape(&mut self, shape: CursorShape) {
+        if self.cursor_shape == Some(shape) {
+            return;
+        }
+
+        let style = match shape {
+            CursorShape::Block => cursor::SetCursorStyle::SteadyBlock,
+            CursorShape::Bar => cursor::SetCursorStyle::SteadyBar,
+            CursorShape::Underline => cursor::SetCursorStyle::SteadyUnderScore,
+        };
+        _ = queue!(stdout(), style);
+        self.cursor_shape = Some(shape);
+    }
 }
 
 impl Renderer for CrosstermRenderer {
     fn render(&mut self, ctx: &mut RenderContext<'_>) -> Result<()> {
         _ = queue!(stdout(), cursor::Hide);
 
+        // long messages, like the buffer list, can take up to half of the screen
+        let message_height = match (ctx.prompt, ctx.message) {
+            (Some(_), _) => 1,
+            (None, Some(message)) => message.text.lines().count().max(1) as u16,
+            (None, None) => 0,
+        };
+        let message_height = u16::min(message_height, self.size.height / 2);
+        let dock_height = u16::max(ctx.editing_plugin.dock_height(), message_height);
+        if dock_height != self.dock_height {
+            self.dock_height = dock_height;
+            self.invalidate();
+        }
+
         let mut editor_rect = Rect::with_size(0, 0, self.size);
-        editor_rect.cut_bottom(ctx.editing_plugin.dock_height());
+        editor_rect.cut_bottom(dock_height);
 
         self.render_layout_node(ctx, ctx.layout, editor_rect);
 
@@ -305,7 +506,9 @@ impl Renderer for CrosstermRenderer {
         }
 
         self.maybe_render_global_statusline(ctx, editor_rect);
+        self.render_dock(ctx, editor_rect);
         self.position_cursor(ctx);
+        self.set_cursor_shape(ctx.editing_plugin.cursor_shape());
 
         _ = queue!(stdout(), cursor::Show);
 
@@ -323,7 +526,13 @@ impl Renderer for CrosstermRenderer {
             return Err(RendererError::FailedToSetupRenderer);
         }
 
-        if crossterm::execute!(stdout(), crossterm::terminal::EnterAlternateScreen).is_err() {
+        if crossterm::execute!(
+            stdout(),
+            crossterm::terminal::EnterAlternateScreen,
+            crossterm::event::EnableFocusChange
+        )
+        .is_err()
+        {
             _ = crossterm::terminal::disable_raw_mode();
             return Err(RendererError::FailedToSetupRenderer);
         }
@@ -332,7 +541,14 @@ impl Renderer for CrosstermRenderer {
     }
 
     fn shutdown(&self) -> Result<()> {
-        if crossterm::execute!(stdout(), crossterm::terminal::LeaveAlternateScreen).is_err() {
+        if crossterm::execute!(
+            stdout(),
+            crossterm::event::DisableFocusChange,
+            cursor::SetCursorStyle::DefaultUserShape,
+            crossterm::terminal::LeaveAlternateScreen
+        )
+        .is_err()
+        {
             return Err(RendererError::FailedToShutdownRenderer);
         }
 